@@ -0,0 +1,128 @@
+//! Sharing and access checks over synthetic forests of page chains.
+//!
+//! Every forest has [`CHAINS`] linear chains of the given length and [`AGREEMENTS`] granted agreements, each sharing
+//! the genesis of every chain to its own receiver. The agreements are restored after the pages, so every share is
+//! applied to chains already in the state.
+
+use contract_interpreter::{AccessAgreement, Contract};
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use guardian::{
+    snapshot::{Snapshot, SnapshotNode},
+    GuardianState,
+};
+use guardian_common::custom_types::*;
+use local_storage::prelude::*;
+
+const CHAINS: u32 = 10;
+const AGREEMENTS: u32 = 8;
+const LENGTHS: [u32; 3] = [10, 100, 1000];
+
+const SENDER: ethaddr::Address = ethaddr::Address([1; 20]);
+
+fn hash(n: u32) -> Hash {
+    let mut bytes = [0; 64];
+    bytes[..4].copy_from_slice(&n.to_be_bytes());
+    Hash::from(bytes)
+}
+
+fn receiver(agreement: u32) -> ethaddr::Address {
+    let mut bytes = [0xaa; 20];
+    bytes[..4].copy_from_slice(&agreement.to_be_bytes());
+    ethaddr::Address(bytes)
+}
+
+/// the hash of the `revision`th revision of `chain`
+fn page(chain: u32, revision: u32) -> u32 {
+    chain * 1_000_000 + revision + 1
+}
+
+fn node(hash_n: u32, prev: Option<u32>, contract: Option<(Contract, u8)>) -> SnapshotNode {
+    SnapshotNode {
+        hash: hash(hash_n),
+        prev: prev.map(hash),
+        merge: None,
+        page: None,
+        seqno: contract.as_ref().map(|(_, seqno)| *seqno),
+        contract: contract.map(|(contract, _)| contract),
+    }
+}
+
+fn forest(length: u32) -> Snapshot<u32> {
+    let mut nodes = vec![];
+    for chain in 0..CHAINS {
+        nodes.push(node(page(chain, 0), None, None));
+        for revision in 1..length {
+            nodes.push(node(
+                page(chain, revision),
+                Some(page(chain, revision - 1)),
+                None,
+            ));
+        }
+    }
+    for agreement in 0..AGREEMENTS {
+        let contract = Contract::AccessAgreement(AccessAgreement {
+            sender: SENDER,
+            receiver: receiver(agreement).into(),
+            pages: (0..CHAINS)
+                .map(|chain| (format!("Page_{chain}"), hash(page(chain, 0))))
+                .collect(),
+            page_pattern: None,
+            terms: None,
+            valid_from: None,
+            valid_until: None,
+            may_reshare: false,
+            template_version: 2,
+        });
+        let genesis = u32::MAX - 2 * agreement;
+        nodes.push(node(genesis, None, Some((contract.clone(), 0))));
+        nodes.push(node(genesis - 1, Some(genesis), Some((contract, 1))));
+    }
+    Snapshot {
+        version: guardian::snapshot::VERSION,
+        taken_at: chrono::Utc::now().naive_utc(),
+        cursor: 0,
+        nodes,
+    }
+}
+
+fn restore(snapshot: Snapshot<u32>) -> GuardianState<MemoryStorage> {
+    GuardianState::restore(MemoryStorage::new(), snapshot).0
+}
+
+fn share(c: &mut Criterion) {
+    let mut group = c.benchmark_group("restore");
+    group.sample_size(10);
+    for length in LENGTHS {
+        let snapshot = forest(length);
+        group.bench_with_input(BenchmarkId::from_parameter(length), &snapshot, |b, s| {
+            b.iter(|| restore(s.clone()))
+        });
+    }
+    group.finish();
+}
+
+fn check(c: &mut Criterion) {
+    let mut group = c.benchmark_group("get_rev_accessible");
+    for length in LENGTHS {
+        let state = restore(forest(length));
+        let tip = hash(page(CHAINS - 1, length - 1));
+        group.bench_with_input(BenchmarkId::from_parameter(length), &tip, |b, tip| {
+            b.iter(|| state.get_rev_accessible(receiver(AGREEMENTS - 1), *tip, SENDER))
+        });
+    }
+    group.finish();
+}
+
+fn latests(c: &mut Criterion) {
+    let mut group = c.benchmark_group("get_accessible_latests");
+    for length in LENGTHS {
+        let state = restore(forest(length));
+        group.bench_with_input(BenchmarkId::from_parameter(length), &state, |b, state| {
+            b.iter(|| state.get_accessible_latests(receiver(0), SENDER))
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, share, check, latests);
+criterion_main!(benches);
@@ -1,15 +1,127 @@
 use super::*;
+use guardian_common::custom_types::Timestamp;
 
 /// Data Access Agreement
-#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+#[derive(
+    Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Debug, serde::Serialize, serde::Deserialize,
+)]
 pub struct AccessAgreement {
     pub sender: Address,
-    pub receiver: Address,
+    /// a group cannot sign, agreements with a group only take effect without terms
+    pub receiver: Principal,
     pub pages: Vec<(String, Hash)>,
+    /// shares every page matching it as well, the ones created later included
+    #[serde(default)]
+    pub page_pattern: Option<PagePattern>,
     pub terms: Option<String>,
+    /// no access before this time
+    #[serde(default)]
+    pub valid_from: Option<chrono::NaiveDateTime>,
+    /// no access from this time on
+    #[serde(default)]
+    pub valid_until: Option<chrono::NaiveDateTime>,
+    /// the receiver may share the pages onward, by agreements they send themselves
+    #[serde(default)]
+    pub may_reshare: bool,
+    /// the version of the template it was parsed with, 1 listed the pages as `files=`
+    pub template_version: u32,
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+impl AccessAgreement {
+    /// whether `time` lies within [`valid_from`](Self::valid_from) and [`valid_until`](Self::valid_until)
+    pub fn is_valid_at(&self, time: chrono::NaiveDateTime) -> bool {
+        !matches!(self.valid_from, Some(from) if time < from) && !self.is_expired_at(time)
+    }
+    /// whether the agreement ended before `time`, it will never be valid again
+    pub fn is_expired_at(&self, time: chrono::NaiveDateTime) -> bool {
+        self.valid_until.is_some_and(|until| until <= time)
+    }
+}
+
+/// the receiver of an [`AccessAgreement`], a single user or all members of a [`Group`]
+#[derive(
+    Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug, serde::Serialize, serde::Deserialize,
+)]
+#[serde(untagged)]
+pub enum Principal {
+    User(Address),
+    /// the genesis hash of the [`Group`]
+    Group(Hash),
+}
+
+impl From<Address> for Principal {
+    fn from(user: Address) -> Self {
+        Principal::User(user)
+    }
+}
+
+impl PartialEq<Address> for Principal {
+    fn eq(&self, user: &Address) -> bool {
+        matches!(self, Principal::User(addr) if addr == user)
+    }
+}
+
+impl std::fmt::Display for Principal {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Principal::User(user) => user.fmt(f),
+            Principal::Group(group) => group.fmt(f),
+        }
+    }
+}
+
+impl std::str::FromStr for Principal {
+    type Err = ethaddr::ParseAddressError;
+
+    /// an address, or the genesis hash of a group
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        s.parse()
+            .map(Principal::User)
+            .or_else(|e| s.parse().map(Principal::Group).map_err(|()| e))
+    }
+}
+
+/// pages of a namespace whose titles start with a prefix, written as `<namespace number>:<title prefix>`
+#[derive(
+    Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Debug, serde::Serialize, serde::Deserialize,
+)]
+pub struct PagePattern {
+    pub namespace: i32,
+    /// an empty prefix matches the whole namespace
+    pub prefix: String,
+}
+
+impl PagePattern {
+    /// whether the page `title` in `namespace` matches, spaces and underscores are the same in titles
+    pub fn matches(&self, namespace: i32, title: &str) -> bool {
+        namespace == self.namespace
+            && title
+                .replace(' ', "_")
+                .starts_with(&self.prefix.replace(' ', "_"))
+    }
+}
+
+impl std::fmt::Display for PagePattern {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}", self.namespace, self.prefix)
+    }
+}
+
+impl std::str::FromStr for PagePattern {
+    type Err = std::num::ParseIntError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (namespace, prefix) = s.split_once(':').unwrap_or((s, ""));
+        Ok(PagePattern {
+            namespace: namespace.trim().parse()?,
+            prefix: prefix.to_string(),
+        })
+    }
+}
+
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, serde::Serialize, serde::Deserialize,
+)]
 pub enum AccessAgreementEffects {
     /// no terms, signed by declaring user. this should share the file to the receiver
     Granted,
@@ -34,11 +146,40 @@ pub enum AccessAgreementError {
 
     #[error("pages missing")]
     PagesMissing,
-    #[error("page not transcluded")]
-    PageNotTranscluded,
+    #[error("page {0} not transcluded")]
+    PageNotTranscluded(String),
+    #[error("page_pattern malformatted {0}")]
+    PagePatternMalformatted(std::num::ParseIntError),
+
+    #[error("valid_from malformatted {0}")]
+    ValidFromMalformatted(chrono::ParseError),
+    #[error("valid_until malformatted {0}")]
+    ValidUntilMalformatted(chrono::ParseError),
+    #[error("may_reshare malformatted {0}")]
+    MayReshareMalformatted(std::str::ParseBoolError),
 
-    #[error("unknown options specified")]
-    AdditionalKeys,
+    #[error("unknown option {0} specified")]
+    AdditionalKeys(String),
+    #[error("unknown template version {0}")]
+    UnknownVersion(u32),
+}
+
+impl AccessAgreementError {
+    /// the parameter of the template the error is about
+    pub fn param(&self) -> Option<&str> {
+        use AccessAgreementError::*;
+        Some(match self {
+            SenderMissing | SenderMalformatted(_) => "sender",
+            ReceiverMissing | ReceiverMalformatted(_) => "receiver",
+            PagesMissing | PageNotTranscluded(_) => "pages",
+            PagePatternMalformatted(_) => "page_pattern",
+            ValidFromMalformatted(_) => "valid_from",
+            ValidUntilMalformatted(_) => "valid_until",
+            MayReshareMalformatted(_) => "may_reshare",
+            AdditionalKeys(key) => key,
+            UnknownVersion(_) => return None,
+        })
+    }
 }
 
 const DECLARATION: Option<u8> = Some(0);
@@ -102,6 +243,7 @@ impl TryFrom<GenericContractInfo<'_>> for AccessAgreement {
     /// Tries to generate a Data Access Agreement from the [`GenericContractInfo`].
     fn try_from(info: GenericContractInfo) -> Result<Self, Self::Error> {
         let GenericContractInfo {
+            version,
             transclusions,
             mut params,
             ..
@@ -121,35 +263,288 @@ impl TryFrom<GenericContractInfo<'_>> for AccessAgreement {
             .parse()
             .map_err(ReceiverMalformatted)?;
 
-        let pages = params
-            .remove("pages")
-            .ok_or(PagesMissing)?
-            .split(", ")
+        let page_pattern = params
+            .remove("page_pattern")
+            .map(|pattern| pattern.parse())
+            .transpose()
+            .map_err(PagePatternMalformatted)?;
+
+        // version 1 named the pages files and separated them by bare commas
+        let (key, separator) = match version {
+            1 => ("files", ","),
+            2 => ("pages", ", "),
+            version => return Err(UnknownVersion(version)),
+        };
+        // a pattern may take the place of the pages
+        let pages = match params.remove(key) {
+            Some(pages) => pages,
+            None if page_pattern.is_some() => String::new(),
+            None => return Err(PagesMissing),
+        };
+        let pages = pages
+            .split(separator)
+            .map(str::trim)
+            .filter(|name| !name.is_empty())
             .map(|name| {
                 // Replace of "Media:" to allow correct mapping of transcluded file title to its transclusion hash (MediaWiki limitation)
-                let tmp_name = name.replace(" ", "_").replace("Media:", ""); 
+                let tmp_name = name.replace(" ", "_").replace("Media:", "");
                 let name = tmp_name.as_str();
                 transclusions
                     .get(name)
                     .copied()
                     .map(|hash| (name.to_string(), hash))
+                    .ok_or_else(|| PageNotTranscluded(name.to_string()))
             })
-            .collect::<Option<_>>()
-            .ok_or(PageNotTranscluded)?;
+            .collect::<Result<_, _>>()?;
 
         let terms = params.remove("terms");
 
-        if !params.is_empty() {
+        // same format as the revision timestamps
+        let valid_from = params
+            .remove("valid_from")
+            .map(|time| time.parse::<Timestamp>().map(Into::into))
+            .transpose()
+            .map_err(ValidFromMalformatted)?;
+        let valid_until = params
+            .remove("valid_until")
+            .map(|time| time.parse::<Timestamp>().map(Into::into))
+            .transpose()
+            .map_err(ValidUntilMalformatted)?;
+        let may_reshare = params
+            .remove("may_reshare")
+            .map(|may_reshare| may_reshare.parse())
+            .transpose()
+            .map_err(MayReshareMalformatted)?
+            .unwrap_or_default();
+
+        if let Some(key) = params.keys().min() {
             // after all params must be empty, correct?
 
-            return Err(AdditionalKeys);
+            return Err(AdditionalKeys(key.to_string()));
         }
 
         Ok(AccessAgreement {
             sender,
             receiver,
             pages,
+            page_pattern,
             terms,
+            valid_from,
+            valid_until,
+            may_reshare,
+            template_version: version,
         })
     }
 }
+
+impl super::TemplateParams for AccessAgreement {
+    fn template_params(&self) -> Vec<(&'static str, String)> {
+        let pages: Vec<&str> = self.pages.iter().map(|(name, _)| &name[..]).collect();
+        let mut params = vec![
+            ("sender", self.sender.to_string()),
+            ("receiver", self.receiver.to_string()),
+            // the separator of the current version
+            ("pages", pages.join(", ")),
+        ];
+        if let Some(page_pattern) = &self.page_pattern {
+            params.push(("page_pattern", page_pattern.to_string()));
+        }
+        if let Some(terms) = &self.terms {
+            params.push(("terms", terms.clone()));
+        }
+        if let Some(valid_from) = self.valid_from {
+            params.push(("valid_from", Timestamp::from(valid_from).to_string()));
+        }
+        if let Some(valid_until) = self.valid_until {
+            params.push(("valid_until", Timestamp::from(valid_until).to_string()));
+        }
+        if self.may_reshare {
+            params.push(("may_reshare", true.to_string()));
+        }
+        params
+    }
+
+    fn transcluded_pages(&self) -> Vec<(&str, Hash)> {
+        self.pages
+            .iter()
+            .map(|(name, hash)| (&name[..], *hash))
+            .collect()
+    }
+}
+
+#[test]
+fn time_window() {
+    let time = |s: &str| s.parse::<Timestamp>().unwrap().into();
+    let page: Hash = [1; 64].into();
+    let params = [
+        ("sender", "0x0101010101010101010101010101010101010101"),
+        ("receiver", "0x0202020202020202020202020202020202020202"),
+        ("pages", "Main_Page"),
+        ("valid_from", "20240101000000"),
+        ("valid_until", "20240201000000"),
+    ];
+    let aa = AccessAgreement::try_from(GenericContractInfo {
+        hash: [0; 64].into(),
+        template: "AccessAgreement",
+        version: 2,
+        file: None,
+        transclusions: [("Main_Page", page)].into(),
+        params: params.map(|(k, v)| (k, v.to_string())).into(),
+    })
+    .unwrap();
+    assert_eq!(aa.valid_from, Some(time("20240101000000")));
+    assert_eq!(aa.valid_until, Some(time("20240201000000")));
+
+    assert!(!aa.is_valid_at(time("20231231235959")));
+    assert!(aa.is_valid_at(time("20240101000000")));
+    assert!(aa.is_valid_at(time("20240131235959")));
+    assert!(!aa.is_valid_at(time("20240201000000")));
+    assert!(!aa.is_expired_at(time("20240131235959")));
+    assert!(aa.is_expired_at(time("20240201000000")));
+
+    let content = Contract::AccessAgreement(aa)
+        .make_content(&TemplateRegistry::builtin())
+        .unwrap();
+    let main = &content.content["main"];
+    assert!(main.contains("\n|valid_from=20240101000000"));
+    assert!(main.contains("\n|valid_until=20240201000000\n}}"));
+}
+
+#[test]
+fn time_window_malformatted() {
+    let params = [
+        ("sender", "0x0101010101010101010101010101010101010101"),
+        ("receiver", "0x0202020202020202020202020202020202020202"),
+        ("pages", "Main_Page"),
+        ("valid_until", "next week"),
+    ];
+    let result = AccessAgreement::try_from(GenericContractInfo {
+        hash: [0; 64].into(),
+        template: "AccessAgreement",
+        version: 2,
+        file: None,
+        transclusions: [("Main_Page", [1; 64].into())].into(),
+        params: params.map(|(k, v)| (k, v.to_string())).into(),
+    });
+    assert!(matches!(
+        result,
+        Err(AccessAgreementError::ValidUntilMalformatted(_))
+    ));
+}
+
+#[test]
+fn may_reshare() {
+    let info = |may_reshare: Option<&str>| {
+        let mut params: std::collections::HashMap<&str, String> = [
+            ("sender", "0x0101010101010101010101010101010101010101"),
+            ("receiver", "0x0202020202020202020202020202020202020202"),
+            ("pages", "Main_Page"),
+        ]
+        .map(|(k, v)| (k, v.to_string()))
+        .into();
+        if let Some(may_reshare) = may_reshare {
+            params.insert("may_reshare", may_reshare.to_string());
+        }
+        AccessAgreement::try_from(GenericContractInfo {
+            hash: [0; 64].into(),
+            template: "AccessAgreement",
+            version: 2,
+            file: None,
+            transclusions: [("Main_Page", [1; 64].into())].into(),
+            params,
+        })
+    };
+    assert!(!info(None).unwrap().may_reshare);
+    assert!(!info(Some("false")).unwrap().may_reshare);
+    assert!(matches!(
+        info(Some("sure")),
+        Err(AccessAgreementError::MayReshareMalformatted(_))
+    ));
+
+    let aa = info(Some("true")).unwrap();
+    assert!(aa.may_reshare);
+    let content = Contract::AccessAgreement(aa)
+        .make_content(&TemplateRegistry::builtin())
+        .unwrap();
+    assert!(content.content["main"].contains("\n|may_reshare=true\n}}"));
+}
+
+#[test]
+fn page_pattern() {
+    let params = [
+        ("sender", "0x0101010101010101010101010101010101010101"),
+        ("receiver", "0x0202020202020202020202020202020202020202"),
+        ("page_pattern", "0:Audit/"),
+    ];
+    let aa = AccessAgreement::try_from(GenericContractInfo {
+        hash: [0; 64].into(),
+        template: "AccessAgreement",
+        version: 2,
+        file: None,
+        transclusions: Default::default(),
+        params: params.map(|(k, v)| (k, v.to_string())).into(),
+    })
+    .unwrap();
+    assert!(aa.pages.is_empty());
+    let pattern = aa.page_pattern.clone().unwrap();
+    assert!(pattern.matches(0, "Audit/2024 report"));
+    assert!(!pattern.matches(0, "Audits"));
+    assert!(!pattern.matches(2, "Audit/2024_report"));
+    assert!("6"
+        .parse::<PagePattern>()
+        .unwrap()
+        .matches(6, "Anything.png"));
+    assert!("Main:Audit/".parse::<PagePattern>().is_err());
+
+    let content = Contract::AccessAgreement(aa)
+        .make_content(&TemplateRegistry::builtin())
+        .unwrap();
+    assert!(content.content["main"].contains("\n|page_pattern=0:Audit/"));
+}
+
+#[test]
+fn template_versions() {
+    let info = |version, params: [(&'static str, &str); 3]| GenericContractInfo {
+        hash: [0; 64].into(),
+        template: "AccessAgreement",
+        version,
+        file: None,
+        transclusions: [
+            ("Main_Page", [1; 64].into()),
+            ("Other_Page", [2; 64].into()),
+        ]
+        .into(),
+        params: params.map(|(k, v)| (k, v.to_string())).into(),
+    };
+    let sender = ("sender", "0x0101010101010101010101010101010101010101");
+    let receiver = ("receiver", "0x0202020202020202020202020202020202020202");
+
+    let old = AccessAgreement::try_from(info(
+        1,
+        [sender, receiver, ("files", "Main Page,Other_Page")],
+    ))
+    .unwrap();
+    let current = AccessAgreement::try_from(info(
+        2,
+        [sender, receiver, ("pages", "Main_Page, Other Page")],
+    ))
+    .unwrap();
+    assert_eq!((old.template_version, current.template_version), (1, 2));
+    assert_eq!(old.pages, current.pages);
+    assert!(matches!(
+        AccessAgreement::try_from(info(1, [sender, receiver, ("pages", "Main_Page")])),
+        Err(AccessAgreementError::PagesMissing)
+    ));
+    assert!(matches!(
+        AccessAgreement::try_from(info(3, [sender, receiver, ("pages", "Main_Page")])),
+        Err(AccessAgreementError::UnknownVersion(3))
+    ));
+
+    // made with the current version, whichever one it was parsed with
+    let content = Contract::AccessAgreement(old)
+        .make_content(&TemplateRegistry::builtin())
+        .unwrap();
+    assert!(content.content["main"].contains("\n|pages=Main_Page, Other_Page\n"));
+    let transclusions = &content.content["transclusion-hashes"];
+    assert!(transclusions.contains("Main_Page") && transclusions.contains("Other_Page"));
+}
@@ -0,0 +1,128 @@
+use super::*;
+
+/// Access Revocation, withdraws a Data Access Agreement
+#[derive(
+    Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Debug, serde::Serialize, serde::Deserialize,
+)]
+pub struct AccessRevocation {
+    /// the sender of the revoked [`AccessAgreement`], only they can revoke it
+    pub sender: Address,
+    /// the genesis hash of the revoked [`AccessAgreement`]
+    pub agreement: Hash,
+    /// the version of the template it was parsed with
+    pub template_version: u32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum AccessRevocationEffects {
+    /// signed by the sender of the agreement. Everything the agreement shared is no longer shared.
+    Revoked,
+}
+
+/// Enumeration of error types for the Access Revocation
+#[derive(thiserror::Error, Debug)]
+#[non_exhaustive]
+pub enum AccessRevocationError {
+    #[error("sender missing")]
+    SenderMissing,
+    #[error("sender malformatted {0}")]
+    SenderMalformatted(ethaddr::ParseAddressError),
+
+    #[error("agreement missing")]
+    AgreementMissing,
+    #[error("agreement is not a hash")]
+    AgreementMalformatted,
+
+    #[error("unknown option {0} specified")]
+    AdditionalKeys(String),
+}
+
+impl AccessRevocationError {
+    /// the parameter of the template the error is about
+    pub fn param(&self) -> Option<&str> {
+        use AccessRevocationError::*;
+        Some(match self {
+            SenderMissing | SenderMalformatted(_) => "sender",
+            AgreementMissing | AgreementMalformatted => "agreement",
+            AdditionalKeys(key) => key,
+        })
+    }
+}
+
+const DECLARATION: Option<u8> = Some(0);
+const SENDER_SIGNATURE: Option<u8> = Some(1);
+
+impl super::SequencedContract for AccessRevocation {
+    type Effect = AccessRevocationEffects;
+
+    /// Checks the effectiveness of the given revisions of the Access Revocation (passed as Iterator).
+    fn is_effective(
+        &self,
+        revisions: impl std::iter::Iterator<Item = Option<u8>>,
+    ) -> Option<AccessRevocationEffects> {
+        let mut states = revisions.flatten();
+        match (states.next(), states.next(), states.next()) {
+            (SENDER_SIGNATURE, DECLARATION, None) => Some(AccessRevocationEffects::Revoked),
+            _ => None,
+        }
+    }
+
+    /// Determines the number of the ''effectiveness'' state of the Access Revocation revision based on the presence or absence of the sender signature.
+    fn sequence_number(&self, rev: &verifier::v1_2::Revision) -> Option<u8> {
+        let Some(prev) = &rev.prev else {
+            return DECLARATION;
+        };
+        if let Some(signature) = &prev.signature {
+            if self.sender == ethaddr::Address::from(signature.public_key) {
+                return SENDER_SIGNATURE;
+            }
+        }
+        None
+    }
+}
+
+impl TryFrom<GenericContractInfo<'_>> for AccessRevocation {
+    type Error = AccessRevocationError;
+
+    /// Tries to generate an Access Revocation from the [`GenericContractInfo`].
+    fn try_from(info: GenericContractInfo) -> Result<Self, Self::Error> {
+        let GenericContractInfo {
+            version,
+            mut params,
+            ..
+        } = info;
+
+        use AccessRevocationError::*;
+
+        let sender = params
+            .remove("sender")
+            .ok_or(SenderMissing)?
+            .parse()
+            .map_err(SenderMalformatted)?;
+
+        let agreement = params
+            .remove("agreement")
+            .ok_or(AgreementMissing)?
+            .parse()
+            .map_err(|()| AgreementMalformatted)?;
+
+        if let Some(key) = params.keys().min() {
+            return Err(AdditionalKeys(key.to_string()));
+        }
+
+        Ok(AccessRevocation {
+            sender,
+            agreement,
+            template_version: version,
+        })
+    }
+}
+
+impl TemplateParams for AccessRevocation {
+    fn template_params(&self) -> Vec<(&'static str, String)> {
+        vec![
+            ("sender", self.sender.to_string()),
+            ("agreement", self.agreement.to_string()),
+        ]
+    }
+}
@@ -0,0 +1,181 @@
+use std::ops::Range;
+
+use super::*;
+
+/// Why [`Contract::from_revision`] found no contract in a revision.
+#[derive(thiserror::Error, Debug)]
+pub enum ContractDiagnostic {
+    /// Nothing on the page is a contract, the revision is an ordinary one.
+    #[error("not a contract: {0}")]
+    NotAContract(NotAContract),
+    /// A contract template is on the page, but it cannot be parsed.
+    #[error(transparent)]
+    Invalid(Box<InvalidContract>),
+}
+
+/// Enumeration of reasons a revision is not a contract.
+#[derive(thiserror::Error, Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum NotAContract {
+    #[error("no text")]
+    NoText,
+    #[error("{0}")]
+    Wikitext(wikitext::WikitextError),
+    #[error("no contract template")]
+    NoContractTemplate,
+}
+
+/// A contract template on a page that cannot be parsed, with the place in the text it fails at.
+///
+/// Positions are byte ranges in the `main` content of the revision.
+#[derive(Debug)]
+pub struct InvalidContract {
+    /// Name of the template as written on the page.
+    pub template: String,
+    /// The whole template.
+    pub span: Range<usize>,
+    /// The parameter the error is about, if it is written on the page.
+    pub param: Option<ParamDiagnostic>,
+    pub error: ContractParseError,
+}
+
+/// A parameter of an [`InvalidContract`].
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct ParamDiagnostic {
+    /// Positional parameters have no name.
+    pub name: Option<String>,
+    /// The value as parsed, with the escapes resolved.
+    pub value: String,
+    /// The value as written.
+    pub span: Range<usize>,
+}
+
+impl From<NotAContract> for ContractDiagnostic {
+    fn from(reason: NotAContract) -> Self {
+        ContractDiagnostic::NotAContract(reason)
+    }
+}
+
+impl From<InvalidContract> for ContractDiagnostic {
+    fn from(invalid: InvalidContract) -> Self {
+        ContractDiagnostic::Invalid(Box::new(invalid))
+    }
+}
+
+impl InvalidContract {
+    /// The `template` on the page failing with `error`, [its parameter](ContractParseError::param) is looked up unless `param` is given.
+    pub(crate) fn new(
+        template: &wikitext::Template,
+        param: Option<&wikitext::Param>,
+        error: ContractParseError,
+    ) -> Self {
+        let param = param.or_else(|| template.get(error.param()?));
+        InvalidContract {
+            template: template.name.to_string(),
+            span: template.span.clone(),
+            param: param.map(|param| ParamDiagnostic {
+                name: param.name.map(str::to_string),
+                value: param.value.to_string(),
+                span: param.value_span.clone(),
+            }),
+            error,
+        }
+    }
+}
+
+impl std::fmt::Display for InvalidContract {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "invalid {} at {}..{}: {}",
+            self.template, self.span.start, self.span.end, self.error
+        )?;
+        if let Some(param) = &self.param {
+            let name = param.name.as_deref().unwrap_or("positional parameter");
+            write!(
+                f,
+                " ({name}={:?} at {}..{})",
+                param.value, param.span.start, param.span.end
+            )?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for InvalidContract {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&self.error)
+    }
+}
+
+#[cfg(test)]
+fn revision(main: &str, transclusions: &str) -> Revision {
+    let content = [
+        ("main".to_string(), main.to_string()),
+        ("transclusion-hashes".to_string(), transclusions.to_string()),
+    ];
+    Revision {
+        verification_hash: Hash::default(),
+        content: guardian_common::custom_types::RevisionContent {
+            file: None,
+            content: content.into(),
+            content_hash: Hash::default(),
+        },
+        metadata: verifier::v1_2::RevisionMetadata {
+            metadata_hash: Hash::default(),
+            domain_id: "42".to_string(),
+            timestamp: "20240601000000".parse().unwrap(),
+        },
+        prev: None,
+        merge: None,
+    }
+}
+
+#[test]
+fn diagnostics() {
+    let mut templates = TemplateRegistry::builtin();
+    let revocation = Hash::from([1; 64]);
+    templates.insert(ContractKind::AccessRevocation, 1, revocation);
+    let transclusions =
+        format!(r#"[{{"dbkey":"AccessRevocation","ns":10,"verification_hash":"{revocation}"}}]"#);
+    let diagnose = |main: &str, transclusions: &str| {
+        Contract::from_revision(&revision(main, transclusions), &templates).unwrap_err()
+    };
+
+    let ordinary = diagnose("Some text {{Note|careful}}", &transclusions);
+    assert!(matches!(
+        ordinary,
+        ContractDiagnostic::NotAContract(NotAContract::NoContractTemplate)
+    ));
+
+    let main = "Revoking:\n{{AccessRevocation\n|sender=0x12\n|agreement=00\n}}";
+    let ContractDiagnostic::Invalid(invalid) = diagnose(main, &transclusions) else {
+        panic!("malformatted sender is not an invalid contract");
+    };
+    assert!(matches!(
+        invalid.error,
+        ContractParseError::AccessRevocation(AccessRevocationError::SenderMalformatted(_))
+    ));
+    assert_eq!(&main[invalid.span.clone()], &main[10..]);
+    let param = invalid.param.unwrap();
+    assert_eq!(
+        (param.name.as_deref(), &param.value[..]),
+        (Some("sender"), "0x12")
+    );
+    assert_eq!(&main[param.span], "0x12");
+
+    let main = "{{AccessRevocation\n|sender\n}}";
+    let ContractDiagnostic::Invalid(invalid) = diagnose(main, &transclusions) else {
+        panic!("missing = is not an invalid contract");
+    };
+    assert!(matches!(invalid.error, ContractParseError::UnnamedParam));
+    assert_eq!(&main[invalid.param.unwrap().span], "sender\n");
+
+    let ContractDiagnostic::Invalid(invalid) = diagnose(main, "[{") else {
+        panic!("malformatted transclusions are not an invalid contract");
+    };
+    assert!(matches!(
+        invalid.error,
+        ContractParseError::TransclusionsMalformatted(_)
+    ));
+}
@@ -0,0 +1,144 @@
+use super::*;
+
+/// Fork Resolution, picks the branch of a forked Aqua-Chain that continues it
+#[derive(
+    Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Debug, serde::Serialize, serde::Deserialize,
+)]
+pub struct ForkResolution {
+    /// the owner of the forked chain
+    pub sender: Address,
+    /// the revision the chain forked at
+    pub fork: Hash,
+    /// the tip of the picked branch
+    pub tip: Hash,
+    /// the version of the template it was parsed with
+    pub template_version: u32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ForkResolutionEffects {
+    /// signed by the sender. The other branches of the fork are no longer shared.
+    Resolved,
+}
+
+/// Enumeration of error types for the Fork Resolution
+#[derive(thiserror::Error, Debug)]
+#[non_exhaustive]
+pub enum ForkResolutionError {
+    #[error("sender missing")]
+    SenderMissing,
+    #[error("sender malformatted {0}")]
+    SenderMalformatted(ethaddr::ParseAddressError),
+
+    #[error("fork missing")]
+    ForkMissing,
+    #[error("fork is not a hash")]
+    ForkMalformatted,
+
+    #[error("tip missing")]
+    TipMissing,
+    #[error("tip is not a hash")]
+    TipMalformatted,
+
+    #[error("unknown option {0} specified")]
+    AdditionalKeys(String),
+}
+
+impl ForkResolutionError {
+    /// the parameter of the template the error is about
+    pub fn param(&self) -> Option<&str> {
+        use ForkResolutionError::*;
+        Some(match self {
+            SenderMissing | SenderMalformatted(_) => "sender",
+            ForkMissing | ForkMalformatted => "fork",
+            TipMissing | TipMalformatted => "tip",
+            AdditionalKeys(key) => key,
+        })
+    }
+}
+
+const DECLARATION: Option<u8> = Some(0);
+const SENDER_SIGNATURE: Option<u8> = Some(1);
+
+impl super::SequencedContract for ForkResolution {
+    type Effect = ForkResolutionEffects;
+
+    /// Checks the effectiveness of the given revisions of the Fork Resolution (passed as Iterator).
+    fn is_effective(
+        &self,
+        revisions: impl std::iter::Iterator<Item = Option<u8>>,
+    ) -> Option<ForkResolutionEffects> {
+        let mut states = revisions.flatten();
+        match (states.next(), states.next(), states.next()) {
+            (SENDER_SIGNATURE, DECLARATION, None) => Some(ForkResolutionEffects::Resolved),
+            _ => None,
+        }
+    }
+
+    /// Determines the number of the ''effectiveness'' state of the Fork Resolution revision based on the presence or absence of the sender signature.
+    fn sequence_number(&self, rev: &verifier::v1_2::Revision) -> Option<u8> {
+        let Some(prev) = &rev.prev else {
+            return DECLARATION;
+        };
+        if let Some(signature) = &prev.signature {
+            if self.sender == ethaddr::Address::from(signature.public_key) {
+                return SENDER_SIGNATURE;
+            }
+        }
+        None
+    }
+}
+
+impl TryFrom<GenericContractInfo<'_>> for ForkResolution {
+    type Error = ForkResolutionError;
+
+    /// Tries to generate a Fork Resolution from the [`GenericContractInfo`].
+    fn try_from(info: GenericContractInfo) -> Result<Self, Self::Error> {
+        let GenericContractInfo {
+            version,
+            mut params,
+            ..
+        } = info;
+
+        use ForkResolutionError::*;
+
+        let sender = params
+            .remove("sender")
+            .ok_or(SenderMissing)?
+            .parse()
+            .map_err(SenderMalformatted)?;
+
+        let fork = params
+            .remove("fork")
+            .ok_or(ForkMissing)?
+            .parse()
+            .map_err(|()| ForkMalformatted)?;
+
+        let tip = params
+            .remove("tip")
+            .ok_or(TipMissing)?
+            .parse()
+            .map_err(|()| TipMalformatted)?;
+
+        if let Some(key) = params.keys().min() {
+            return Err(AdditionalKeys(key.to_string()));
+        }
+
+        Ok(ForkResolution {
+            sender,
+            fork,
+            tip,
+            template_version: version,
+        })
+    }
+}
+
+impl TemplateParams for ForkResolution {
+    fn template_params(&self) -> Vec<(&'static str, String)> {
+        vec![
+            ("sender", self.sender.to_string()),
+            ("fork", self.fork.to_string()),
+            ("tip", self.tip.to_string()),
+        ]
+    }
+}
@@ -0,0 +1,160 @@
+use super::*;
+
+/// Group of users, an [`AccessAgreement`] naming its genesis hash as receiver shares to all of its members
+///
+/// unlike other contracts a group changes along its chain, every revision may list other members. Only the members of
+/// the latest revision signed by the owner count.
+#[derive(
+    Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Debug, serde::Serialize, serde::Deserialize,
+)]
+pub struct Group {
+    /// the only one whose signature makes the members count
+    pub owner: Address,
+    pub members: Vec<Address>,
+    /// the version of the template it was parsed with
+    pub template_version: u32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum GroupEffects {
+    /// signed by the owner after the last change of members. Agreements with the group share to the members.
+    Formed,
+}
+
+/// Enumeration of error types for the Group
+#[derive(thiserror::Error, Debug)]
+#[non_exhaustive]
+pub enum GroupError {
+    #[error("owner missing")]
+    OwnerMissing,
+    #[error("owner malformatted {0}")]
+    OwnerMalformatted(ethaddr::ParseAddressError),
+
+    #[error("members missing")]
+    MembersMissing,
+    #[error("member malformatted {0}")]
+    MemberMalformatted(ethaddr::ParseAddressError),
+
+    #[error("unknown option {0} specified")]
+    AdditionalKeys(String),
+}
+
+impl GroupError {
+    /// the parameter of the template the error is about
+    pub fn param(&self) -> Option<&str> {
+        use GroupError::*;
+        Some(match self {
+            OwnerMissing | OwnerMalformatted(_) => "owner",
+            MembersMissing | MemberMalformatted(_) => "members",
+            AdditionalKeys(key) => key,
+        })
+    }
+}
+
+const DECLARATION: Option<u8> = Some(0);
+const OWNER_SIGNATURE: Option<u8> = Some(1);
+const AMENDMENT: Option<u8> = Some(2);
+
+impl super::SequencedContract for Group {
+    type Effect = GroupEffects;
+
+    /// Checks the effectiveness of the given revisions of the Group (passed as Iterator).
+    fn is_effective(
+        &self,
+        revisions: impl std::iter::Iterator<Item = Option<u8>>,
+    ) -> Option<GroupEffects> {
+        let mut states = revisions.flatten();
+        // the latest revision has to be signed, any number of amendments and signatures may lie before it
+        if Some(states.next()?) != OWNER_SIGNATURE {
+            return None;
+        }
+        loop {
+            match states.next() {
+                OWNER_SIGNATURE | AMENDMENT => continue,
+                DECLARATION => break,
+                _ => return None,
+            }
+        }
+        states.next().is_none().then_some(GroupEffects::Formed)
+    }
+
+    /// Determines the number of the ''effectiveness'' state of the Group revision, anything not signed by the owner is an amendment to be signed.
+    fn sequence_number(&self, rev: &verifier::v1_2::Revision) -> Option<u8> {
+        let Some(prev) = &rev.prev else {
+            return DECLARATION;
+        };
+        match &prev.signature {
+            Some(signature) if self.owner == ethaddr::Address::from(signature.public_key) => {
+                OWNER_SIGNATURE
+            }
+            _ => AMENDMENT,
+        }
+    }
+}
+
+impl TryFrom<GenericContractInfo<'_>> for Group {
+    type Error = GroupError;
+
+    /// Tries to generate a Group from the [`GenericContractInfo`].
+    fn try_from(info: GenericContractInfo) -> Result<Self, Self::Error> {
+        let GenericContractInfo {
+            version,
+            mut params,
+            ..
+        } = info;
+
+        use GroupError::*;
+
+        let owner = params
+            .remove("owner")
+            .ok_or(OwnerMissing)?
+            .parse()
+            .map_err(OwnerMalformatted)?;
+
+        let members = params
+            .remove("members")
+            .ok_or(MembersMissing)?
+            .split(',')
+            .map(str::trim)
+            .filter(|member| !member.is_empty())
+            .map(str::parse)
+            .collect::<Result<_, _>>()
+            .map_err(MemberMalformatted)?;
+
+        if let Some(key) = params.keys().min() {
+            return Err(AdditionalKeys(key.to_string()));
+        }
+
+        Ok(Group {
+            owner,
+            members,
+            template_version: version,
+        })
+    }
+}
+
+impl TemplateParams for Group {
+    fn template_params(&self) -> Vec<(&'static str, String)> {
+        let members: Vec<String> = self.members.iter().map(ToString::to_string).collect();
+        vec![
+            ("owner", self.owner.to_string()),
+            ("members", members.join(",")),
+        ]
+    }
+}
+
+#[test]
+fn amendments_need_signing() {
+    let group = Group {
+        owner: Address([1; 20]),
+        members: vec![],
+        template_version: 1,
+    };
+    let effect = |states: &[u8]| group.is_effective(states.iter().rev().map(|s| Some(*s)));
+    assert_eq!(effect(&[0]), None);
+    assert_eq!(effect(&[0, 1]), Some(GroupEffects::Formed));
+    assert_eq!(effect(&[0, 1, 2]), None);
+    assert_eq!(effect(&[0, 1, 2, 1]), Some(GroupEffects::Formed));
+    assert_eq!(effect(&[0, 1, 2, 2, 1]), Some(GroupEffects::Formed));
+    assert_eq!(effect(&[0, 0, 1]), None);
+}
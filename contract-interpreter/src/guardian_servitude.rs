@@ -1,10 +1,14 @@
 /// Guardian Servitude
-#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+#[derive(
+    Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Debug, serde::Serialize, serde::Deserialize,
+)]
 pub struct GuardianServitude {
     /// the guardian who wants to serve the [`user`]
     pub guardian: ethaddr::Address,
     /// the user who accepts the [`guardian`] serving them
     pub user: ethaddr::Address,
+    /// the version of the template it was parsed with
+    pub template_version: u32,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
@@ -31,8 +35,20 @@ pub enum GuardianServitudeError {
     #[error("user address malformatted {0}")]
     UserMalformatted(ethaddr::ParseAddressError),
 
-    #[error("unknown options specified")]
-    AdditionalKeys,
+    #[error("unknown option {0} specified")]
+    AdditionalKeys(String),
+}
+
+impl GuardianServitudeError {
+    /// the parameter of the template the error is about
+    pub fn param(&self) -> Option<&str> {
+        use GuardianServitudeError::*;
+        Some(match self {
+            GuardianMissing | GuardianMalformatted(_) => "guardian",
+            UserMissing | UserMalformatted(_) => "user",
+            AdditionalKeys(key) => key,
+        })
+    }
 }
 
 const DECLARATION: Option<u8> = Some(0);
@@ -89,7 +105,11 @@ impl TryFrom<GenericContractInfo<'_>> for GuardianServitude {
 
     /// Tries to generate a Guardian Servitude from the [`GenericContractInfo`].
     fn try_from(info: GenericContractInfo) -> Result<Self, Self::Error> {
-        let GenericContractInfo { mut params, .. } = info;
+        let GenericContractInfo {
+            version,
+            mut params,
+            ..
+        } = info;
 
         use GuardianServitudeError::*;
 
@@ -100,10 +120,23 @@ impl TryFrom<GenericContractInfo<'_>> for GuardianServitude {
         let user = ethaddr::Address::from_str_checksum(&params.remove("user").ok_or(UserMissing)?)
             .map_err(UserMalformatted)?;
 
-        if !params.is_empty() {
-            return Err(AdditionalKeys);
+        if let Some(key) = params.keys().min() {
+            return Err(AdditionalKeys(key.to_string()));
         }
 
-        Ok(GuardianServitude { guardian, user })
+        Ok(GuardianServitude {
+            guardian,
+            user,
+            template_version: version,
+        })
+    }
+}
+
+impl super::TemplateParams for GuardianServitude {
+    fn template_params(&self) -> Vec<(&'static str, String)> {
+        vec![
+            ("guardian", self.guardian.to_string()),
+            ("user", self.user.to_string()),
+        ]
     }
 }
@@ -2,17 +2,28 @@ use std::collections::HashMap;
 
 use guardian_common::{
     crypt::Digest,
-    custom_types::Base64,
     prelude::{Address, Hash},
 };
 use verifier::v1_2::Revision;
 
 mod access_agreement;
 pub use access_agreement::*;
+mod access_revocation;
+pub use access_revocation::*;
+mod fork_resolution;
+pub use fork_resolution::*;
+mod group;
+pub use group::*;
 mod guardian_servitude;
 pub use guardian_servitude::*;
 mod tls_identity_claim;
 pub use tls_identity_claim::*;
+mod template_registry;
+pub use template_registry::*;
+mod diagnostic;
+pub mod timeline;
+pub mod wikitext;
+pub use diagnostic::*;
 
 /// Trait for the contracts whose ''effectiveness'' depends on the correct order of their revisions.
 pub trait SequencedContract {
@@ -27,7 +38,9 @@ pub trait SequencedContract {
 }
 
 /// Enumeration of possible contract types.
-#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+#[derive(
+    Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Debug, serde::Serialize, serde::Deserialize,
+)]
 #[non_exhaustive]
 pub enum Contract {
     /// Data Access Agreement that is used to share Aqua-Chains with other users.
@@ -36,47 +49,87 @@ pub enum Contract {
     GuardianServitude(GuardianServitude),
     /// [mTLS](https://en.wikipedia.org/wiki/Mutual_authentication#mTLS) Certificate of the Guardian.
     TlsIdentityClaim(TlsIdentityClaim),
+    /// Access Revocation that is used to withdraw a Data Access Agreement.
+    AccessRevocation(AccessRevocation),
+    /// Fork Resolution that is used to pick a branch of a forked Aqua-Chain.
+    ForkResolution(ForkResolution),
+    /// Group that is used to share Aqua-Chains with several users by one Data Access Agreement.
+    Group(Group),
 }
 
 macro_rules! matchhash {
-    ($($contract:ident <-> $hex:literal),* $(,)?) => {
-        impl TryFrom<GenericContractInfo<'_>> for Contract {
-            type Error = ContractParseError;
+    (@hash) => { None };
+    (@hash $hex:literal) => { Some(::hex_literal::hex!($hex).into()) };
+    ($($contract:ident ($version:literal) $(<-> $hex:literal)?),* $(,)?) => {
+        /// Enumeration of the kinds of contracts, one per template.
+        #[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug, serde::Serialize, serde::Deserialize)]
+        #[non_exhaustive]
+        pub enum ContractKind {
+            $($contract,)*
+        }
+        impl ContractKind {
+            pub const ALL: &'static [ContractKind] = &[$(ContractKind::$contract,)*];
+
+            /// Version of the template new contracts are made with, versions count up from 1.\
+            /// Every version has its own parameters, contracts are parsed by the ones of the version their template hash is registered for.
+            pub fn current_version(self) -> u32 {
+                match self {
+                    $(ContractKind::$contract => $version,)*
+                }
+            }
 
-            fn try_from(gci: GenericContractInfo) -> Result<Self, Self::Error> {
-                match <[u8; 64]>::from(*gci.hash) {
+            /// Verification hash of the [current version](ContractKind::current_version) of the template, recognized without any configuration.\
+            /// `None` for templates not published yet, their hashes have to be [loaded](TemplateRegistry::load) or discovered.
+            pub fn builtin_hash(self) -> Option<Hash> {
+                match self {
+                    $(ContractKind::$contract => matchhash!(@hash $($hex)?),)*
+                }
+            }
+
+            fn parse(self, gci: GenericContractInfo) -> Result<Contract, ContractParseError> {
+                match self {
                     $(
-                        hash if hash == ::hex_literal::hex!($hex) => {
-                            let ret = $contract::try_from(gci);
-                            dbg!(&ret);
-                            Ok(Contract::$contract(ret?))
-                        },
+                        ContractKind::$contract => {
+                            Ok(Contract::$contract($contract::try_from(gci)?))
+                        }
                     )*
-                    _ => Err(ContractParseError::UnknownContractHash)
                 }
             }
         }
         impl Contract {
-            fn contract_hash(&self) -> Hash {
+            pub fn kind(&self) -> ContractKind {
                 match self {
-                    $(
-                        Contract::$contract(_) => {
-                            ::hex_literal::hex!($hex).into()
-                        },
-                    )*
+                    $(Contract::$contract(_) => ContractKind::$contract,)*
+                }
+            }
+            /// Version of the template it was parsed with.
+            pub fn template_version(&self) -> u32 {
+                match self {
+                    $(Contract::$contract(contract) => contract.template_version,)*
+                }
+            }
+            fn codec(&self) -> &dyn TemplateParams {
+                match self {
+                    $(Contract::$contract(contract) => contract,)*
                 }
             }
         }
     };
 
 }
-// Hashes of contracts need to be adjusted here for the contract to be recognized as valid contracts.
-// When templates are changed, the hashes need to be updated here.
-// %todo this be imported more elegantly in the future.
+// Current template versions and their hashes, recognized without a configured [`TemplateRegistry`].
+// Older versions and further hashes are added to the registry, new contracts are made with these.
 matchhash! {
-    AccessAgreement <-> "725c2b99a955a690e50a1f22f356a64b02c144dd5adcbc09ac09f861fe2cc45a47185d7a9f5ecc60af86c0e60545aabe8c8c9c34feff92ea1da511ec0e2ef2ac",
-    GuardianServitude <-> "2c82d270181179987518d620c102a0fc9db1d5ed7238795cc87d9e1de70ed3b6f67236dd3152881d620f9270b7dcb7fea72bd7e9b859dc2478a3058b078f5204",
-    TlsIdentityClaim <-> "95ce4ec4bf2b92019feff4843ddd7b849db8c7c0bd2afe325566dee7c6d5bcc6d1870032d3fa5230bb2f184a689f9b758f8282a2a1984238178581fb7895df13",
+    // version 1 listed the pages as files=
+    AccessAgreement(2) <-> "725c2b99a955a690e50a1f22f356a64b02c144dd5adcbc09ac09f861fe2cc45a47185d7a9f5ecc60af86c0e60545aabe8c8c9c34feff92ea1da511ec0e2ef2ac",
+    GuardianServitude(1) <-> "2c82d270181179987518d620c102a0fc9db1d5ed7238795cc87d9e1de70ed3b6f67236dd3152881d620f9270b7dcb7fea72bd7e9b859dc2478a3058b078f5204",
+    TlsIdentityClaim(1) <-> "95ce4ec4bf2b92019feff4843ddd7b849db8c7c0bd2afe325566dee7c6d5bcc6d1870032d3fa5230bb2f184a689f9b758f8282a2a1984238178581fb7895df13",
+    // not published yet
+    AccessRevocation(1),
+    // not published yet
+    ForkResolution(1),
+    // not published yet
+    Group(1),
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
@@ -99,83 +152,80 @@ pub enum ContractEffect {
             tls_identity_claim::TlsIdentityClaimEffects,
         ),
     ),
+    AccessRevocation(
+        (
+            access_revocation::AccessRevocation,
+            access_revocation::AccessRevocationEffects,
+        ),
+    ),
+    ForkResolution(
+        (
+            fork_resolution::ForkResolution,
+            fork_resolution::ForkResolutionEffects,
+        ),
+    ),
+    Group((group::Group, group::GroupEffects)),
+}
+
+impl ContractEffect {
+    /// Name of the effect without the contract, like `Granted`.
+    pub fn name(&self) -> String {
+        match self {
+            ContractEffect::AccessAgreement((_, effect)) => format!("{effect:?}"),
+            ContractEffect::GuardianServitude((_, effect)) => format!("{effect:?}"),
+            ContractEffect::TlsIdentityClaim((_, effect)) => format!("{effect:?}"),
+            ContractEffect::AccessRevocation((_, effect)) => format!("{effect:?}"),
+            ContractEffect::ForkResolution((_, effect)) => format!("{effect:?}"),
+            ContractEffect::Group((_, effect)) => format!("{effect:?}"),
+        }
+    }
+}
+
+/// The codec of a contract kind, writing it as the parameters of the current version of its template.\
+/// Every kind parses the parameters back to the same contract by its `TryFrom<`[`GenericContractInfo`]`>`, values are
+/// [escaped](wikitext::escape) in between.
+pub trait TemplateParams {
+    /// Named parameters in the order they are written, values as they are parsed.
+    fn template_params(&self) -> Vec<(&'static str, String)>;
+    /// Pages transcluded besides the template, parsing looks up their hashes by name.
+    fn transcluded_pages(&self) -> Vec<(&str, Hash)> {
+        Vec::new()
+    }
 }
 
 impl Contract {
-    /// Creates the content of a revision from a generic Contract data.\
-    /// This can be used to construct a revision in form of JSON file for this to be then pushed in the PKC.
-    pub fn make_content(&self) -> guardian_common::custom_types::RevisionContent {
+    /// Creates the content of a revision from a generic Contract data, [`from_revision`](Contract::from_revision) parses it back to the same contract.\
+    /// This can be used to construct a revision in form of JSON file for this to be then pushed in the PKC.\
+    /// The template is transcluded with the [current hash](TemplateRegistry::current_hash) of its kind in `templates`.
+    pub fn make_content(
+        &self,
+        templates: &TemplateRegistry,
+    ) -> Result<guardian_common::custom_types::RevisionContent, UnconfiguredTemplate> {
+        let template_hash = templates.current_hash(self.kind())?;
         let mut content = std::collections::BTreeMap::default();
 
-        let name = match self {
-            Contract::AccessAgreement(_) => "AccessAgreement",
-            Contract::GuardianServitude(_) => "GuardianServitude",
-            Contract::TlsIdentityClaim(_) => "TlsIdentityClaim",
-        };
-
-        lazy_static::lazy_static! {
-            static ref REPLACE: aho_corasick::AhoCorasick = aho_corasick::AhoCorasick::new(["|", "{", "}"]).unwrap();
-        };
+        let name = self.kind().template_name();
+        let codec = self.codec();
 
         let mut transclusions = vec![Transclusion {
             dbkey: name,
             ns: 10,
-            verification_hash: self.contract_hash(),
+            verification_hash: template_hash,
         }];
-        let mut main = format!("{{{{{}\n|", name);
-        let /*mut*/ file = None;
-
-        match self {
-            Contract::AccessAgreement(aa) => {
-                main += &format!("sender={}\n|", aa.sender);
-                main += &format!("receiver={}\n|", aa.receiver);
-                let mut filesiter = aa.pages.iter();
-                main += &format!(
-                    "files={}",
-                    filesiter.next().map(|(k, _v)| &k[..]).unwrap_or_default()
-                );
-                for (filename, transcluded_hash) in filesiter {
-                    main += ",";
-                    main += filename;
-                    transclusions.push(Transclusion {
-                        dbkey: filename,
-                        // todo: aaaaaaaa namespace
-                        ns: 0,
-                        verification_hash: *transcluded_hash,
-                    });
-                }
-                if let Some(terms) = &aa.terms {
-                    main += &format!(
-                        "\n|terms={}",
-                        REPLACE
-                            .try_replace_all(terms, &["{{|}}", "{{(}}", "{{)}}"])
-                            .expect("what the fuck is a kilometer")
-                    );
-                }
-                main += "\n}}";
-            }
-            Contract::GuardianServitude(gs) => {
-                main += &format!("guardian={}\n|", gs.guardian);
-                main += &format!("user={}\n}}}}", gs.user);
-            }
-            Contract::TlsIdentityClaim(tic) => {
-                // file = Some(guardian_common::custom_types::FileContent {
-                //     size: tic.cert.len() as u32,
-                //     data: tic.cert.to_vec().into(),
-                //     filename: tic.guardian.to_string(),
-                //     comment: Default::default(),
-                // });
-                main += &format!("guardian={}\n|", tic.guardian);
-                main += &format!("file={}\n|", Base64::from(tic.cert.to_vec()));
-                main += &format!("host={}\n|", tic.host);
-                main += &format!("port={}\n}}}}", tic.port);
-                // let file_hash = guardian_common::crypt::Hasher::digest(&tic.cert[..]);
-                // content.insert(
-                //     "file_hash".to_string(),
-                //     Hash::from(file_hash).to_stackstr().to_string(),
-                // );
-            }
+        for (page, verification_hash) in codec.transcluded_pages() {
+            transclusions.push(Transclusion {
+                dbkey: page,
+                // todo: aaaaaaaa namespace
+                ns: 0,
+                verification_hash,
+            });
+        }
+        let mut main = format!("{{{{{}", name);
+        for (key, value) in codec.template_params() {
+            main += &format!("\n|{}={}", key, wikitext::escape(&value));
         }
+        main += "\n}}";
+        let /*mut*/ file = None;
 
         content.insert(
             "transclusion-hashes".to_string(),
@@ -191,11 +241,11 @@ impl Contract {
             Hash::from(c.finalize())
         };
 
-        guardian_common::custom_types::RevisionContent {
+        Ok(guardian_common::custom_types::RevisionContent {
             file,
             content,
             content_hash,
-        }
+        })
     }
 }
 
@@ -216,6 +266,15 @@ impl SequencedContract for Contract {
             Contract::TlsIdentityClaim(tic) => {
                 ContractEffect::TlsIdentityClaim((tic.clone(), tic.is_effective(revisions)?))
             }
+            Contract::AccessRevocation(ar) => {
+                ContractEffect::AccessRevocation((ar.clone(), ar.is_effective(revisions)?))
+            }
+            Contract::ForkResolution(fr) => {
+                ContractEffect::ForkResolution((fr.clone(), fr.is_effective(revisions)?))
+            }
+            Contract::Group(group) => {
+                ContractEffect::Group((group.clone(), group.is_effective(revisions)?))
+            }
         })
     }
 
@@ -224,6 +283,9 @@ impl SequencedContract for Contract {
             Contract::AccessAgreement(aa) => aa.sequence_number(revision),
             Contract::GuardianServitude(gs) => gs.sequence_number(revision),
             Contract::TlsIdentityClaim(tic) => tic.sequence_number(revision),
+            Contract::AccessRevocation(ar) => ar.sequence_number(revision),
+            Contract::ForkResolution(fr) => fr.sequence_number(revision),
+            Contract::Group(group) => group.sequence_number(revision),
         }
     }
 }
@@ -232,14 +294,43 @@ impl SequencedContract for Contract {
 #[derive(thiserror::Error, Debug)]
 #[non_exhaustive]
 pub enum ContractParseError {
-    #[error("unknown contract hash")]
-    UnknownContractHash,
-    #[error("access agreement")]
+    #[error("unknown hash {hash} of template {template}")]
+    UnknownContractHash { template: String, hash: Hash },
+    #[error("transclusion-hashes missing")]
+    TransclusionsMissing,
+    #[error("transclusion-hashes malformatted {0}")]
+    TransclusionsMalformatted(serde_json::Error),
+    #[error("template {0} not transcluded")]
+    TemplateNotTranscluded(String),
+    #[error("parameter without name")]
+    UnnamedParam,
+    #[error("access agreement: {0}")]
     AccessAgreement(#[from] AccessAgreementError),
-    #[error("guardian servitude")]
+    #[error("guardian servitude: {0}")]
     GuardianServitude(#[from] GuardianServitudeError),
-    #[error("tls identity claim")]
+    #[error("tls identity claim: {0}")]
     TlsIdentityClaim(#[from] TlsIdentityClaimError),
+    #[error("access revocation: {0}")]
+    AccessRevocation(#[from] AccessRevocationError),
+    #[error("fork resolution: {0}")]
+    ForkResolution(#[from] ForkResolutionError),
+    #[error("group: {0}")]
+    Group(#[from] GroupError),
+}
+
+impl ContractParseError {
+    /// The parameter of the template the error is about.
+    pub fn param(&self) -> Option<&str> {
+        match self {
+            ContractParseError::AccessAgreement(e) => e.param(),
+            ContractParseError::GuardianServitude(e) => e.param(),
+            ContractParseError::TlsIdentityClaim(e) => e.param(),
+            ContractParseError::AccessRevocation(e) => e.param(),
+            ContractParseError::ForkResolution(e) => e.param(),
+            ContractParseError::Group(e) => e.param(),
+            _ => None,
+        }
+    }
 }
 
 /// This structure represents a generic contract
@@ -249,6 +340,10 @@ pub struct GenericContractInfo<'a> {
     /// Verification hash of the contract.\
     /// More specifically it is a verification hash of the contract template used to create a contract.
     pub hash: Hash,
+    /// Name of the contract template, without the template namespace.
+    pub template: &'a str,
+    /// Version of the template, the parameters depend on it.
+    pub version: u32,
     /// Name of the file linked to the revision
     pub file: Option<&'a [u8]>,
     /// The transclusion hashes can be found in content -> content -> *transclusion-hashes*.\
@@ -304,7 +399,7 @@ pub fn is_contract_effective(
     let mut iter = contract_and_state.clone();
     let realfirst = iter.next()?;
     let same_contract = iter.fold(realfirst.get_contract_data(), |acc, elem| {
-        acc.and_then(|acc| (acc == elem.get_contract_data()?).then_some(acc))
+        acc.and_then(|acc| acc.continues(elem.get_contract_data()?).then_some(acc))
     })?;
 
     let rev_iter = contract_and_state.map(|tuple| tuple.get_contract_seqno());
@@ -314,16 +409,82 @@ pub fn is_contract_effective(
 }
 
 impl Contract {
-    /// Extracts data needed for a contract from revision and returns a contract if it can be detected.
-    pub fn from_revision(rev: &Revision) -> Option<Result<Self, ContractParseError>> {
-        let (hash, transclusions, params) = contract_content(rev)?;
+    /// Whether this can follow the `earlier` contract on the same chain. Only [`Group`]s may change along the way, their latest data counts.
+    fn continues(&self, earlier: &Contract) -> bool {
+        match (self, earlier) {
+            (Contract::Group(group), Contract::Group(earlier)) => group.owner == earlier.owner,
+            _ => self == earlier,
+        }
+    }
+
+    /// Extracts data needed for a contract from revision and returns a contract if it can be detected.\
+    /// Only the template versions in `templates` are recognized, the first template on the page made with one of them is the contract.
+    /// Without one, a template named like a [`ContractKind`] is a contract made with an unknown version.
+    ///
+    /// The [diagnostic](ContractDiagnostic) tells whether the revision is not a contract at all or which parameter of its contract is invalid.
+    pub fn from_revision(
+        rev: &Revision,
+        templates: &TemplateRegistry,
+    ) -> Result<Self, ContractDiagnostic> {
+        let text = rev
+            .content
+            .content
+            .get("main")
+            .ok_or(NotAContract::NoText)?;
+        let on_page = wikitext::templates(text).map_err(NotAContract::Wikitext)?;
+        let transclusions = transclusions(rev);
+        let transcluded = |template: &wikitext::Template| {
+            let transclusions = transclusions.as_ref().ok()?;
+            transclusions
+                .get(template.name.replace(' ', "_").as_str())
+                .copied()
+        };
+
+        let registered = on_page.iter().find_map(|template| {
+            let hash = transcluded(template)?;
+            Some((template, hash, templates.version_of(hash)?))
+        });
+        let Some((template, hash, (kind, version))) = registered else {
+            // "Group" is a common name for unrelated templates, it only counts once a version of the contract is known
+            let template = on_page
+                .iter()
+                .find(|template| {
+                    ContractKind::from_template_name(template.name)
+                        .is_some_and(|kind| kind != ContractKind::Group || templates.knows(kind))
+                })
+                .ok_or(NotAContract::NoContractTemplate)?;
+            let error = match (transcluded(template), transclusions) {
+                (_, Err(error)) => error,
+                (Some(hash), Ok(_)) => ContractParseError::UnknownContractHash {
+                    template: template.name.to_string(),
+                    hash,
+                },
+                (None, Ok(_)) => {
+                    ContractParseError::TemplateNotTranscluded(template.name.to_string())
+                }
+            };
+            return Err(InvalidContract::new(template, None, error).into());
+        };
+
+        // contracts only have named parameters
+        let mut params = ContractParams::new();
+        for param in &template.params {
+            let Some(name) = param.name else {
+                let error = ContractParseError::UnnamedParam;
+                return Err(InvalidContract::new(template, Some(param), error).into());
+            };
+            params.insert(name, param.value.to_string());
+        }
         let generic_contract_info = GenericContractInfo {
             hash,
+            template: template.name,
+            version,
             file: rev.content.file.as_ref().map(|x| x.data.as_ref()),
-            transclusions,
+            transclusions: transclusions.unwrap_or_default(),
             params,
         };
-        Some(Contract::try_from(generic_contract_info))
+        kind.parse(generic_contract_info)
+            .map_err(|error| InvalidContract::new(template, None, error).into())
     }
 
     /// Identifies the revision of the contract and returns a figure that describes possible contract state.
@@ -355,21 +516,17 @@ struct Transclusion<'a> {
     ns: i32,
     verification_hash: Hash,
 }
-/// Extracts from a given revision [verification hash][`GenericContractInfo::hash`] of the contract's template,\
-/// [transclusion hashes][`GenericContractInfo::transclusions`] of the pages linked to the revision and\
-/// [parameters of the contract][`GenericContractInfo::params`].
-fn contract_content(rev: &Revision) -> Option<(Hash, Transclusions, ContractParams)> {
-    let mediawiki_text = rev.content.content.get("main")?;
-    let mediawiki_text = mediawiki_text.strip_prefix("{{")?;
-    let mediawiki_text = mediawiki_text.strip_suffix("\n}}")?;
-    let mut item_iter = mediawiki_text.split("\n|");
-    let tmp_template_name = item_iter.next()?.replace(" ", "_");
-    let template_name = tmp_template_name.as_str();
-
-    let transclusions: Vec<Transclusion> =
-        serde_json::from_str(rev.content.content.get("transclusion-hashes")?).ok()?;
-
-    let transclusion_lookup: HashMap<&str, Hash> = transclusions
+/// Extracts from a given revision the [transclusion hashes][`GenericContractInfo::transclusions`] of the templates and pages linked to the revision.
+fn transclusions(rev: &Revision) -> Result<Transclusions<'_>, ContractParseError> {
+    let transclusions = rev
+        .content
+        .content
+        .get("transclusion-hashes")
+        .ok_or(ContractParseError::TransclusionsMissing)?;
+    let transclusions: Vec<Transclusion> = serde_json::from_str(transclusions)
+        .map_err(ContractParseError::TransclusionsMalformatted)?;
+
+    Ok(transclusions
         .into_iter()
         .map(
             |Transclusion {
@@ -378,22 +535,7 @@ fn contract_content(rev: &Revision) -> Option<(Hash, Transclusions, ContractPara
                  ..
              }| { (dbkey, verification_hash) },
         )
-        .collect();
-
-    let template_hash = *transclusion_lookup.get(template_name)?;
-
-    lazy_static::lazy_static! {
-        static ref REPLACE: aho_corasick::AhoCorasick = aho_corasick::AhoCorasick::new(["{{|}}", "{{(}}", "{{)}}"]).unwrap();
-    };
-    let map = item_iter
-        .map(|item| {
-            let (key, value) = item.split_once("=")?;
-            let value_replaced = REPLACE.try_replace_all(value, &["|", "{", "}"]).unwrap();
-            Some((key, value_replaced))
-        })
-        .collect::<Option<_>>()?;
-
-    Some((template_hash, transclusion_lookup, map))
+        .collect())
 }
 
 // #[test]
@@ -0,0 +1,236 @@
+use std::collections::BTreeMap;
+
+use super::*;
+
+/// Template versions recognized as contracts, several verification hashes per [`ContractKind`] and version
+///
+/// starts out with the [built-in](ContractKind::builtin_hash) hash of the current version of every kind that has one.
+/// Editing a template in the PKC makes a new hash, which has to be added here to keep recognizing contracts made with it.
+/// Contracts are parsed by the parameters of the version their template hash is registered for.
+#[derive(Clone, PartialEq, Eq, Debug, serde::Serialize, serde::Deserialize)]
+#[serde(transparent)]
+pub struct TemplateRegistry {
+    /// the hashes of every version of every kind, oldest first
+    templates: BTreeMap<ContractKind, BTreeMap<u32, Vec<Hash>>>,
+}
+
+/// Enumeration of errors while loading a [`TemplateRegistry`]
+#[derive(thiserror::Error, Debug)]
+#[non_exhaustive]
+pub enum TemplateRegistryError {
+    #[error("reading the template registry: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("template registry malformatted {0}")]
+    Malformatted(#[from] serde_json::Error),
+}
+
+/// No hash of the current version of the template of a kind is known, so contracts of it can neither be made nor
+/// recognized until it is [loaded](TemplateRegistry::load) or discovered.
+#[derive(thiserror::Error, Debug, Clone, Copy, PartialEq, Eq)]
+#[error("no hash configured for version {version} of the {kind:?} template, add it to the template registry")]
+pub struct UnconfiguredTemplate {
+    pub kind: ContractKind,
+    pub version: u32,
+}
+
+impl Default for TemplateRegistry {
+    fn default() -> Self {
+        Self::builtin()
+    }
+}
+
+impl ContractKind {
+    /// Names of the template pages of the kind in the template namespace, without it.\
+    /// The first one is the page new contracts are made with, the others are recognized as well.
+    pub fn template_names(self) -> &'static [&'static str] {
+        match self {
+            ContractKind::AccessAgreement => &[
+                "DataAccessAgreement",
+                "Data_Access_Agreement",
+                "AccessAgreement",
+            ],
+            ContractKind::GuardianServitude => &["GuardianServitude"],
+            ContractKind::TlsIdentityClaim => &["TlsIdentityClaim"],
+            ContractKind::AccessRevocation => &["AccessRevocation"],
+            ContractKind::ForkResolution => &["ForkResolution"],
+            ContractKind::Group => &["Group"],
+        }
+    }
+
+    /// Name of the template page new contracts are made with.
+    pub fn template_name(self) -> &'static str {
+        self.template_names()[0]
+    }
+
+    /// The kind of contracts made with the template `name`, spaces and underscores are the same.
+    pub fn from_template_name(name: &str) -> Option<ContractKind> {
+        let name = name.replace(' ', "_");
+        ContractKind::ALL
+            .iter()
+            .copied()
+            .find(|kind| kind.template_names().contains(&name.as_str()))
+    }
+}
+
+impl TemplateRegistry {
+    /// Registry knowing no template at all.
+    pub fn empty() -> Self {
+        Self {
+            templates: BTreeMap::new(),
+        }
+    }
+
+    /// Registry knowing the [built-in](ContractKind::builtin_hash) hash of the current version of every kind that has one.
+    pub fn builtin() -> Self {
+        let mut registry = Self::empty();
+        for kind in ContractKind::ALL {
+            if let Some(hash) = kind.builtin_hash() {
+                registry.insert(*kind, kind.current_version(), hash);
+            }
+        }
+        registry
+    }
+
+    /// Reads further hashes from a JSON file mapping kinds to versions to lists of verification hashes, the built-in
+    /// ones are kept.
+    /// ```json
+    /// { "AccessAgreement": { "1": ["…"], "2": ["725c2b99…", "…"] } }
+    /// ```
+    pub fn load(path: impl AsRef<std::path::Path>) -> Result<Self, TemplateRegistryError> {
+        let file: TemplateRegistry = serde_json::from_slice(&std::fs::read(path)?)?;
+        let mut registry = Self::builtin();
+        registry.extend(&file);
+        Ok(registry)
+    }
+
+    /// Adds `hash` as a template of `version` of `kind`, returns false if it was known already.
+    pub fn insert(&mut self, kind: ContractKind, version: u32, hash: Hash) -> bool {
+        if self.version_of(hash).is_some() {
+            return false;
+        }
+        self.templates
+            .entry(kind)
+            .or_default()
+            .entry(version)
+            .or_default()
+            .push(hash);
+        true
+    }
+
+    /// Adds all hashes known to `other`.
+    pub fn extend(&mut self, other: &TemplateRegistry) {
+        for (kind, versions) in &other.templates {
+            for (version, hashes) in versions {
+                for hash in hashes {
+                    self.insert(*kind, *version, *hash);
+                }
+            }
+        }
+    }
+
+    /// The kind and version of contracts made with the template `hash`.
+    pub fn version_of(&self, hash: Hash) -> Option<(ContractKind, u32)> {
+        self.templates.iter().find_map(|(kind, versions)| {
+            versions
+                .iter()
+                .find(|(_version, hashes)| hashes.contains(&hash))
+                .map(|(version, _hashes)| (*kind, *version))
+        })
+    }
+
+    /// The kind of contracts made with the template `hash`.
+    pub fn kind_of(&self, hash: Hash) -> Option<ContractKind> {
+        self.version_of(hash).map(|(kind, _version)| kind)
+    }
+
+    /// Whether a hash of any version of the template of `kind` is known.
+    pub fn knows(&self, kind: ContractKind) -> bool {
+        self.templates
+            .get(&kind)
+            .is_some_and(|versions| versions.values().any(|hashes| !hashes.is_empty()))
+    }
+
+    /// The hash new contracts of `kind` are made with, the latest one known for its current version.
+    pub fn current_hash(&self, kind: ContractKind) -> Result<Hash, UnconfiguredTemplate> {
+        let version = kind.current_version();
+        self.hashes(kind, version)
+            .last()
+            .copied()
+            .ok_or(UnconfiguredTemplate { kind, version })
+    }
+
+    /// The kinds without a hash for their current version, their contracts are not recognized.
+    pub fn unconfigured(&self) -> Vec<UnconfiguredTemplate> {
+        ContractKind::ALL
+            .iter()
+            .filter_map(|kind| self.current_hash(*kind).err())
+            .collect()
+    }
+
+    /// The known hashes of `version` of the template of `kind`, oldest first.
+    pub fn hashes(&self, kind: ContractKind, version: u32) -> &[Hash] {
+        self.templates
+            .get(&kind)
+            .and_then(|versions| versions.get(&version))
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+    }
+}
+
+#[test]
+fn several_versions() {
+    let edited = Hash::from([1; 64]);
+    let old = Hash::from([2; 64]);
+    let mut registry = TemplateRegistry::builtin();
+    let builtin = ContractKind::AccessAgreement.builtin_hash().unwrap();
+    assert_eq!(registry.kind_of(edited), None);
+
+    assert!(registry.insert(ContractKind::AccessAgreement, 2, edited));
+    assert!(!registry.insert(ContractKind::AccessAgreement, 2, edited));
+    assert!(!registry.insert(ContractKind::AccessAgreement, 1, edited));
+    assert!(registry.insert(ContractKind::AccessAgreement, 1, old));
+    assert_eq!(
+        registry.kind_of(edited),
+        Some(ContractKind::AccessAgreement)
+    );
+    assert_eq!(
+        registry.version_of(builtin),
+        Some((ContractKind::AccessAgreement, 2))
+    );
+    assert_eq!(
+        registry.version_of(old),
+        Some((ContractKind::AccessAgreement, 1))
+    );
+    assert_eq!(
+        registry.hashes(ContractKind::AccessAgreement, 2),
+        [builtin, edited]
+    );
+    assert_eq!(
+        registry.current_hash(ContractKind::AccessAgreement),
+        Ok(edited)
+    );
+    assert_eq!(
+        registry.current_hash(ContractKind::AccessRevocation),
+        Err(UnconfiguredTemplate {
+            kind: ContractKind::AccessRevocation,
+            version: 1
+        })
+    );
+
+    let json = serde_json::to_string(&registry).unwrap();
+    let read: TemplateRegistry = serde_json::from_str(&json).unwrap();
+    assert_eq!(read, registry);
+    assert_eq!(
+        ContractKind::from_template_name("ForkResolution"),
+        Some(ContractKind::ForkResolution)
+    );
+    assert_eq!(
+        ContractKind::from_template_name("DataAccessAgreement"),
+        Some(ContractKind::AccessAgreement)
+    );
+    assert_eq!(
+        ContractKind::from_template_name("Data Access Agreement"),
+        Some(ContractKind::AccessAgreement)
+    );
+    assert_eq!(ContractKind::from_template_name("Main Page"), None);
+}
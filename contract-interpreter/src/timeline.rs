@@ -0,0 +1,156 @@
+//! The contract of every revision of exported chains and the effect it has over time.
+//!
+//! Every revision is looked at as the latest of its branch, so a contract that never takes effect shows the
+//! revision where the [sequence numbers](SequencedContract::sequence_number) stop making sense.
+
+use std::collections::HashMap;
+
+use guardian_common::custom_types::Revision as RevisionV1_1;
+
+use super::*;
+
+/// A revision in a [`Timeline`], with the contract it holds and the effect of the contract with it as the latest.
+#[derive(Clone, Debug, serde::Serialize)]
+pub struct Step {
+    pub hash: Hash,
+    pub prev: Option<Hash>,
+    pub merge: Option<Hash>,
+    pub time_stamp: chrono::NaiveDateTime,
+    /// The address that signed the revision.
+    pub signer: Option<Address>,
+    pub contract: Option<Contract>,
+    /// Why a contract template on the page is invalid.
+    pub diagnostic: Option<String>,
+    pub sequence_number: Option<u8>,
+    #[serde(serialize_with = "effect_name")]
+    pub effect: Option<ContractEffect>,
+}
+
+/// The revisions of a chain in order of their time stamps.
+#[derive(Clone, Debug, serde::Serialize)]
+pub struct Timeline {
+    /// The first revision of the chain, its genesis unless the revisions start later.
+    pub genesis: Hash,
+    /// Title of the page of the chain, if it is known.
+    pub title: Option<String>,
+    pub steps: Vec<Step>,
+}
+
+fn effect_name<S: serde::Serializer>(
+    effect: &Option<ContractEffect>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    serde::Serialize::serialize(&effect.as_ref().map(ContractEffect::name), serializer)
+}
+
+/// The previous revision of `hash` among `revisions`.
+fn prev_of(revisions: &HashMap<Hash, RevisionV1_1>, hash: &Hash) -> Option<Hash> {
+    let prev = revisions.get(hash)?.metadata.previous_verification_hash?;
+    revisions.contains_key(&prev).then_some(prev)
+}
+
+/// Splits `revisions` into their chains and interprets every revision by the template versions of `templates`.
+pub fn timelines(
+    revisions: &HashMap<Hash, RevisionV1_1>,
+    templates: &TemplateRegistry,
+) -> Vec<Timeline> {
+    let mut parsed = HashMap::new();
+    for (hash, revision) in revisions {
+        let prev = prev_of(revisions, hash).and_then(|prev| revisions.get(&prev));
+        let merge = revision
+            .metadata
+            .merge_verification_hash
+            .and_then(|merge| revisions.get(&merge));
+        let revision = verifier::v1_2::rev_v1_1_to_rev_v1_2(revision, prev, merge);
+        let step = match Contract::from_revision(&revision, templates) {
+            Ok(contract) => {
+                let sequence_number = contract.sequence_number(&revision);
+                (Some(contract), None, sequence_number)
+            }
+            Err(ContractDiagnostic::NotAContract(_)) => (None, None, None),
+            Err(ContractDiagnostic::Invalid(invalid)) => (None, Some(invalid.to_string()), None),
+        };
+        parsed.insert(*hash, step);
+    }
+
+    let mut chains: HashMap<Hash, Vec<Step>> = HashMap::new();
+    for (hash, revision) in revisions {
+        // the branch ending at `hash`, latest first. bounded in case the previous hashes loop
+        let mut branch = vec![*hash];
+        while let Some(prev) = prev_of(revisions, branch.last().unwrap()) {
+            if branch.len() > revisions.len() {
+                break;
+            }
+            branch.push(prev);
+        }
+        let effect = branch
+            .iter()
+            .map(|hash| {
+                let (contract, _, sequence_number) = &parsed[hash];
+                Some((contract.as_ref()?, *sequence_number))
+            })
+            .collect::<Option<Vec<_>>>()
+            .and_then(|contracts| is_contract_effective(contracts.into_iter()));
+
+        let (contract, diagnostic, sequence_number) = parsed[hash].clone();
+        let genesis = *branch.last().unwrap();
+        chains.entry(genesis).or_default().push(Step {
+            hash: *hash,
+            prev: revision.metadata.previous_verification_hash,
+            merge: revision.metadata.merge_verification_hash,
+            time_stamp: revision.metadata.time_stamp.clone().into(),
+            signer: revision
+                .signature
+                .as_ref()
+                .map(|signature| Address::from(signature.public_key)),
+            contract,
+            diagnostic,
+            sequence_number,
+            effect,
+        });
+    }
+
+    let mut timelines: Vec<Timeline> = chains
+        .into_iter()
+        .map(|(genesis, mut steps)| {
+            steps.sort_by_key(|step| (step.time_stamp, step.hash != genesis, step.hash));
+            Timeline {
+                genesis,
+                title: None,
+                steps,
+            }
+        })
+        .collect();
+    timelines.sort_by_key(|timeline| (timeline.steps[0].time_stamp, timeline.genesis));
+    timelines
+}
+
+#[test]
+fn daa_timeline() {
+    let files = [
+        include_str!("../tests/test_data/DAA_SIG_SENDER_NO_WIT.json"),
+        include_str!("../tests/test_data/DAA_SIG_RECEIVER_NO_WIT.json"),
+    ];
+    let revisions: HashMap<Hash, RevisionV1_1> = files
+        .iter()
+        .map(|file| {
+            let revision: RevisionV1_1 = serde_json::from_str(file).unwrap();
+            (revision.metadata.verification_hash, revision)
+        })
+        .collect();
+
+    let timelines = timelines(&revisions, &TemplateRegistry::builtin());
+    assert_eq!(timelines.len(), 1);
+    let steps = &timelines[0].steps;
+    assert_eq!(steps.len(), 2);
+    assert_eq!(steps[0].hash, timelines[0].genesis);
+    assert!(steps.iter().all(|step| step.contract.is_some()));
+    // signed by the receiver before the sender ever did
+    assert_eq!(steps[0].sequence_number, Some(0));
+    assert_eq!(steps[1].sequence_number, Some(2));
+    assert!(steps.iter().all(|step| step.effect.is_none()));
+
+    let json = serde_json::to_value(&timelines).unwrap();
+    assert_eq!(json[0]["steps"][1]["sequence_number"], 2);
+    assert!(json[0]["steps"][1]["effect"].is_null());
+}
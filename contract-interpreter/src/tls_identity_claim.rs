@@ -1,7 +1,8 @@
 /// Guardian TLS Certificate
-#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Hash, serde::Serialize, serde::Deserialize)]
 pub struct TlsIdentityClaim {
     /// The bytes of a [`webpki::types::CertificateDer<'static>`].
+    #[serde(with = "cert_base64")]
     pub cert: std::sync::Arc<[u8]>,
     /// The address of the Guardian who declares this one of its certificates by signature.
     pub guardian: ethaddr::Address,
@@ -9,6 +10,27 @@ pub struct TlsIdentityClaim {
     pub host: String,
     /// The port on which the Guardian is listening.
     pub port: u16,
+    /// The version of the template it was parsed with.
+    pub template_version: u32,
+}
+
+/// (de)serializes the certificate as base64, like it is written in the contract
+mod cert_base64 {
+    use base64::Engine;
+
+    pub fn serialize<S: serde::Serializer>(cert: &[u8], s: S) -> Result<S::Ok, S::Error> {
+        s.serialize_str(&base64::engine::general_purpose::STANDARD.encode(cert))
+    }
+
+    pub fn deserialize<'de, D: serde::Deserializer<'de>>(
+        d: D,
+    ) -> Result<std::sync::Arc<[u8]>, D::Error> {
+        let encoded: String = serde::Deserialize::deserialize(d)?;
+        base64::engine::general_purpose::STANDARD
+            .decode(encoded)
+            .map(Into::into)
+            .map_err(serde::de::Error::custom)
+    }
 }
 
 impl std::fmt::Debug for TlsIdentityClaim {
@@ -19,7 +41,8 @@ impl std::fmt::Debug for TlsIdentityClaim {
                 &base64::display::Base64Display::new(
                     &self.cert,
                     &base64::engine::general_purpose::STANDARD,
-                ).to_string(),
+                )
+                .to_string(),
             )
             .field("guardian", &self.guardian)
             .field("host", &self.host)
@@ -95,8 +118,22 @@ pub enum TlsIdentityClaimError {
     #[error("guardian address malformatted {0}")]
     PortMalformatted(std::num::ParseIntError),
 
-    #[error("unknown options specified")]
-    AdditionalKeys,
+    #[error("unknown option {0} specified")]
+    AdditionalKeys(String),
+}
+
+impl TlsIdentityClaimError {
+    /// The parameter of the template the error is about.
+    pub fn param(&self) -> Option<&str> {
+        use TlsIdentityClaimError::*;
+        Some(match self {
+            CertMissing | CertNotBase64 | CertMalformatted(_) | CertSubjectMismatch(_) => "file",
+            GuardianMissing | GuardianMalformatted(_) => "guardian",
+            HostMissing => "host",
+            PortMissing | PortMalformatted(_) => "port",
+            AdditionalKeys(key) => key,
+        })
+    }
 }
 
 use super::GenericContractInfo;
@@ -106,6 +143,7 @@ impl TryFrom<GenericContractInfo<'_>> for TlsIdentityClaim {
     /// Tries to generate a Guardian TLS Certificate from the [`GenericContractInfo`].
     fn try_from(info: GenericContractInfo) -> Result<Self, Self::Error> {
         let GenericContractInfo {
+            version,
             mut params,
             // file,
             ..
@@ -138,10 +176,10 @@ impl TryFrom<GenericContractInfo<'_>> for TlsIdentityClaim {
             ))
             .map_err(CertSubjectMismatch)?;
 
-        if !params.is_empty() {
+        if let Some(key) = params.keys().min() {
             // > after all params must be empty, correct?
             // yes.
-            return Err(AdditionalKeys);
+            return Err(AdditionalKeys(key.to_string()));
         }
 
         Ok(TlsIdentityClaim {
@@ -149,10 +187,25 @@ impl TryFrom<GenericContractInfo<'_>> for TlsIdentityClaim {
             host,
             port,
             cert: cert_file.to_vec().into(),
+            template_version: version,
         })
     }
 }
 
+impl super::TemplateParams for TlsIdentityClaim {
+    fn template_params(&self) -> Vec<(&'static str, String)> {
+        vec![
+            ("guardian", self.guardian.to_string()),
+            (
+                "file",
+                guardian_common::custom_types::Base64::from(self.cert.to_vec()).to_string(),
+            ),
+            ("host", self.host.clone()),
+            ("port", self.port.to_string()),
+        ]
+    }
+}
+
 #[test]
 fn address_is_dnsname() {
     webpki::types::DnsName::try_from("0x8B6488E003B81ecF69f108f7D10A62eB1D40afb6")
@@ -0,0 +1,300 @@
+//! Parsing of the templates in MediaWiki text, as contracts are written on PKC pages.
+//!
+//! A page may hold several templates and any text around them. Parameters are split at the `|`s of their template
+//! only, the ones of nested templates and links stay in the value. Named parameters are split at their first `=`
+//! and trimmed like MediaWiki does, positional ones are kept as they are.
+//!
+//! The characters `|`, `{`, `}`, `[` and `]` are written as `{{|}}`, `{{(}}`, `{{)}}`, `{{!(}}` and `{{)!}}` inside
+//! values, see [`escape`], so a value never opens a template or link swallowing the parameters after it.
+//! `{{!}}` and `{{=}}` are read as `|` and `=` as well.
+
+use std::{borrow::Cow, ops::Range};
+
+/// Escape templates and the characters they stand for. The first ones are written by [`escape`].
+const ESCAPES: [(&str, &str); 7] = [
+    ("{{|}}", "|"),
+    ("{{(}}", "{"),
+    ("{{)}}", "}"),
+    ("{{!(}}", "["),
+    ("{{)!}}", "]"),
+    ("{{!}}", "|"),
+    ("{{=}}", "="),
+];
+
+/// A template in MediaWiki text, with the byte ranges of its parts in the text.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Template<'a> {
+    /// The name of the template, trimmed.
+    pub name: &'a str,
+    pub name_span: Range<usize>,
+    /// The parameters in order of appearance.
+    pub params: Vec<Param<'a>>,
+    /// From the opening `{{` to the closing `}}`.
+    pub span: Range<usize>,
+}
+
+/// A parameter of a [`Template`].
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Param<'a> {
+    /// The name of a named parameter, trimmed. Positional parameters have none.
+    pub name: Option<&'a str>,
+    pub name_span: Option<Range<usize>>,
+    /// The value with the escapes [resolved](unescape), trimmed for named parameters.
+    pub value: Cow<'a, str>,
+    /// The value as written.
+    pub value_span: Range<usize>,
+    /// Everything between the `|` before and the `|` or `}}` after the parameter.
+    pub span: Range<usize>,
+}
+
+/// Enumeration of errors in MediaWiki text
+#[derive(thiserror::Error, Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum WikitextError {
+    #[error("template opened at byte {0} is not closed")]
+    Unclosed(usize),
+    #[error("template at byte {0} has no name")]
+    NameMissing(usize),
+}
+
+impl<'a> Template<'a> {
+    /// The named parameter `name`, the last one counts if it is given several times like in MediaWiki.
+    pub fn get(&self, name: &str) -> Option<&Param<'a>> {
+        self.params
+            .iter()
+            .rev()
+            .find(|param| param.name == Some(name))
+    }
+
+    /// The parameters without a name, the first one is `1` in MediaWiki.
+    pub fn positional(&self) -> impl Iterator<Item = &Param<'a>> {
+        self.params.iter().filter(|param| param.name.is_none())
+    }
+}
+
+/// The escape template at the start of `text`, with the character it stands for.
+fn escape_at(text: &[u8]) -> Option<(&'static str, &'static str)> {
+    ESCAPES
+        .into_iter()
+        .find(|(escaped, _)| text.starts_with(escaped.as_bytes()))
+}
+
+/// Parses all templates in `text` outside of other templates. Text around them is skipped.
+pub fn templates(text: &str) -> Result<Vec<Template<'_>>, WikitextError> {
+    let bytes = text.as_bytes();
+    let mut templates = vec![];
+    let mut i = 0;
+    while i < bytes.len() {
+        if let Some((escaped, _)) = escape_at(&bytes[i..]) {
+            i += escaped.len();
+        } else if bytes[i..].starts_with(b"{{") {
+            let template = template(text, i)?;
+            i = template.span.end;
+            templates.push(template);
+        } else {
+            i += 1;
+        }
+    }
+    Ok(templates)
+}
+
+/// Parses the template opened by the `{{` at `start`.
+fn template(text: &str, start: usize) -> Result<Template<'_>, WikitextError> {
+    let bytes = text.as_bytes();
+    // the parts between the `|`s, each with the position of its first `=`
+    let mut parts: Vec<(Range<usize>, Option<usize>)> = vec![];
+    let mut part_start = start + 2;
+    let mut equals = None;
+    let (mut templates, mut links) = (0usize, 0usize);
+    let mut i = part_start;
+    let end = loop {
+        let rest = &bytes[i..];
+        if rest.is_empty() {
+            return Err(WikitextError::Unclosed(start));
+        }
+        // all tokens are ASCII, so `i` only stops at char boundaries when one is found
+        if let Some((escaped, _)) = escape_at(rest) {
+            i += escaped.len();
+            continue;
+        }
+        if rest.starts_with(b"{{") {
+            templates += 1;
+            i += 2;
+        } else if rest.starts_with(b"}}") {
+            if templates == 0 {
+                parts.push((part_start..i, equals));
+                break i + 2;
+            }
+            templates -= 1;
+            i += 2;
+        } else if rest.starts_with(b"[[") {
+            links += 1;
+            i += 2;
+        } else if rest.starts_with(b"]]") && links > 0 {
+            links -= 1;
+            i += 2;
+        } else if templates == 0 && links == 0 && rest[0] == b'|' {
+            parts.push((part_start..i, equals.take()));
+            i += 1;
+            part_start = i;
+        } else {
+            if templates == 0 && links == 0 && rest[0] == b'=' && equals.is_none() {
+                equals = Some(i);
+            }
+            i += 1;
+        }
+    };
+
+    let mut parts = parts.into_iter();
+    let (name_part, _) = parts.next().expect("the name is always pushed");
+    let name_span = trim(text, name_part);
+    if name_span.is_empty() {
+        return Err(WikitextError::NameMissing(start));
+    }
+    let params = parts
+        .map(|(span, equals)| match equals {
+            Some(equals) => {
+                let name_span = trim(text, span.start..equals);
+                let value_span = trim(text, equals + 1..span.end);
+                Param {
+                    name: Some(&text[name_span.clone()]),
+                    name_span: Some(name_span),
+                    value: unescape(&text[value_span.clone()]),
+                    value_span,
+                    span,
+                }
+            }
+            None => Param {
+                name: None,
+                name_span: None,
+                value: unescape(&text[span.clone()]),
+                value_span: span.clone(),
+                span,
+            },
+        })
+        .collect();
+    Ok(Template {
+        name: &text[name_span.clone()],
+        name_span,
+        params,
+        span: start..end,
+    })
+}
+
+/// `span` without the whitespace around it.
+fn trim(text: &str, span: Range<usize>) -> Range<usize> {
+    let part = &text[span.clone()];
+    let start = span.start + (part.len() - part.trim_start().len());
+    let end = span.end - (part.len() - part.trim_end().len());
+    start..end.max(start)
+}
+
+/// Writes `value` so it can be the value of a template parameter, the inverse of [`unescape`].
+pub fn escape(value: &str) -> Cow<'_, str> {
+    if !value.contains(['|', '{', '}', '[', ']']) {
+        return Cow::Borrowed(value);
+    }
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '|' => escaped += "{{|}}",
+            '{' => escaped += "{{(}}",
+            '}' => escaped += "{{)}}",
+            '[' => escaped += "{{!(}}",
+            ']' => escaped += "{{)!}}",
+            c => escaped.push(c),
+        }
+    }
+    Cow::Owned(escaped)
+}
+
+/// Resolves the escape templates in the value of a template parameter, anything else is kept as written.
+pub fn unescape(value: &str) -> Cow<'_, str> {
+    if !value.contains("{{") {
+        return Cow::Borrowed(value);
+    }
+    let mut unescaped = String::with_capacity(value.len());
+    let mut rest = value;
+    while let Some(at) = rest.find("{{") {
+        unescaped += &rest[..at];
+        rest = &rest[at..];
+        match escape_at(rest.as_bytes()) {
+            Some((escaped, c)) => {
+                unescaped += c;
+                rest = &rest[escaped.len()..];
+            }
+            None => {
+                unescaped += "{";
+                rest = &rest[1..];
+            }
+        }
+    }
+    unescaped += rest;
+    Cow::Owned(unescaped)
+}
+
+#[test]
+fn params_with_spans() {
+    let text = "Intro {{Note|careful}}\n{{ AccessAgreement\n| sender = 0x01 \n|terms=a=b {{Bold|[[Page|x]]}}\n}} outro";
+    let templates = templates(text).unwrap();
+    assert_eq!(templates.len(), 2);
+    assert_eq!(templates[0].name, "Note");
+    assert_eq!(templates[0].positional().next().unwrap().value, "careful");
+
+    let agreement = &templates[1];
+    assert_eq!(agreement.name, "AccessAgreement");
+    assert_eq!(&text[agreement.span.clone()], &text[23..text.len() - 6]);
+    let sender = agreement.get("sender").unwrap();
+    assert_eq!(sender.value, "0x01");
+    assert_eq!(&text[sender.name_span.clone().unwrap()], "sender");
+    assert_eq!(&text[sender.value_span.clone()], "0x01");
+    assert_eq!(&text[sender.span.clone()], " sender = 0x01 \n");
+    assert_eq!(
+        agreement.get("terms").unwrap().value,
+        "a=b {{Bold|[[Page|x]]}}"
+    );
+}
+
+#[test]
+fn escapes_are_symmetric() {
+    let values = [
+        "plain",
+        "a|b",
+        "{{Bold|x}}",
+        "}{",
+        "{{|}}",
+        "{{(}}",
+        "[[Page|x]]",
+        "][",
+        "{{!(}}",
+    ];
+    for value in values {
+        assert_eq!(unescape(&escape(value)), value);
+        let text = format!("{{{{T\n|terms={}\n}}}}", escape(value));
+        let templates = templates(&text).unwrap();
+        assert_eq!(templates[0].get("terms").unwrap().value, value);
+    }
+    assert_eq!(unescape("a{{!}}b{{=}}c"), "a|b=c");
+}
+
+#[test]
+fn malformed() {
+    assert_eq!(templates("{{T|a={{B}}"), Err(WikitextError::Unclosed(0)));
+    assert_eq!(templates("x {{ |a}}"), Err(WikitextError::NameMissing(2)));
+    assert_eq!(templates("}} {{!}} no templates").unwrap(), vec![]);
+}
+
+#[test]
+fn unbalanced_links_stay_in_their_value() {
+    for terms in ["see [[Policy", "see ]] and [[Policy|x", "[[a]] [["] {
+        let text = format!(
+            "{{{{T\n|terms={}\n|may_reshare=true\n|valid_until=20240201000000\n}}}}",
+            escape(terms)
+        );
+        let templates = templates(&text).unwrap();
+        assert_eq!(templates.len(), 1);
+        assert_eq!(templates[0].get("terms").unwrap().value, terms);
+        assert_eq!(templates[0].get("may_reshare").unwrap().value, "true");
+        assert!(templates[0].get("valid_until").is_some());
+    }
+}
@@ -93,7 +93,7 @@ fn _test_list_revisions<'a>(
     let mut rec_vec: Vec<_> = Vec::new();
 
     for rev in revision {
-        let contract = Contract::from_revision(rev)?.ok()?;
+        let contract = Contract::from_revision(rev, &TemplateRegistry::builtin()).ok()?;
         let state = Contract::sequence_number(&contract, rev);
         println!("contract : {:?} \n state: {:?}", contract, state);
         rec_vec.push((contract, state));
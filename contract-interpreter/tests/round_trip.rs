@@ -0,0 +1,239 @@
+use contract_interpreter::*;
+use guardian_common::prelude::{Address, Hash};
+use proptest::prelude::*;
+
+/// the built-in templates, with made up hashes for the ones that are not published yet
+fn templates() -> TemplateRegistry {
+    let mut templates = TemplateRegistry::builtin();
+    for (n, unconfigured) in (1..).zip(templates.unconfigured()) {
+        templates.insert(unconfigured.kind, unconfigured.version, Hash::from([n; 64]));
+    }
+    templates
+}
+
+/// the contract parsed from a revision with the content made for `contract`
+fn round_trip(contract: &Contract) -> Contract {
+    let templates = templates();
+    let revision = verifier::v1_2::Revision {
+        verification_hash: Hash::default(),
+        content: contract
+            .make_content(&templates)
+            .expect("template configured"),
+        metadata: verifier::v1_2::RevisionMetadata {
+            metadata_hash: Hash::default(),
+            domain_id: "42".to_string(),
+            timestamp: "20240601000000".parse().unwrap(),
+        },
+        prev: None,
+        merge: None,
+    };
+    Contract::from_revision(&revision, &templates).expect("made content parses")
+}
+
+fn address() -> impl Strategy<Value = Address> {
+    any::<[u8; 20]>().prop_map(Address)
+}
+
+fn hash() -> impl Strategy<Value = Hash> {
+    (any::<[u8; 32]>(), any::<[u8; 32]>()).prop_map(|(a, b)| {
+        let mut bytes = [0; 64];
+        bytes[..32].copy_from_slice(&a);
+        bytes[32..].copy_from_slice(&b);
+        Hash::from(bytes)
+    })
+}
+
+/// pieces of MediaWiki markup, which arbitrary text hardly ever contains
+fn markup() -> impl Strategy<Value = String> {
+    let tokens = prop::sample::select(vec![
+        "[[", "]]", "{{", "}}", "|", "=", "[", "]", "{{!}}", "{{=}}", "{{|}}", "{{(}}", "{{)}}",
+        "{{!(}}", "{{)!}}", "\n",
+    ]);
+    let piece = prop_oneof![tokens.prop_map(String::from), "[a-z ]{1,3}"];
+    prop::collection::vec(piece, 0..10).prop_map(|pieces| pieces.concat())
+}
+
+/// any text, without the whitespace around it that MediaWiki trims from parameters
+fn text() -> impl Strategy<Value = String> {
+    prop_oneof!["\\PC*", "[\\[\\]{}|=a ]*", markup()].prop_map(|text| text.trim().to_string())
+}
+
+/// timestamps of revisions have whole seconds
+fn time() -> impl Strategy<Value = chrono::NaiveDateTime> {
+    (0i64..253_402_300_799).prop_map(|secs| {
+        chrono::DateTime::from_timestamp(secs, 0)
+            .unwrap()
+            .naive_utc()
+    })
+}
+
+fn access_agreement() -> impl Strategy<Value = Contract> {
+    let receiver = prop_oneof![
+        address().prop_map(Principal::User),
+        hash().prop_map(Principal::Group),
+    ];
+    // page names are stored as MediaWiki db keys, spaces written as underscores
+    let pages = prop::collection::btree_map("Page_[A-Za-z0-9_:.,-]{0,12}", hash(), 0..4);
+    let page_pattern = (any::<i32>(), "[A-Za-z0-9_/:]{0,8}")
+        .prop_map(|(namespace, prefix)| PagePattern { namespace, prefix });
+    (
+        address(),
+        receiver,
+        pages,
+        prop::option::of(page_pattern),
+        prop::option::of(text()),
+        prop::option::of(time()),
+        prop::option::of(time()),
+        any::<bool>(),
+    )
+        .prop_map(
+            |(
+                sender,
+                receiver,
+                pages,
+                page_pattern,
+                terms,
+                valid_from,
+                valid_until,
+                may_reshare,
+            )| {
+                Contract::AccessAgreement(AccessAgreement {
+                    sender,
+                    receiver,
+                    pages: pages.into_iter().collect(),
+                    page_pattern,
+                    terms,
+                    valid_from,
+                    valid_until,
+                    may_reshare,
+                    template_version: ContractKind::AccessAgreement.current_version(),
+                })
+            },
+        )
+}
+
+fn guardian_servitude() -> impl Strategy<Value = Contract> {
+    (address(), address()).prop_map(|(guardian, user)| {
+        Contract::GuardianServitude(GuardianServitude {
+            guardian,
+            user,
+            template_version: ContractKind::GuardianServitude.current_version(),
+        })
+    })
+}
+
+/// a certificate has to name its guardian, making one takes a while so there is only one
+fn tls_identity_claim() -> impl Strategy<Value = Contract> {
+    static CERT: std::sync::OnceLock<(Address, std::sync::Arc<[u8]>)> = std::sync::OnceLock::new();
+    let (guardian, cert) = CERT
+        .get_or_init(|| {
+            let guardian = Address([7; 20]);
+            let params = rcgen::CertificateParams::new([guardian.to_string()]).unwrap();
+            let key_pair = rcgen::KeyPair::generate().unwrap();
+            let cert = params.self_signed(&key_pair).unwrap();
+            (guardian, cert.der().to_vec().into())
+        })
+        .clone();
+    (text(), any::<u16>()).prop_map(move |(host, port)| {
+        Contract::TlsIdentityClaim(TlsIdentityClaim {
+            cert: cert.clone(),
+            guardian,
+            host,
+            port,
+            template_version: ContractKind::TlsIdentityClaim.current_version(),
+        })
+    })
+}
+
+fn access_revocation() -> impl Strategy<Value = Contract> {
+    (address(), hash()).prop_map(|(sender, agreement)| {
+        Contract::AccessRevocation(AccessRevocation {
+            sender,
+            agreement,
+            template_version: ContractKind::AccessRevocation.current_version(),
+        })
+    })
+}
+
+fn fork_resolution() -> impl Strategy<Value = Contract> {
+    (address(), hash(), hash()).prop_map(|(sender, fork, tip)| {
+        Contract::ForkResolution(ForkResolution {
+            sender,
+            fork,
+            tip,
+            template_version: ContractKind::ForkResolution.current_version(),
+        })
+    })
+}
+
+fn group() -> impl Strategy<Value = Contract> {
+    (address(), prop::collection::vec(address(), 0..5)).prop_map(|(owner, members)| {
+        Contract::Group(Group {
+            owner,
+            members,
+            template_version: ContractKind::Group.current_version(),
+        })
+    })
+}
+
+proptest! {
+    #[test]
+    fn access_agreements(contract in access_agreement()) {
+        prop_assert_eq!(round_trip(&contract), contract);
+    }
+
+    #[test]
+    fn guardian_servitudes(contract in guardian_servitude()) {
+        prop_assert_eq!(round_trip(&contract), contract);
+    }
+
+    #[test]
+    fn tls_identity_claims(contract in tls_identity_claim()) {
+        prop_assert_eq!(round_trip(&contract), contract);
+    }
+
+    #[test]
+    fn access_revocations(contract in access_revocation()) {
+        prop_assert_eq!(round_trip(&contract), contract);
+    }
+
+    #[test]
+    fn fork_resolutions(contract in fork_resolution()) {
+        prop_assert_eq!(round_trip(&contract), contract);
+    }
+
+    #[test]
+    fn groups(contract in group()) {
+        prop_assert_eq!(round_trip(&contract), contract);
+    }
+}
+
+/// links and unbalanced brackets in the terms must not swallow the parameters after them
+#[test]
+fn terms_with_links() {
+    for terms in [
+        "see [[Policy",
+        "see [[Policy|the policy]]",
+        "]] before [[",
+        "[[a]] [[b|c]] ]]",
+        "{{Bold|[[x]]}}",
+        "[http://example.org link]",
+    ] {
+        let contract = Contract::AccessAgreement(AccessAgreement {
+            sender: Address([1; 20]),
+            receiver: Principal::User(Address([2; 20])),
+            pages: vec![("Main_Page".to_string(), Hash::from([3; 64]))],
+            page_pattern: None,
+            terms: Some(terms.to_string()),
+            valid_from: None,
+            valid_until: Some(
+                chrono::DateTime::from_timestamp(1_706_745_600, 0)
+                    .unwrap()
+                    .naive_utc(),
+            ),
+            may_reshare: true,
+            template_version: ContractKind::AccessAgreement.current_version(),
+        });
+        assert_eq!(round_trip(&contract), contract, "terms {terms:?}");
+    }
+}
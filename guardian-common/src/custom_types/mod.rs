@@ -27,7 +27,7 @@ import! {
 
 fn from_hex<const SIZE: usize>(s: &str) -> Option<[u8; SIZE]> {
     // make sure it has the correct length (2 characters per byte) and that it is only valic characters
-    if !s.as_bytes().len() == SIZE * 2 || !s.is_ascii() {
+    if !s.len() == SIZE * 2 || !s.is_ascii() {
         return None;
     }
     let mut data = [0u8; SIZE];
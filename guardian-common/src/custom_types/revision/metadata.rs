@@ -34,6 +34,9 @@ pub struct RevisionMetadata {
     // todo! remove, this is an abomination
     #[serde(deserialize_with = "opt_hash_de", serialize_with = "opt_hash_ser")]
     pub previous_verification_hash: Option<Hash>,
+    /// the other parent of a merge revision, joining its branch into the one of `previous_verification_hash`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub merge_verification_hash: Option<Hash>,
     pub metadata_hash: Hash,
     pub verification_hash: Hash,
 }
@@ -47,6 +50,8 @@ pub struct ExportRevisionMetadata {
     // todo! remove, this is an abomination
     #[serde(deserialize_with = "opt_hash_de", serialize_with = "opt_hash_ser")]
     pub previous_verification_hash: Option<Hash>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub merge_verification_hash: Option<Hash>,
     pub metadata_hash: Hash,
     pub signature: Option<super::signature::RevisionSignature>,
     pub witness: Option<super::witness::RevisionWitness>,
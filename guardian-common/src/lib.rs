@@ -66,11 +66,46 @@ pub mod auth {
 pub mod storage {
     use std::{fmt::Debug, future::Future};
 
+    use futures::{Stream, StreamExt};
+
     use crate::custom_types::*;
 
+    /// what happened to a revision in a [`Storage`]
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+    #[non_exhaustive]
+    pub enum StorageEvent {
+        /// the revision was added by the owner of the storage
+        Created,
+        /// the revision was received from someone else and waits in the inbox
+        ImportedIntoInbox,
+        /// the revision was removed
+        Deleted,
+        /// the page the revision belongs to was renamed, the revision itself is unchanged
+        Moved,
+    }
+
+    /// a single change reported by [`Storage::changes`]
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    pub struct StorageChange<Cursor> {
+        pub hash: Hash,
+        pub event: StorageEvent,
+        /// pass this to [`Storage::changes`] to continue after this change
+        pub cursor: Cursor,
+    }
+
+    /// the page a revision belongs to, as told by [`Storage::get_context`]
+    pub trait PageContext {
+        /// the MediaWiki namespace number
+        fn namespace(&self) -> i32;
+        /// the title without the namespace
+        fn title(&self) -> &str;
+    }
+
     pub trait Storage: Sized {
         type Error: std::error::Error + Debug;
         type Context;
+        /// position in the changes of a storage, see [`Storage::changes`]
+        type Cursor: Clone + Debug + Send;
 
         fn get_context(
             &self,
@@ -91,10 +126,49 @@ pub mod storage {
             hash: Hash,
         ) -> impl Future<Output = Result<Branch<Self::Context>, Self::Error>> + Send;
         fn list(&self) -> impl Future<Output = Result<Vec<Hash>, Self::Error>> + Send;
-        fn update_handler<F: Fn(Hash, String) + Send + Sync>(
+        /// all changes after `since`, or since the creation of the storage when `since` is `None`
+        ///
+        /// the stream never ends, it waits for new changes instead. errors are passed on and the stream carries on
+        /// after them, so it is up to the consumer to give up or retry. changes are delivered at least once,
+        /// resuming from a cursor may repeat some of the changes before it.
+        fn changes(
+            &self,
+            since: Option<Self::Cursor>,
+        ) -> impl Stream<Item = Result<StorageChange<Self::Cursor>, Self::Error>> + Send + '_;
+        /// a cursor at the current end of [`Storage::changes`], resuming from it skips everything that happened so far
+        fn latest_cursor(&self) -> impl Future<Output = Result<Self::Cursor, Self::Error>> + Send;
+        /// whether trying again may succeed, like after a lost connection. a missing revision stays missing
+        ///
+        /// storages that cannot tell count every error as transient
+        fn is_transient(error: &Self::Error) -> bool {
+            let _ = error;
+            true
+        }
+        /// puts every change from [`Storage::changes`] into the given function, errors are logged and skipped
+        fn update_handler<F: Fn(Hash, StorageEvent) + Send + Sync>(
             &self,
             f: F,
-        ) -> impl Future<Output = Result<std::convert::Infallible, Self::Error>> + Send;
+        ) -> impl Future<Output = Result<std::convert::Infallible, Self::Error>> + Send
+        where
+            Self: Sync,
+            Self::Error: Send,
+        {
+            async move {
+                let mut cursor = None;
+                loop {
+                    let mut changes = std::pin::pin!(self.changes(cursor.clone()));
+                    while let Some(change) = changes.next().await {
+                        match change {
+                            Ok(change) => {
+                                f(change.hash, change.event);
+                                cursor = Some(change.cursor);
+                            }
+                            Err(e) => eprintln!("error while waiting for changes: {e}... retrying"),
+                        }
+                    }
+                }
+            }
+        }
     }
 }
 
@@ -0,0 +1,223 @@
+use std::{
+    num::NonZeroUsize,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+};
+
+use futures::StreamExt;
+use guardian_common::{
+    custom_types::*,
+    storage::{Storage, StorageChange, StorageEvent},
+};
+use lru::LruCache;
+use parking_lot::Mutex;
+
+/// how many revisions and branches are cached by [`CachedStorage::new`]
+const DEFAULT_CAPACITY: NonZeroUsize = match NonZeroUsize::new(1024) {
+    Some(capacity) => capacity,
+    None => unreachable!(),
+};
+
+/// hit and miss counters of a [`CachedStorage`]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CacheStats {
+    pub revision_hits: u64,
+    pub revision_misses: u64,
+    pub branch_hits: u64,
+    pub branch_misses: u64,
+}
+
+#[derive(Debug)]
+struct Cache<Context> {
+    revisions: Mutex<LruCache<Hash, Revision>>,
+    /// branches keyed by their latest hash
+    branches: Mutex<LruCache<Hash, Branch<Context>>>,
+    revision_hits: AtomicU64,
+    revision_misses: AtomicU64,
+    branch_hits: AtomicU64,
+    branch_misses: AtomicU64,
+}
+
+/// Wraps any [`Storage`] with an LRU cache of revisions and memoized branches
+///
+/// Revisions are immutable, so a revision read once never has to be read again. The same goes for a branch ending in a
+/// given latest hash, it only changes when one of its revisions gets deleted or its page gets moved.
+/// Those events are picked up from [`changes`](Storage::changes) (and therefore the
+/// [`update_handler`](Storage::update_handler)), so the cache only stays correct while they are consumed.
+///
+/// Clones share the same cache.
+///
+/// # Example
+///
+/// ```
+/// # #[tokio::main(flavor = "current_thread")]
+/// # async fn main() -> local_storage::Result<()> {
+/// use local_storage::prelude::*;
+///
+/// let storage = CachedStorage::new(MemoryStorage::new());
+/// let rev = guardian_common::custom_types::Revision::default();
+/// let hash = rev.metadata.verification_hash;
+/// # let context = RevContext { namespace: 0, name: "Main_Page".into(), genesis_hash: hash, domain_id: "42".into() };
+/// storage.store(rev, context).await?;
+///
+/// storage.read(hash).await?;
+/// assert_eq!(storage.stats().revision_hits, 1);
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct CachedStorage<S: Storage> {
+    inner: S,
+    cache: Arc<Cache<S::Context>>,
+}
+
+impl<S: Storage + Clone> Clone for CachedStorage<S> {
+    fn clone(&self) -> Self {
+        CachedStorage {
+            inner: self.inner.clone(),
+            cache: self.cache.clone(),
+        }
+    }
+}
+
+impl<S: Storage> CachedStorage<S> {
+    /// caches up to 1024 revisions and 1024 branches
+    pub fn new(inner: S) -> Self {
+        CachedStorage::with_capacity(inner, DEFAULT_CAPACITY, DEFAULT_CAPACITY)
+    }
+    pub fn with_capacity(inner: S, revisions: NonZeroUsize, branches: NonZeroUsize) -> Self {
+        CachedStorage {
+            inner,
+            cache: Arc::new(Cache {
+                revisions: Mutex::new(LruCache::new(revisions)),
+                branches: Mutex::new(LruCache::new(branches)),
+                revision_hits: Default::default(),
+                revision_misses: Default::default(),
+                branch_hits: Default::default(),
+                branch_misses: Default::default(),
+            }),
+        }
+    }
+    /// the wrapped storage, changes made directly to it are only noticed through [`changes`](Storage::changes)
+    pub fn inner(&self) -> &S {
+        &self.inner
+    }
+    pub fn stats(&self) -> CacheStats {
+        CacheStats {
+            revision_hits: self.cache.revision_hits.load(Ordering::Relaxed),
+            revision_misses: self.cache.revision_misses.load(Ordering::Relaxed),
+            branch_hits: self.cache.branch_hits.load(Ordering::Relaxed),
+            branch_misses: self.cache.branch_misses.load(Ordering::Relaxed),
+        }
+    }
+
+    /// forgets everything that might be outdated by `change`
+    fn invalidate(&self, change: &StorageChange<S::Cursor>) {
+        match change.event {
+            StorageEvent::Deleted => {
+                self.cache.revisions.lock().pop(&change.hash);
+                self.invalidate_branches(change.hash);
+            }
+            // the revisions stay the same, but the branches carry the page name in their context
+            StorageEvent::Moved => self.invalidate_branches(change.hash),
+            _ => {}
+        }
+    }
+
+    fn invalidate_branches(&self, hash: Hash) {
+        let mut branches = self.cache.branches.lock();
+        let outdated: Vec<Hash> = branches
+            .iter()
+            .filter(|(_, branch)| branch.hashes.contains(&hash))
+            .map(|(latest, _)| *latest)
+            .collect();
+        for latest in outdated {
+            branches.pop(&latest);
+        }
+    }
+}
+
+impl<S> Storage for CachedStorage<S>
+where
+    S: Storage + Sync,
+    S::Context: Clone + Send + Sync,
+{
+    type Error = S::Error;
+    type Context = S::Context;
+    type Cursor = S::Cursor;
+
+    /// not cached, the context of a page may change when it is moved
+    fn get_context(
+        &self,
+        hash: Hash,
+    ) -> impl std::future::Future<Output = Result<Self::Context, Self::Error>> + Send {
+        self.inner.get_context(hash)
+    }
+
+    /// stores through to the wrapped storage and keeps the revision cached
+    async fn store(&self, rev: Revision, context: Self::Context) -> Result<(), Self::Error> {
+        let cached = rev.clone();
+        self.inner.store(rev, context).await?;
+        self.cache
+            .revisions
+            .lock()
+            .put(cached.metadata.verification_hash, cached);
+        Ok(())
+    }
+
+    async fn read(&self, hash: Hash) -> Result<Revision, Self::Error> {
+        let cached = self.cache.revisions.lock().get(&hash).cloned();
+        if let Some(revision) = cached {
+            self.cache.revision_hits.fetch_add(1, Ordering::Relaxed);
+            return Ok(revision);
+        }
+        self.cache.revision_misses.fetch_add(1, Ordering::Relaxed);
+
+        let revision = self.inner.read(hash).await?;
+        self.cache.revisions.lock().put(hash, revision.clone());
+        Ok(revision)
+    }
+
+    async fn get_branch(&self, hash: Hash) -> Result<Branch<Self::Context>, Self::Error> {
+        let cached = self.cache.branches.lock().get(&hash).cloned();
+        if let Some(branch) = cached {
+            self.cache.branch_hits.fetch_add(1, Ordering::Relaxed);
+            return Ok(branch);
+        }
+        self.cache.branch_misses.fetch_add(1, Ordering::Relaxed);
+
+        let branch = self.inner.get_branch(hash).await?;
+        self.cache.branches.lock().put(hash, branch.clone());
+        Ok(branch)
+    }
+
+    /// not cached, the latest revisions change all the time
+    fn list(&self) -> impl std::future::Future<Output = Result<Vec<Hash>, Self::Error>> + Send {
+        self.inner.list()
+    }
+
+    /// the changes of the wrapped storage, invalidating the cache on the way
+    fn changes(
+        &self,
+        since: Option<Self::Cursor>,
+    ) -> impl futures::Stream<Item = Result<StorageChange<Self::Cursor>, Self::Error>> + Send + '_
+    {
+        self.inner.changes(since).inspect(move |change| {
+            if let Ok(change) = change {
+                self.invalidate(change);
+            }
+        })
+    }
+
+    fn latest_cursor(
+        &self,
+    ) -> impl std::future::Future<Output = Result<Self::Cursor, Self::Error>> + Send {
+        self.inner.latest_cursor()
+    }
+
+    fn is_transient(error: &Self::Error) -> bool {
+        S::is_transient(error)
+    }
+}
@@ -0,0 +1,31 @@
+use guardian_common::custom_types::Hash;
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error("io error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("json parsing error: {0}")]
+    Json(#[from] serde_json::Error),
+    #[error("sqlite error: {0}")]
+    Sqlite(#[from] rusqlite::Error),
+    #[error("revision not found: {0}")]
+    NotFound(Hash),
+    #[error("file of revision {0} holds a revision with a different verification_hash")]
+    HashMismatch(Hash),
+}
+
+impl Error {
+    /// io and database errors may go away, a missing or broken revision does not
+    pub fn is_transient(&self) -> bool {
+        matches!(self, Error::Io(_) | Error::Sqlite(_))
+    }
+}
+
+/// errors of an [`Outbox`](crate::outbox::Outbox), either from the wrapped storage or from its journal
+#[derive(thiserror::Error, Debug)]
+pub enum OutboxError<E: std::error::Error> {
+    #[error(transparent)]
+    Storage(E),
+    #[error("outbox journal error: {0}")]
+    Journal(#[from] Error),
+}
@@ -0,0 +1,248 @@
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+use guardian_common::{
+    custom_types::*,
+    storage::{StorageChange, StorageEvent},
+};
+use pkc_api::storage::RevContext;
+
+use crate::{error::Error, Result};
+
+/// the content of a single revision file, the file is named after `revision`'s verification hash
+#[derive(Debug, serde::Deserialize, serde::Serialize, Clone)]
+struct StoredRevision {
+    context: RevContext,
+    revision: Revision,
+}
+
+/// Directory [implementing](crate::fs::FsStorage#impl-Storage-for-FsStorage) the [`Storage`](guardian_common::storage::Storage) trait
+///
+/// Every revision is kept in its own file `<verification_hash>.json` directly inside the directory, together with the
+/// [`RevContext`] it was stored with. Revisions are immutable, so the file name is all that is needed to find one.
+/// Files not matching that pattern are ignored, which means other tools may put things next to the revisions.
+///
+/// # Example
+///
+/// ```no_run
+/// # async fn example() -> local_storage::Result<()> {
+/// use local_storage::prelude::*;
+///
+/// let storage = FsStorage::new("revisions")?;
+/// for latest in storage.list().await? {
+///     let branch = storage.get_branch(latest).await?;
+///     println!("{}: {} revisions", branch.metadata.name, branch.hashes.len());
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct FsStorage {
+    creation: SystemTime,
+    root: PathBuf,
+}
+
+impl FsStorage {
+    /// opens the directory at `root`, creating it if it does not exist yet
+    pub fn new(root: impl Into<PathBuf>) -> Result<Self> {
+        let root = root.into();
+        std::fs::create_dir_all(&root)?;
+        Ok(FsStorage::new_with_options(SystemTime::now(), root))
+    }
+    /// `creation` works like in [`Pkc`](pkc_api::Pkc::new_with_options): the changes start with all revisions written after it.
+    pub fn new_with_options(creation: SystemTime, root: PathBuf) -> Self {
+        FsStorage { creation, root }
+    }
+    /// the directory the revisions are kept in
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    fn revision_path(&self, hash: Hash) -> PathBuf {
+        self.root.join(format!("{}.json", hash.to_stackstr()))
+    }
+
+    async fn read_stored(&self, hash: Hash) -> Result<StoredRevision> {
+        let data = match tokio::fs::read(self.revision_path(hash)).await {
+            Ok(data) => data,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                return Err(Error::NotFound(hash))
+            }
+            Err(e) => return Err(e.into()),
+        };
+        let stored: StoredRevision = serde_json::from_slice(&data)?;
+
+        // the file name is the only thing tying the content to the hash, so make sure no one renamed it
+        if stored.revision.metadata.verification_hash != hash {
+            return Err(Error::HashMismatch(hash));
+        }
+
+        Ok(stored)
+    }
+
+    /// lists all revision files in the directory together with the time they were last written
+    async fn scan(&self) -> Result<HashMap<Hash, SystemTime>> {
+        let mut dir = tokio::fs::read_dir(&self.root).await?;
+        let mut found = HashMap::new();
+        while let Some(entry) = dir.next_entry().await? {
+            let file_name = entry.file_name();
+            let Some(hash) = file_name
+                .to_str()
+                .and_then(|name| name.strip_suffix(".json"))
+                .and_then(|name| name.parse::<Hash>().ok())
+            else {
+                continue;
+            };
+            let modified = entry.metadata().await?.modified()?;
+            found.insert(hash, modified);
+        }
+        Ok(found)
+    }
+}
+
+impl guardian_common::storage::Storage for FsStorage {
+    type Error = Error;
+    type Context = RevContext;
+    type Cursor = SystemTime;
+
+    /// the context is the one the revision was stored with
+    async fn get_context(&self, hash: Hash) -> Result<Self::Context> {
+        Ok(self.read_stored(hash).await?.context)
+    }
+
+    /// writes the revision to `<verification_hash>.json`
+    ///
+    /// the file is written under a temporary name first and then renamed, so readers never see half a revision.
+    /// storing the same revision twice just overwrites the file.
+    async fn store(&self, rev: Revision, context: Self::Context) -> Result<()> {
+        let path = self.revision_path(rev.metadata.verification_hash);
+        let tmp_path = path.with_extension("json.tmp");
+
+        let data = serde_json::to_vec_pretty(&StoredRevision {
+            context,
+            revision: rev,
+        })?;
+        tokio::fs::write(&tmp_path, data).await?;
+        tokio::fs::rename(tmp_path, path).await?;
+
+        Ok(())
+    }
+
+    /// read takes a hash and gives you the corresponding revision
+    async fn read(&self, hash: Hash) -> Result<Revision> {
+        Ok(self.read_stored(hash).await?.revision)
+    }
+
+    /// follows the `previous_verification_hash` links from `hash` down to the genesis revision
+    async fn get_branch(&self, hash: Hash) -> Result<Branch<RevContext>> {
+        let StoredRevision { context, revision } = self.read_stored(hash).await?;
+
+        let mut hashes = vec![hash];
+        let mut prev = revision.metadata.previous_verification_hash;
+        while let Some(prev_hash) = prev {
+            hashes.push(prev_hash);
+            prev = self
+                .read_stored(prev_hash)
+                .await?
+                .revision
+                .metadata
+                .previous_verification_hash;
+        }
+
+        Ok(Branch {
+            metadata: context,
+            hashes,
+        })
+    }
+
+    /// all revisions which are not the previous revision of another revision
+    async fn list(&self) -> Result<Vec<Hash>> {
+        let stored = self.scan().await?;
+
+        let mut latests: HashSet<Hash> = stored.keys().copied().collect();
+        for hash in stored.keys() {
+            let revision = self.read_stored(*hash).await?.revision;
+            if let Some(prev) = revision.metadata.previous_verification_hash {
+                latests.remove(&prev);
+            }
+        }
+
+        Ok(latests.into_iter().collect())
+    }
+
+    /// scans the directory every second and reports every revision file that appeared ([`StorageEvent::Created`]) or
+    /// disappeared ([`StorageEvent::Deleted`])
+    ///
+    /// on the first scan all files written since `since`, or since the creation of this [`FsStorage`], count as new.
+    /// the cursor is the time of a scan, files removed while nobody was scanning are not reported.
+    fn changes(
+        &self,
+        since: Option<Self::Cursor>,
+    ) -> impl futures::Stream<Item = Result<StorageChange<Self::Cursor>>> + Send + '_ {
+        let interval = tokio::time::interval(std::time::Duration::from_secs(1));
+        let state = (
+            since.unwrap_or(self.creation),
+            None::<HashMap<Hash, SystemTime>>,
+            VecDeque::new(),
+            interval,
+        );
+
+        futures::stream::unfold(
+            state,
+            move |(mut last, mut known, mut pending, mut interval)| async move {
+                loop {
+                    if let Some(change) = pending.pop_front() {
+                        return Some((Ok(change), (last, known, pending, interval)));
+                    }
+                    interval.tick().await;
+                    let now = SystemTime::now();
+                    let current = match self.scan().await {
+                        Ok(current) => current,
+                        Err(e) => return Some((Err(e), (last, known, pending, interval))),
+                    };
+
+                    let mut found = vec![];
+                    match &known {
+                        Some(known) => {
+                            for hash in current.keys().filter(|hash| !known.contains_key(hash)) {
+                                found.push((*hash, StorageEvent::Created));
+                            }
+                            for hash in known.keys().filter(|hash| !current.contains_key(hash)) {
+                                found.push((*hash, StorageEvent::Deleted));
+                            }
+                        }
+                        None => {
+                            for (hash, _) in
+                                current.iter().filter(|(_, modified)| **modified >= last)
+                            {
+                                found.push((*hash, StorageEvent::Created));
+                            }
+                        }
+                    }
+                    let count = found.len();
+                    pending.extend(found.into_iter().enumerate().map(|(i, (hash, event))| {
+                        StorageChange {
+                            hash,
+                            event,
+                            // only once the whole scan went through it is done
+                            cursor: if i + 1 == count { now } else { last },
+                        }
+                    }));
+                    known = Some(current);
+                    last = now;
+                }
+            },
+        )
+    }
+
+    async fn latest_cursor(&self) -> Result<Self::Cursor> {
+        Ok(SystemTime::now())
+    }
+
+    fn is_transient(error: &Error) -> bool {
+        error.is_transient()
+    }
+}
@@ -0,0 +1,32 @@
+//! # Local Storage
+//!
+//! Implementations of the [`Storage`](guardian_common::storage::Storage) trait which do not need a PKC behind them.
+//!
+//! They use the same [`RevContext`](pkc_api::storage::RevContext) as [`pkc_api::Pkc`], so a guardian running on top of
+//! them can still exchange branches with guardians running on a PKC.
+//!
+//! - [`FsStorage`](fs::FsStorage) keeps revisions as content-addressed JSON files in a directory
+//! - [`SqliteStorage`](sqlite::SqliteStorage) keeps revisions in a SQLite database with indexed branch lookups
+//! - [`MemoryStorage`](memory::MemoryStorage) keeps revisions in memory, mostly useful for tests
+//!
+//! [`CachedStorage`](cached::CachedStorage) wraps any of them, or a [`pkc_api::Pkc`], with an LRU cache, [`Outbox`](outbox::Outbox) with a journal of
+//! revisions still to be stored while it is unreachable.
+
+pub mod cached;
+pub mod error;
+pub mod fs;
+pub mod memory;
+pub mod outbox;
+pub mod sqlite;
+
+pub mod prelude {
+    pub use super::cached::CachedStorage;
+    pub use super::fs::FsStorage;
+    pub use super::memory::MemoryStorage;
+    pub use super::outbox::Outbox;
+    pub use super::sqlite::SqliteStorage;
+    pub use guardian_common::storage::*;
+    pub use pkc_api::storage::RevContext;
+}
+
+pub type Result<T> = std::result::Result<T, error::Error>;
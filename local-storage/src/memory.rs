@@ -0,0 +1,222 @@
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Arc,
+};
+
+use guardian_common::{
+    custom_types::*,
+    storage::{StorageChange, StorageEvent},
+};
+use parking_lot::RwLock;
+use pkc_api::storage::RevContext;
+use tokio::sync::broadcast;
+
+use crate::{error::Error, Result};
+
+/// how many changes a slow consumer may fall behind before it has to catch up from the history
+const CHANNEL_CAPACITY: usize = 1024;
+
+#[derive(Debug)]
+struct Changes {
+    /// every change since creation, so consumers started late still see everything
+    history: Vec<(Hash, StorageEvent)>,
+    /// the position of every new change in `history`
+    sendr: broadcast::Sender<usize>,
+}
+
+#[derive(Debug)]
+struct Inner {
+    revisions: RwLock<HashMap<Hash, (Revision, RevContext)>>,
+    changes: RwLock<Changes>,
+}
+
+/// In-memory [implementation](crate::memory::MemoryStorage#impl-Storage-for-MemoryStorage) of the [`Storage`](guardian_common::storage::Storage) trait
+///
+/// Clones share the same revisions. Every change is announced over a [`tokio::sync::broadcast`] channel, so a revision
+/// stored from one task fires the [`update_handler`](guardian_common::storage::Storage::update_handler) running in another.
+/// Like with [`Pkc`](pkc_api::Pkc), the [`changes`](guardian_common::storage::Storage::changes) start with all changes
+/// since the storage was created, no matter when they are requested, which keeps tests deterministic.
+///
+/// # Example
+///
+/// ```
+/// # #[tokio::main(flavor = "current_thread")]
+/// # async fn main() -> local_storage::Result<()> {
+/// use local_storage::prelude::*;
+///
+/// let storage = MemoryStorage::new();
+/// let (sendr, mut recvr) = tokio::sync::mpsc::unbounded_channel();
+/// let watcher = storage.clone();
+/// tokio::spawn(async move { watcher.update_handler(move |hash, _kind| { sendr.send(hash).ok(); }).await });
+///
+/// let rev = guardian_common::custom_types::Revision::default();
+/// let hash = rev.metadata.verification_hash;
+/// # let context = RevContext { namespace: 0, name: "Main_Page".into(), genesis_hash: hash, domain_id: "42".into() };
+/// storage.store(rev, context).await?;
+/// assert_eq!(recvr.recv().await, Some(hash));
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct MemoryStorage {
+    inner: Arc<Inner>,
+}
+
+impl Default for MemoryStorage {
+    fn default() -> Self {
+        let (sendr, _) = broadcast::channel(CHANNEL_CAPACITY);
+        MemoryStorage {
+            inner: Arc::new(Inner {
+                revisions: Default::default(),
+                changes: RwLock::new(Changes {
+                    history: vec![],
+                    sendr,
+                }),
+            }),
+        }
+    }
+}
+
+impl MemoryStorage {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// removes a revision, reporting it as [`StorageEvent::Deleted`]
+    pub fn remove(&self, hash: Hash) -> Option<Revision> {
+        let (revision, _context) = self.inner.revisions.write().remove(&hash)?;
+        self.notify(hash, StorageEvent::Deleted);
+        Some(revision)
+    }
+
+    fn notify(&self, hash: Hash, event: StorageEvent) {
+        let mut changes = self.inner.changes.write();
+        let seqno = changes.history.len();
+        changes.history.push((hash, event));
+        // an error only means nobody is listening right now, they will find it in the history
+        changes.sendr.send(seqno).ok();
+    }
+}
+
+impl guardian_common::storage::Storage for MemoryStorage {
+    type Error = Error;
+    type Context = RevContext;
+    type Cursor = usize;
+
+    async fn get_context(&self, hash: Hash) -> Result<Self::Context> {
+        self.inner
+            .revisions
+            .read()
+            .get(&hash)
+            .map(|(_, context)| context.clone())
+            .ok_or(Error::NotFound(hash))
+    }
+
+    /// keeps the revision and reports it as [`StorageEvent::Created`], storing a revision again changes nothing
+    async fn store(&self, rev: Revision, context: Self::Context) -> Result<()> {
+        let hash = rev.metadata.verification_hash;
+        let is_new = {
+            use std::collections::hash_map::Entry::*;
+            match self.inner.revisions.write().entry(hash) {
+                Occupied(_) => false,
+                Vacant(v) => {
+                    v.insert((rev, context));
+                    true
+                }
+            }
+        };
+        if is_new {
+            self.notify(hash, StorageEvent::Created);
+        }
+        Ok(())
+    }
+
+    async fn read(&self, hash: Hash) -> Result<Revision> {
+        self.inner
+            .revisions
+            .read()
+            .get(&hash)
+            .map(|(revision, _)| revision.clone())
+            .ok_or(Error::NotFound(hash))
+    }
+
+    async fn get_branch(&self, hash: Hash) -> Result<Branch<RevContext>> {
+        let revisions = self.inner.revisions.read();
+        let (revision, context) = revisions.get(&hash).ok_or(Error::NotFound(hash))?;
+
+        let mut hashes = vec![hash];
+        let mut prev = revision.metadata.previous_verification_hash;
+        while let Some(prev_hash) = prev {
+            hashes.push(prev_hash);
+            let (prev_revision, _) = revisions
+                .get(&prev_hash)
+                .ok_or(Error::NotFound(prev_hash))?;
+            prev = prev_revision.metadata.previous_verification_hash;
+        }
+
+        Ok(Branch {
+            metadata: context.clone(),
+            hashes,
+        })
+    }
+
+    /// all revisions which are not the previous revision of another revision
+    async fn list(&self) -> Result<Vec<Hash>> {
+        let revisions = self.inner.revisions.read();
+        let prevs: HashSet<Hash> = revisions
+            .values()
+            .filter_map(|(revision, _)| revision.metadata.previous_verification_hash)
+            .collect();
+        Ok(revisions
+            .keys()
+            .filter(|hash| !prevs.contains(hash))
+            .copied()
+            .collect())
+    }
+
+    /// replays the history from `since` and then waits for new changes on the broadcast channel
+    ///
+    /// the cursor is the number of changes seen, so resuming never repeats or misses a change.
+    fn changes(
+        &self,
+        since: Option<Self::Cursor>,
+    ) -> impl futures::Stream<Item = Result<StorageChange<Self::Cursor>>> + Send + '_ {
+        // subscribe while holding the lock, so no change falls between the history and the channel
+        let (next, recvr) = {
+            let changes = self.inner.changes.read();
+            let next = since.unwrap_or_default().min(changes.history.len());
+            (next, changes.sendr.subscribe())
+        };
+
+        futures::stream::unfold((next, recvr), move |(mut next, mut recvr)| async move {
+            loop {
+                // everything up to the end of the history can be taken from there
+                let recorded = self.inner.changes.read().history.get(next).copied();
+                if let Some((hash, event)) = recorded {
+                    next += 1;
+                    let change = StorageChange {
+                        hash,
+                        event,
+                        cursor: next,
+                    };
+                    return Some((Ok(change), (next, recvr)));
+                }
+                match recvr.recv().await {
+                    // only a signal, the change is picked up from the history above
+                    Ok(_) | Err(broadcast::error::RecvError::Lagged(_)) => {}
+                    Err(broadcast::error::RecvError::Closed) => {
+                        unreachable!("the sender lives as long as the storage")
+                    }
+                }
+            }
+        })
+    }
+
+    async fn latest_cursor(&self) -> Result<Self::Cursor> {
+        Ok(self.inner.changes.read().history.len())
+    }
+
+    fn is_transient(error: &Error) -> bool {
+        error.is_transient()
+    }
+}
@@ -0,0 +1,377 @@
+use std::{
+    collections::HashSet,
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::Duration,
+};
+
+use guardian_common::{
+    custom_types::*,
+    storage::{Storage, StorageChange},
+};
+use parking_lot::Mutex;
+use tokio::io::AsyncWriteExt;
+
+use crate::error::OutboxError;
+
+/// a single line of the journal
+#[derive(Debug, serde::Deserialize, serde::Serialize, Clone)]
+struct Queued<Context> {
+    context: Context,
+    revision: Revision,
+}
+
+#[derive(Debug)]
+struct Journal<Context> {
+    path: PathBuf,
+    queue: Mutex<Vec<Queued<Context>>>,
+    /// held while the journal file is written, so appends and rewrites do not interleave
+    file: tokio::sync::Mutex<()>,
+    /// held while replaying, so revisions are not stored twice
+    flushing: tokio::sync::Mutex<()>,
+    /// held from checking the queue until a revision is stored or queued, so none overtakes a failing one
+    storing: tokio::sync::Mutex<()>,
+}
+
+/// Wraps any [`Storage`] so that [`store`](Storage::store) keeps working while the storage is unreachable
+///
+/// A failed store is appended to a journal file (one json object per line) instead of failing. Once something is
+/// queued, every later store goes to the journal as well, so revisions never overtake the ones they build on.
+/// [`flush`](Outbox::flush) replays the journal in chain order and skips revisions the storage already has, which
+/// happens when a store went through but its answer got lost.
+///
+/// Queued revisions can already be read, so the guardian can keep building its state on top of them.
+///
+/// # Example
+///
+/// ```no_run
+/// # async fn example(pkc: pkc_api::Pkc) -> local_storage::Result<()> {
+/// use local_storage::prelude::*;
+///
+/// let storage = Outbox::open(pkc, "outbox.jsonl")?;
+/// tokio::spawn(storage.clone().replay_every(std::time::Duration::from_secs(5)));
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct Outbox<S: Storage> {
+    inner: S,
+    journal: Arc<Journal<S::Context>>,
+}
+
+impl<S: Storage + Clone> Clone for Outbox<S> {
+    fn clone(&self) -> Self {
+        Outbox {
+            inner: self.inner.clone(),
+            journal: self.journal.clone(),
+        }
+    }
+}
+
+impl<S> Outbox<S>
+where
+    S: Storage,
+    S::Context: Clone + serde::Serialize + serde::de::DeserializeOwned,
+{
+    /// uses the journal at `path`, picking up whatever is still queued in it
+    ///
+    /// an incomplete last line, left by a crash while appending, is cut off. any other broken line is an error.
+    pub fn open(inner: S, path: impl Into<PathBuf>) -> crate::Result<Self> {
+        let path = path.into();
+        let data = match std::fs::read_to_string(&path) {
+            Ok(data) => data,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => String::new(),
+            Err(e) => return Err(e.into()),
+        };
+        // every complete line ends with a newline, the torn one never got it
+        let complete = data.rfind('\n').map_or(0, |end| end + 1);
+        if complete < data.len() {
+            eprintln!(
+                "outbox: cutting off an incomplete last line of {}",
+                path.display()
+            );
+            std::fs::OpenOptions::new()
+                .write(true)
+                .open(&path)?
+                .set_len(complete as u64)?;
+        }
+        let queue = data[..complete]
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(serde_json::from_str)
+            .collect::<Result<_, _>>()?;
+        Ok(Outbox {
+            inner,
+            journal: Arc::new(Journal {
+                path,
+                queue: Mutex::new(queue),
+                file: Default::default(),
+                flushing: Default::default(),
+                storing: Default::default(),
+            }),
+        })
+    }
+    pub fn inner(&self) -> &S {
+        &self.inner
+    }
+    pub fn path(&self) -> &Path {
+        &self.journal.path
+    }
+    /// the hashes of all revisions still waiting to be stored
+    pub fn pending(&self) -> Vec<Hash> {
+        self.journal
+            .queue
+            .lock()
+            .iter()
+            .map(|queued| queued.revision.metadata.verification_hash)
+            .collect()
+    }
+
+    fn queued(&self, hash: Hash) -> Option<Queued<S::Context>> {
+        self.journal
+            .queue
+            .lock()
+            .iter()
+            .find(|queued| queued.revision.metadata.verification_hash == hash)
+            .cloned()
+    }
+
+    async fn enqueue(&self, queued: Queued<S::Context>) -> crate::Result<()> {
+        let mut line = serde_json::to_vec(&queued)?;
+        line.push(b'\n');
+
+        let _file = self.journal.file.lock().await;
+        let mut file = tokio::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.journal.path)
+            .await?;
+        file.write_all(&line).await?;
+        file.sync_data().await?;
+        self.journal.queue.lock().push(queued);
+        Ok(())
+    }
+
+    /// writes the journal anew from what is left in the queue
+    async fn rewrite(&self) -> crate::Result<()> {
+        let _file = self.journal.file.lock().await;
+        let mut data = vec![];
+        for queued in self.journal.queue.lock().iter() {
+            serde_json::to_writer(&mut data, queued)?;
+            data.push(b'\n');
+        }
+        let tmp_path = self.journal.path.with_extension("tmp");
+        tokio::fs::write(&tmp_path, data).await?;
+        tokio::fs::rename(tmp_path, &self.journal.path).await?;
+        Ok(())
+    }
+
+    /// stores everything queued in chain order, stopping at the first revision the storage refuses
+    ///
+    /// returns how many revisions left the queue.
+    pub async fn flush(&self) -> Result<usize, OutboxError<S::Error>> {
+        let _flushing = self.journal.flushing.lock().await;
+        let queued = chain_order(self.journal.queue.lock().clone());
+
+        let mut done = 0;
+        let mut result = Ok(());
+        for Queued { context, revision } in queued {
+            let hash = revision.metadata.verification_hash;
+            // a duplicate, the storage already has it
+            if self.inner.read(hash).await.is_err() {
+                if let Err(e) = self.inner.store(revision, context).await {
+                    result = Err(OutboxError::Storage(e));
+                    break;
+                }
+            }
+            self.journal
+                .queue
+                .lock()
+                .retain(|queued| queued.revision.metadata.verification_hash != hash);
+            done += 1;
+        }
+
+        if done > 0 {
+            self.rewrite().await?;
+        }
+        result.map(|()| done)
+    }
+
+    /// flushes the queue every `period`, forever
+    pub async fn replay_every(self, period: Duration) -> std::convert::Infallible {
+        let mut interval = tokio::time::interval(period);
+        loop {
+            interval.tick().await;
+            if self.journal.queue.lock().is_empty() {
+                continue;
+            }
+            match self.flush().await {
+                Ok(done) => eprintln!("outbox: stored {done} queued revisions"),
+                Err(e) => eprintln!("outbox: storage still unavailable: {e}... retrying"),
+            }
+        }
+    }
+}
+
+/// orders revisions so that every revision comes after its previous revision, if that is queued as well
+fn chain_order<Context>(mut queued: Vec<Queued<Context>>) -> Vec<Queued<Context>> {
+    let mut ordered = Vec::with_capacity(queued.len());
+    let mut remaining: HashSet<Hash> = queued
+        .iter()
+        .map(|queued| queued.revision.metadata.verification_hash)
+        .collect();
+    while !queued.is_empty() {
+        let (ready, waiting): (Vec<_>, Vec<_>) = queued.into_iter().partition(|queued| {
+            !matches!(
+                queued.revision.metadata.previous_verification_hash,
+                Some(prev) if remaining.contains(&prev)
+            )
+        });
+        // hashes cannot form a cycle, but a broken journal should not hang us
+        if ready.is_empty() {
+            ordered.extend(waiting);
+            break;
+        }
+        for queued in &ready {
+            remaining.remove(&queued.revision.metadata.verification_hash);
+        }
+        ordered.extend(ready);
+        queued = waiting;
+    }
+    ordered
+}
+
+impl<S> Storage for Outbox<S>
+where
+    S: Storage + Sync,
+    S::Error: Send + Sync,
+    S::Context: Clone + serde::Serialize + serde::de::DeserializeOwned + Send + Sync,
+{
+    type Error = OutboxError<S::Error>;
+    type Context = S::Context;
+    type Cursor = S::Cursor;
+
+    async fn get_context(&self, hash: Hash) -> Result<Self::Context, Self::Error> {
+        match self.queued(hash) {
+            Some(queued) => Ok(queued.context),
+            None => self
+                .inner
+                .get_context(hash)
+                .await
+                .map_err(OutboxError::Storage),
+        }
+    }
+
+    /// stores the revision, or queues it if the storage fails or there is already something queued
+    ///
+    /// revisions already queued are not queued again. only failing to write the journal is an error.
+    async fn store(&self, rev: Revision, context: Self::Context) -> Result<(), Self::Error> {
+        let hash = rev.metadata.verification_hash;
+        let _storing = self.journal.storing.lock().await;
+        if self.queued(hash).is_some() {
+            return Ok(());
+        }
+        if self.journal.queue.lock().is_empty() {
+            match self.inner.store(rev.clone(), context.clone()).await {
+                Ok(()) => return Ok(()),
+                Err(e) => eprintln!("outbox: failed to store {hash}, queueing it: {e}"),
+            }
+        }
+        self.enqueue(Queued {
+            context,
+            revision: rev,
+        })
+        .await?;
+        Ok(())
+    }
+
+    async fn read(&self, hash: Hash) -> Result<Revision, Self::Error> {
+        match self.queued(hash) {
+            Some(queued) => Ok(queued.revision),
+            None => self.inner.read(hash).await.map_err(OutboxError::Storage),
+        }
+    }
+
+    /// follows queued revisions until the storage takes over
+    async fn get_branch(&self, hash: Hash) -> Result<Branch<Self::Context>, Self::Error> {
+        let Some(head) = self.queued(hash) else {
+            return self
+                .inner
+                .get_branch(hash)
+                .await
+                .map_err(OutboxError::Storage);
+        };
+
+        let mut hashes = vec![hash];
+        let mut prev = head.revision.metadata.previous_verification_hash;
+        while let Some(prev_hash) = prev {
+            match self.queued(prev_hash) {
+                Some(queued) => {
+                    hashes.push(prev_hash);
+                    prev = queued.revision.metadata.previous_verification_hash;
+                }
+                None => {
+                    let stored = self
+                        .inner
+                        .get_branch(prev_hash)
+                        .await
+                        .map_err(OutboxError::Storage)?;
+                    hashes.extend(stored.hashes);
+                    break;
+                }
+            }
+        }
+
+        Ok(Branch {
+            metadata: head.context,
+            hashes,
+        })
+    }
+
+    /// the latest revisions of the storage, with queued revisions replacing the ones they build on
+    async fn list(&self) -> Result<Vec<Hash>, Self::Error> {
+        let stored = self.inner.list().await.map_err(OutboxError::Storage)?;
+        let queue = self.journal.queue.lock().clone();
+
+        let prevs: HashSet<Hash> = queue
+            .iter()
+            .filter_map(|queued| queued.revision.metadata.previous_verification_hash)
+            .collect();
+        let latests: HashSet<Hash> = stored
+            .into_iter()
+            .chain(
+                queue
+                    .iter()
+                    .map(|queued| queued.revision.metadata.verification_hash),
+            )
+            .filter(|hash| !prevs.contains(hash))
+            .collect();
+        Ok(latests.into_iter().collect())
+    }
+
+    /// the changes of the storage, queued revisions show up once they are stored
+    fn changes(
+        &self,
+        since: Option<Self::Cursor>,
+    ) -> impl futures::Stream<Item = Result<StorageChange<Self::Cursor>, Self::Error>> + Send + '_
+    {
+        use futures::StreamExt;
+        self.inner
+            .changes(since)
+            .map(|change| change.map_err(OutboxError::Storage))
+    }
+
+    async fn latest_cursor(&self) -> Result<Self::Cursor, Self::Error> {
+        self.inner
+            .latest_cursor()
+            .await
+            .map_err(OutboxError::Storage)
+    }
+
+    fn is_transient(error: &Self::Error) -> bool {
+        match error {
+            OutboxError::Storage(e) => S::is_transient(e),
+            OutboxError::Journal(e) => e.is_transient(),
+        }
+    }
+}
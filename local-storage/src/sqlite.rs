@@ -0,0 +1,382 @@
+use std::{
+    collections::VecDeque,
+    path::Path,
+    sync::Arc,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use guardian_common::{
+    custom_types::*,
+    storage::{StorageChange, StorageEvent},
+};
+use parking_lot::Mutex;
+use pkc_api::storage::RevContext;
+use rusqlite::{params, Connection, OptionalExtension};
+
+use crate::{error::Error, Result};
+
+const SCHEMA: &str = "
+PRAGMA journal_mode = WAL;
+
+CREATE TABLE IF NOT EXISTS revisions (
+    verification_hash TEXT PRIMARY KEY NOT NULL,
+    revision TEXT NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS edges (
+    verification_hash TEXT PRIMARY KEY NOT NULL REFERENCES revisions,
+    previous_verification_hash TEXT NOT NULL
+);
+CREATE INDEX IF NOT EXISTS edges_previous ON edges (previous_verification_hash);
+
+CREATE TABLE IF NOT EXISTS contexts (
+    verification_hash TEXT PRIMARY KEY NOT NULL REFERENCES revisions,
+    namespace INTEGER NOT NULL,
+    name TEXT NOT NULL,
+    genesis_hash TEXT NOT NULL,
+    domain_id TEXT NOT NULL
+);
+CREATE INDEX IF NOT EXISTS contexts_genesis ON contexts (genesis_hash);
+
+CREATE TABLE IF NOT EXISTS changes (
+    seqno INTEGER PRIMARY KEY AUTOINCREMENT,
+    verification_hash TEXT NOT NULL,
+    kind TEXT NOT NULL,
+    time INTEGER NOT NULL
+);
+";
+
+/// SQLite database [implementing](crate::sqlite::SqliteStorage#impl-Storage-for-SqliteStorage) the [`Storage`](guardian_common::storage::Storage) trait
+///
+/// The database has four tables:
+/// - `revisions`: the revision as json, keyed by verification hash
+/// - `edges`: the `previous_verification_hash` of every revision which has one
+/// - `contexts`: the [`RevContext`] every revision was stored with, indexed by genesis hash
+/// - `changes`: every store and remove, numbered by the monotonically increasing `seqno`
+///
+/// [`get_branch`](guardian_common::storage::Storage::get_branch) and [`list`](guardian_common::storage::Storage::list)
+/// are single queries. All queries run on tokio's blocking thread pool.
+///
+/// # Example
+///
+/// ```no_run
+/// # async fn example() -> local_storage::Result<()> {
+/// use local_storage::prelude::*;
+///
+/// let storage = SqliteStorage::new("revisions.sqlite")?;
+/// for latest in storage.list().await? {
+///     let branch = storage.get_branch(latest).await?;
+///     println!("{}: {} revisions", branch.metadata.name, branch.hashes.len());
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct SqliteStorage {
+    creation: SystemTime,
+    conn: Arc<Mutex<Connection>>,
+}
+
+impl SqliteStorage {
+    /// opens the database at `path`, creating it and its tables if they do not exist yet
+    pub fn new(path: impl AsRef<Path>) -> Result<Self> {
+        SqliteStorage::new_with_options(SystemTime::now(), Connection::open(path)?)
+    }
+    /// a database only living as long as this [`SqliteStorage`] and its clones
+    pub fn new_in_memory() -> Result<Self> {
+        SqliteStorage::new_with_options(SystemTime::now(), Connection::open_in_memory()?)
+    }
+    /// `creation` works like in [`Pkc`](pkc_api::Pkc::new_with_options): the changes start with all changes made after it.
+    pub fn new_with_options(creation: SystemTime, conn: Connection) -> Result<Self> {
+        conn.execute_batch(SCHEMA)?;
+        Ok(SqliteStorage {
+            creation,
+            conn: Arc::new(Mutex::new(conn)),
+        })
+    }
+
+    /// the latest revisions of all branches starting at `genesis`
+    pub async fn latests_of(&self, genesis: Hash) -> Result<Vec<Hash>> {
+        self.with_conn(move |conn| {
+            let mut stmt = conn.prepare_cached(
+                "SELECT contexts.verification_hash FROM contexts
+                WHERE contexts.genesis_hash = ?1
+                AND NOT EXISTS (
+                    SELECT 1 FROM edges WHERE edges.previous_verification_hash = contexts.verification_hash
+                )",
+            )?;
+            let latests = stmt
+                .query_map([genesis.to_string()], |row| hash_column(row, 0))?
+                .collect::<rusqlite::Result<_>>()?;
+            Ok(latests)
+        })
+        .await
+    }
+
+    /// removes a revision, reporting it as [`StorageEvent::Deleted`]
+    pub async fn remove(&self, hash: Hash) -> Result<()> {
+        self.with_conn(move |conn| {
+            let hash = hash.to_string();
+            let tx = conn.transaction()?;
+            tx.execute("DELETE FROM edges WHERE verification_hash = ?1", [&hash])?;
+            tx.execute("DELETE FROM contexts WHERE verification_hash = ?1", [&hash])?;
+            let removed = tx.execute(
+                "DELETE FROM revisions WHERE verification_hash = ?1",
+                [&hash],
+            )?;
+            if removed == 0 {
+                return Ok(());
+            }
+            record_change(&tx, &hash, StorageEvent::Deleted)?;
+            tx.commit()?;
+            Ok(())
+        })
+        .await
+    }
+
+    /// runs `f` with the connection on tokio's blocking thread pool
+    async fn with_conn<T, F>(&self, f: F) -> Result<T>
+    where
+        T: Send + 'static,
+        F: FnOnce(&mut Connection) -> Result<T> + Send + Sync + 'static,
+    {
+        let conn = self.conn.clone();
+        match tokio::task::spawn_blocking(move || f(&mut conn.lock())).await {
+            Ok(result) => result,
+            Err(e) => std::panic::resume_unwind(e.into_panic()),
+        }
+    }
+}
+
+fn hash_column(row: &rusqlite::Row<'_>, idx: usize) -> rusqlite::Result<Hash> {
+    let text: String = row.get(idx)?;
+    text.parse().map_err(|_| {
+        rusqlite::Error::FromSqlConversionFailure(
+            idx,
+            rusqlite::types::Type::Text,
+            format!("not a hash: {text}").into(),
+        )
+    })
+}
+
+fn millis_since_epoch(time: SystemTime) -> i64 {
+    time.duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as i64)
+        .unwrap_or_default()
+}
+
+/// events are kept by their name, like `Created`
+fn event_column(row: &rusqlite::Row<'_>, idx: usize) -> rusqlite::Result<StorageEvent> {
+    let text: String = row.get(idx)?;
+    serde_json::from_value(serde_json::Value::String(text)).map_err(|e| {
+        rusqlite::Error::FromSqlConversionFailure(idx, rusqlite::types::Type::Text, e.into())
+    })
+}
+
+fn record_change(conn: &Connection, hash: &str, event: StorageEvent) -> Result<()> {
+    let serde_json::Value::String(kind) = serde_json::to_value(event)? else {
+        unreachable!("events are unit variants")
+    };
+    conn.execute(
+        "INSERT INTO changes (verification_hash, kind, time) VALUES (?1, ?2, ?3)",
+        params![hash, kind, millis_since_epoch(SystemTime::now())],
+    )?;
+    Ok(())
+}
+
+impl guardian_common::storage::Storage for SqliteStorage {
+    type Error = Error;
+    type Context = RevContext;
+    type Cursor = i64;
+
+    /// the context is the one the revision was stored with
+    async fn get_context(&self, hash: Hash) -> Result<Self::Context> {
+        self.with_conn(move |conn| {
+            conn.prepare_cached(
+                "SELECT namespace, name, genesis_hash, domain_id FROM contexts WHERE verification_hash = ?1",
+            )?
+            .query_row([hash.to_string()], |row| {
+                Ok(RevContext {
+                    namespace: row.get(0)?,
+                    name: row.get(1)?,
+                    genesis_hash: hash_column(row, 2)?,
+                    domain_id: row.get(3)?,
+                })
+            })
+            .optional()?
+            .ok_or(Error::NotFound(hash))
+        })
+        .await
+    }
+
+    /// inserts the revision, its edge and its context in one transaction and reports it as [`StorageEvent::Created`]
+    ///
+    /// storing a revision again changes nothing.
+    async fn store(&self, rev: Revision, context: Self::Context) -> Result<()> {
+        self.with_conn(move |conn| {
+            let hash = rev.metadata.verification_hash.to_string();
+            let json = serde_json::to_string(&rev)?;
+
+            let tx = conn.transaction()?;
+            let inserted = tx.execute(
+                "INSERT OR IGNORE INTO revisions (verification_hash, revision) VALUES (?1, ?2)",
+                params![&hash, json],
+            )?;
+            if inserted == 0 {
+                return Ok(());
+            }
+            if let Some(prev) = rev.metadata.previous_verification_hash {
+                tx.execute(
+                    "INSERT INTO edges (verification_hash, previous_verification_hash) VALUES (?1, ?2)",
+                    params![&hash, prev.to_string()],
+                )?;
+            }
+            tx.execute(
+                "INSERT INTO contexts (verification_hash, namespace, name, genesis_hash, domain_id)
+                VALUES (?1, ?2, ?3, ?4, ?5)",
+                params![
+                    &hash,
+                    context.namespace,
+                    context.name,
+                    context.genesis_hash.to_string(),
+                    context.domain_id,
+                ],
+            )?;
+            record_change(&tx, &hash, StorageEvent::Created)?;
+            tx.commit()?;
+            Ok(())
+        })
+        .await
+    }
+
+    /// read takes a hash and gives you the corresponding revision
+    async fn read(&self, hash: Hash) -> Result<Revision> {
+        self.with_conn(move |conn| {
+            let json: String = conn
+                .prepare_cached("SELECT revision FROM revisions WHERE verification_hash = ?1")?
+                .query_row([hash.to_string()], |row| row.get(0))
+                .optional()?
+                .ok_or(Error::NotFound(hash))?;
+            Ok(serde_json::from_str(&json)?)
+        })
+        .await
+    }
+
+    /// follows the edges from `hash` down to the genesis revision with a recursive query
+    async fn get_branch(&self, hash: Hash) -> Result<Branch<RevContext>> {
+        let metadata = self.get_context(hash).await?;
+        self.with_conn(move |conn| {
+            let mut stmt = conn.prepare_cached(
+                "WITH RECURSIVE branch (verification_hash, depth) AS (
+                    SELECT ?1, 0
+                    UNION ALL
+                    SELECT edges.previous_verification_hash, branch.depth + 1
+                    FROM edges JOIN branch ON edges.verification_hash = branch.verification_hash
+                )
+                SELECT branch.verification_hash, revisions.verification_hash IS NOT NULL
+                FROM branch LEFT JOIN revisions ON revisions.verification_hash = branch.verification_hash
+                ORDER BY branch.depth",
+            )?;
+            let mut hashes = vec![];
+            let mut rows = stmt.query([hash.to_string()])?;
+            while let Some(row) = rows.next()? {
+                let branch_hash = hash_column(row, 0)?;
+                let stored: bool = row.get(1)?;
+                if !stored {
+                    return Err(Error::NotFound(branch_hash));
+                }
+                hashes.push(branch_hash);
+            }
+            Ok(Branch { metadata, hashes })
+        })
+        .await
+    }
+
+    /// all revisions which are not the previous revision of another revision
+    async fn list(&self) -> Result<Vec<Hash>> {
+        self.with_conn(|conn| {
+            let mut stmt = conn.prepare_cached(
+                "SELECT verification_hash FROM revisions
+                WHERE NOT EXISTS (
+                    SELECT 1 FROM edges WHERE edges.previous_verification_hash = revisions.verification_hash
+                )",
+            )?;
+            let latests = stmt
+                .query_map([], |row| hash_column(row, 0))?
+                .collect::<rusqlite::Result<_>>()?;
+            Ok(latests)
+        })
+        .await
+    }
+
+    /// polls the `changes` table every second for changes after `since`, or made after the creation of this
+    /// [`SqliteStorage`], in the order of their `seqno`
+    ///
+    /// the cursor is the `seqno`, so resuming never repeats or misses a change.
+    fn changes(
+        &self,
+        since: Option<Self::Cursor>,
+    ) -> impl futures::Stream<Item = Result<StorageChange<Self::Cursor>>> + Send + '_ {
+        // without a cursor the time decides where to start
+        let (last, creation) = match since {
+            Some(seqno) => (seqno, i64::MIN),
+            None => (0, millis_since_epoch(self.creation)),
+        };
+        let interval = tokio::time::interval(std::time::Duration::from_secs(1));
+        let state = (last, VecDeque::new(), interval);
+
+        futures::stream::unfold(
+            state,
+            move |(mut last, mut pending, mut interval)| async move {
+                loop {
+                    if let Some(change) = pending.pop_front() {
+                        return Some((Ok(change), (last, pending, interval)));
+                    }
+                    interval.tick().await;
+                    let changes = self
+                        .with_conn(move |conn| {
+                            let mut stmt = conn.prepare_cached(
+                                "SELECT seqno, verification_hash, kind FROM changes
+                            WHERE seqno > ?1 AND time >= ?2 ORDER BY seqno",
+                            )?;
+                            let changes = stmt
+                                .query_map([last, creation], |row| {
+                                    Ok(StorageChange {
+                                        cursor: row.get(0)?,
+                                        hash: hash_column(row, 1)?,
+                                        event: event_column(row, 2)?,
+                                    })
+                                })?
+                                .collect::<rusqlite::Result<Vec<_>>>()?;
+                            Ok(changes)
+                        })
+                        .await;
+                    match changes {
+                        Ok(changes) => {
+                            if let Some(change) = changes.last() {
+                                last = change.cursor;
+                            }
+                            pending.extend(changes);
+                        }
+                        Err(e) => return Some((Err(e), (last, pending, interval))),
+                    }
+                }
+            },
+        )
+    }
+
+    async fn latest_cursor(&self) -> Result<Self::Cursor> {
+        self.with_conn(|conn| {
+            let seqno =
+                conn.query_row("SELECT COALESCE(MAX(seqno), 0) FROM changes", [], |row| {
+                    row.get(0)
+                })?;
+            Ok(seqno)
+        })
+        .await
+    }
+
+    fn is_transient(error: &Error) -> bool {
+        error.is_transient()
+    }
+}
@@ -0,0 +1,85 @@
+use futures::StreamExt;
+use guardian_common::custom_types::*;
+use local_storage::{cached::CacheStats, prelude::*};
+
+fn hash(n: u8) -> Hash {
+    Hash::from([n; 64])
+}
+
+fn revision(verification_hash: Hash, previous_verification_hash: Option<Hash>) -> Revision {
+    Revision {
+        metadata: RevisionMetadata {
+            verification_hash,
+            previous_verification_hash,
+            ..Default::default()
+        },
+        ..Default::default()
+    }
+}
+
+fn context(genesis_hash: Hash) -> RevContext {
+    RevContext {
+        namespace: 0,
+        name: "Main_Page".to_string(),
+        genesis_hash,
+        domain_id: "42".to_string(),
+    }
+}
+
+#[tokio::test]
+async fn reads_and_branches_are_cached() {
+    let memory = MemoryStorage::new();
+    memory
+        .store(revision(hash(1), None), context(hash(1)))
+        .await
+        .unwrap();
+    memory
+        .store(revision(hash(2), Some(hash(1))), context(hash(1)))
+        .await
+        .unwrap();
+    let storage = CachedStorage::new(memory);
+
+    storage.read(hash(1)).await.unwrap();
+    storage.read(hash(1)).await.unwrap();
+    storage.get_branch(hash(2)).await.unwrap();
+    let branch = storage.get_branch(hash(2)).await.unwrap();
+    assert_eq!(branch.hashes, vec![hash(2), hash(1)]);
+
+    assert_eq!(
+        storage.stats(),
+        CacheStats {
+            revision_hits: 1,
+            revision_misses: 1,
+            branch_hits: 1,
+            branch_misses: 1,
+        }
+    );
+}
+
+#[tokio::test]
+async fn deletes_invalidate() {
+    let storage = CachedStorage::new(MemoryStorage::new());
+    storage
+        .store(revision(hash(1), None), context(hash(1)))
+        .await
+        .unwrap();
+    storage
+        .store(revision(hash(2), Some(hash(1))), context(hash(1)))
+        .await
+        .unwrap();
+    storage.get_branch(hash(2)).await.unwrap();
+
+    let mut changes = std::pin::pin!(storage.changes(None));
+    // the two stores
+    changes.next().await.unwrap().unwrap();
+    changes.next().await.unwrap().unwrap();
+
+    storage.inner().remove(hash(1)).unwrap();
+    let change = changes.next().await.unwrap().unwrap();
+    assert_eq!(change.event, StorageEvent::Deleted);
+
+    assert!(storage.read(hash(1)).await.is_err());
+    assert!(storage.get_branch(hash(2)).await.is_err());
+    assert_eq!(storage.stats().revision_hits, 0);
+    assert_eq!(storage.stats().branch_misses, 2);
+}
@@ -0,0 +1,118 @@
+use guardian_common::custom_types::*;
+use local_storage::prelude::*;
+
+fn hash(n: u8) -> Hash {
+    Hash::from([n; 64])
+}
+
+fn revision(verification_hash: Hash, previous_verification_hash: Option<Hash>) -> Revision {
+    Revision {
+        metadata: RevisionMetadata {
+            verification_hash,
+            previous_verification_hash,
+            ..Default::default()
+        },
+        ..Default::default()
+    }
+}
+
+fn context(genesis_hash: Hash) -> RevContext {
+    RevContext {
+        namespace: 0,
+        name: "Main_Page".to_string(),
+        genesis_hash,
+        domain_id: "42".to_string(),
+    }
+}
+
+#[tokio::test]
+async fn store_and_walk_branch() {
+    let dir = tempfile::tempdir().unwrap();
+    let storage = FsStorage::new(dir.path()).unwrap();
+
+    storage
+        .store(revision(hash(1), None), context(hash(1)))
+        .await
+        .unwrap();
+    storage
+        .store(revision(hash(2), Some(hash(1))), context(hash(1)))
+        .await
+        .unwrap();
+    storage
+        .store(revision(hash(3), Some(hash(2))), context(hash(1)))
+        .await
+        .unwrap();
+    // a second chain
+    storage
+        .store(revision(hash(9), None), context(hash(9)))
+        .await
+        .unwrap();
+
+    let rev = storage.read(hash(2)).await.unwrap();
+    assert_eq!(rev.metadata.previous_verification_hash, Some(hash(1)));
+
+    let branch = storage.get_branch(hash(3)).await.unwrap();
+    assert_eq!(branch.hashes, vec![hash(3), hash(2), hash(1)]);
+    assert_eq!(branch.metadata.genesis_hash, hash(1));
+
+    let context = storage.get_context(hash(2)).await.unwrap();
+    assert_eq!(context.name, "Main_Page");
+
+    let mut latests = storage.list().await.unwrap();
+    latests.sort();
+    assert_eq!(latests, vec![hash(3), hash(9)]);
+
+    assert!(matches!(
+        storage.read(hash(7)).await,
+        Err(local_storage::error::Error::NotFound(_))
+    ));
+}
+
+#[tokio::test]
+async fn renamed_file_is_rejected() {
+    let dir = tempfile::tempdir().unwrap();
+    let storage = FsStorage::new(dir.path()).unwrap();
+
+    storage
+        .store(revision(hash(1), None), context(hash(1)))
+        .await
+        .unwrap();
+    std::fs::rename(
+        dir.path().join(format!("{}.json", hash(1))),
+        dir.path().join(format!("{}.json", hash(2))),
+    )
+    .unwrap();
+
+    assert!(matches!(
+        storage.read(hash(2)).await,
+        Err(local_storage::error::Error::HashMismatch(_))
+    ));
+}
+
+#[tokio::test]
+async fn update_handler_sees_new_and_deleted_files() {
+    let dir = tempfile::tempdir().unwrap();
+    let storage = FsStorage::new(dir.path()).unwrap();
+
+    let (sendr, mut recvr) = tokio::sync::mpsc::unbounded_channel();
+    let watcher = storage.clone();
+    tokio::spawn(async move {
+        watcher
+            .update_handler(move |hash, event| {
+                sendr.send((hash, event)).ok();
+            })
+            .await
+    });
+
+    storage
+        .store(revision(hash(1), None), context(hash(1)))
+        .await
+        .unwrap();
+    let timeout = std::time::Duration::from_secs(5);
+    let change = tokio::time::timeout(timeout, recvr.recv()).await.unwrap();
+    assert_eq!(change, Some((hash(1), StorageEvent::Created)));
+
+    std::fs::remove_file(dir.path().join(format!("{}.json", hash(1)))).unwrap();
+    let change = tokio::time::timeout(timeout, recvr.recv()).await.unwrap();
+    assert_eq!(change, Some((hash(1), StorageEvent::Deleted)));
+}
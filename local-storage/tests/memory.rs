@@ -0,0 +1,139 @@
+use guardian_common::custom_types::*;
+use local_storage::prelude::*;
+
+fn hash(n: u8) -> Hash {
+    Hash::from([n; 64])
+}
+
+fn revision(verification_hash: Hash, previous_verification_hash: Option<Hash>) -> Revision {
+    Revision {
+        metadata: RevisionMetadata {
+            verification_hash,
+            previous_verification_hash,
+            ..Default::default()
+        },
+        ..Default::default()
+    }
+}
+
+fn context(genesis_hash: Hash) -> RevContext {
+    RevContext {
+        namespace: 0,
+        name: "Main_Page".to_string(),
+        genesis_hash,
+        domain_id: "42".to_string(),
+    }
+}
+
+#[tokio::test]
+async fn store_and_walk_branch() {
+    let storage = MemoryStorage::new();
+
+    storage
+        .store(revision(hash(1), None), context(hash(1)))
+        .await
+        .unwrap();
+    storage
+        .store(revision(hash(2), Some(hash(1))), context(hash(1)))
+        .await
+        .unwrap();
+    storage
+        .store(revision(hash(3), None), context(hash(3)))
+        .await
+        .unwrap();
+
+    let branch = storage.get_branch(hash(2)).await.unwrap();
+    assert_eq!(branch.hashes, vec![hash(2), hash(1)]);
+    assert_eq!(branch.metadata.genesis_hash, hash(1));
+
+    let mut latests = storage.list().await.unwrap();
+    latests.sort();
+    assert_eq!(latests, vec![hash(2), hash(3)]);
+
+    assert!(matches!(
+        storage.read(hash(4)).await,
+        Err(local_storage::error::Error::NotFound(_))
+    ));
+}
+
+#[tokio::test]
+async fn update_handler_sees_stores_from_other_tasks() {
+    let storage = MemoryStorage::new();
+
+    // stored before the handler runs, still reported
+    storage
+        .store(revision(hash(1), None), context(hash(1)))
+        .await
+        .unwrap();
+
+    let (sendr, mut recvr) = tokio::sync::mpsc::unbounded_channel();
+    let watcher = storage.clone();
+    tokio::spawn(async move {
+        watcher
+            .update_handler(move |hash, event| {
+                sendr.send((hash, event)).unwrap();
+            })
+            .await
+    });
+
+    let writer = storage.clone();
+    tokio::spawn(async move {
+        writer
+            .store(revision(hash(2), Some(hash(1))), context(hash(1)))
+            .await
+            .unwrap();
+        // storing again is not a change
+        writer
+            .store(revision(hash(2), Some(hash(1))), context(hash(1)))
+            .await
+            .unwrap();
+        writer.remove(hash(2)).unwrap();
+    })
+    .await
+    .unwrap();
+
+    let mut changes = vec![];
+    for _ in 0..3 {
+        changes.push(recvr.recv().await.unwrap());
+    }
+    assert_eq!(
+        changes,
+        vec![
+            (hash(1), StorageEvent::Created),
+            (hash(2), StorageEvent::Created),
+            (hash(2), StorageEvent::Deleted),
+        ]
+    );
+    assert!(recvr.try_recv().is_err());
+}
+
+#[tokio::test]
+async fn changes_resume_from_cursor() {
+    use futures::StreamExt;
+
+    let storage = MemoryStorage::new();
+    for n in 1..=3 {
+        storage
+            .store(revision(hash(n), None), context(hash(n)))
+            .await
+            .unwrap();
+    }
+
+    let first: Vec<_> = storage.changes(None).take(2).collect().await;
+    let cursor = first[1].as_ref().unwrap().cursor;
+
+    storage.remove(hash(1)).unwrap();
+    let rest: Vec<_> = storage
+        .changes(Some(cursor))
+        .take(2)
+        .map(|change| change.map(|change| (change.hash, change.event)).unwrap())
+        .collect()
+        .await;
+    assert_eq!(
+        rest,
+        vec![
+            (hash(3), StorageEvent::Created),
+            (hash(1), StorageEvent::Deleted),
+        ]
+    );
+}
@@ -0,0 +1,227 @@
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc,
+};
+
+use guardian_common::custom_types::*;
+use local_storage::{error::Error, prelude::*};
+use parking_lot::Mutex;
+
+fn hash(n: u8) -> Hash {
+    Hash::from([n; 64])
+}
+
+fn revision(verification_hash: Hash, previous_verification_hash: Option<Hash>) -> Revision {
+    Revision {
+        metadata: RevisionMetadata {
+            verification_hash,
+            previous_verification_hash,
+            ..Default::default()
+        },
+        ..Default::default()
+    }
+}
+
+fn context(genesis_hash: Hash) -> RevContext {
+    RevContext {
+        namespace: 0,
+        name: "Main_Page".to_string(),
+        genesis_hash,
+        domain_id: "42".to_string(),
+    }
+}
+
+/// a [`MemoryStorage`] which can be switched off and remembers the order of stores
+#[derive(Debug, Clone, Default)]
+struct Flaky {
+    memory: MemoryStorage,
+    down: Arc<AtomicBool>,
+    stored: Arc<Mutex<Vec<Hash>>>,
+    /// a revision whose store takes a while and then fails
+    slow_failure: Arc<Mutex<Option<Hash>>>,
+}
+
+impl Flaky {
+    fn check(&self) -> Result<(), Error> {
+        if self.down.load(Ordering::Relaxed) {
+            return Err(std::io::Error::other("storage is down").into());
+        }
+        Ok(())
+    }
+}
+
+impl Storage for Flaky {
+    type Error = Error;
+    type Context = RevContext;
+    type Cursor = usize;
+
+    async fn get_context(&self, hash: Hash) -> Result<RevContext, Error> {
+        self.check()?;
+        self.memory.get_context(hash).await
+    }
+    async fn store(&self, rev: Revision, context: RevContext) -> Result<(), Error> {
+        if *self.slow_failure.lock() == Some(rev.metadata.verification_hash) {
+            tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+            return Err(std::io::Error::other("storage timed out").into());
+        }
+        self.check()?;
+        self.stored.lock().push(rev.metadata.verification_hash);
+        self.memory.store(rev, context).await
+    }
+    async fn read(&self, hash: Hash) -> Result<Revision, Error> {
+        self.check()?;
+        self.memory.read(hash).await
+    }
+    async fn get_branch(&self, hash: Hash) -> Result<Branch<RevContext>, Error> {
+        self.check()?;
+        self.memory.get_branch(hash).await
+    }
+    async fn list(&self) -> Result<Vec<Hash>, Error> {
+        self.check()?;
+        self.memory.list().await
+    }
+    fn changes(
+        &self,
+        since: Option<usize>,
+    ) -> impl futures::Stream<Item = Result<StorageChange<usize>, Error>> + Send + '_ {
+        self.memory.changes(since)
+    }
+    async fn latest_cursor(&self) -> Result<usize, Error> {
+        self.memory.latest_cursor().await
+    }
+}
+
+#[tokio::test]
+async fn queued_while_down_and_replayed_in_chain_order() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("outbox.jsonl");
+    let flaky = Flaky::default();
+    flaky.down.store(true, Ordering::Relaxed);
+
+    {
+        let outbox = Outbox::open(flaky.clone(), &path).unwrap();
+        // out of order on purpose
+        outbox
+            .store(revision(hash(2), Some(hash(1))), context(hash(1)))
+            .await
+            .unwrap();
+        outbox
+            .store(revision(hash(1), None), context(hash(1)))
+            .await
+            .unwrap();
+
+        // queued revisions can already be used
+        assert!(outbox.read(hash(1)).await.is_ok());
+        assert!(outbox.get_context(hash(2)).await.is_ok());
+    }
+
+    // the journal survives a restart
+    let outbox = Outbox::open(flaky.clone(), &path).unwrap();
+    assert_eq!(outbox.pending(), vec![hash(2), hash(1)]);
+    assert!(outbox.flush().await.is_err());
+    assert_eq!(outbox.pending().len(), 2);
+
+    flaky.down.store(false, Ordering::Relaxed);
+    assert_eq!(outbox.list().await.unwrap(), vec![hash(2)]);
+    assert_eq!(
+        outbox.get_branch(hash(2)).await.unwrap().hashes,
+        vec![hash(2), hash(1)]
+    );
+
+    assert_eq!(outbox.flush().await.unwrap(), 2);
+    assert_eq!(*flaky.stored.lock(), vec![hash(1), hash(2)]);
+    assert!(outbox.pending().is_empty());
+    assert_eq!(std::fs::read_to_string(&path).unwrap(), "");
+
+    // with an empty queue stores go straight through
+    outbox
+        .store(revision(hash(3), Some(hash(2))), context(hash(1)))
+        .await
+        .unwrap();
+    assert!(outbox.pending().is_empty());
+    assert_eq!(flaky.stored.lock().len(), 3);
+}
+
+#[tokio::test]
+async fn duplicates_are_not_stored_twice() {
+    let dir = tempfile::tempdir().unwrap();
+    let flaky = Flaky::default();
+    // the storage took it, but we never heard back
+    flaky
+        .memory
+        .store(revision(hash(1), None), context(hash(1)))
+        .await
+        .unwrap();
+    flaky.down.store(true, Ordering::Relaxed);
+
+    let outbox = Outbox::open(flaky.clone(), dir.path().join("outbox.jsonl")).unwrap();
+    for _ in 0..2 {
+        outbox
+            .store(revision(hash(1), None), context(hash(1)))
+            .await
+            .unwrap();
+    }
+    assert_eq!(outbox.pending(), vec![hash(1)]);
+
+    flaky.down.store(false, Ordering::Relaxed);
+    assert_eq!(outbox.flush().await.unwrap(), 1);
+    assert!(flaky.stored.lock().is_empty());
+}
+
+#[tokio::test]
+async fn no_overtaking_a_failing_store_in_flight() {
+    let dir = tempfile::tempdir().unwrap();
+    let flaky = Flaky::default();
+    *flaky.slow_failure.lock() = Some(hash(1));
+
+    let outbox = Outbox::open(flaky.clone(), dir.path().join("outbox.jsonl")).unwrap();
+    let (parent, child) = tokio::join!(
+        outbox.store(revision(hash(1), None), context(hash(1))),
+        outbox.store(revision(hash(2), Some(hash(1))), context(hash(1))),
+    );
+    parent.unwrap();
+    child.unwrap();
+    assert!(flaky.stored.lock().is_empty());
+    assert_eq!(outbox.pending(), vec![hash(1), hash(2)]);
+
+    *flaky.slow_failure.lock() = None;
+    assert_eq!(outbox.flush().await.unwrap(), 2);
+    assert_eq!(*flaky.stored.lock(), vec![hash(1), hash(2)]);
+}
+
+#[tokio::test]
+async fn torn_last_line_is_cut_off() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("outbox.jsonl");
+    let flaky = Flaky::default();
+    flaky.down.store(true, Ordering::Relaxed);
+    {
+        let outbox = Outbox::open(flaky.clone(), &path).unwrap();
+        outbox
+            .store(revision(hash(1), None), context(hash(1)))
+            .await
+            .unwrap();
+    }
+    let intact = std::fs::read_to_string(&path).unwrap();
+
+    // a crash while appending the next revision
+    let mut torn = intact.clone();
+    torn += r#"{"context":{"namespace":0,"#;
+    std::fs::write(&path, &torn).unwrap();
+    let outbox = Outbox::open(flaky.clone(), &path).unwrap();
+    assert_eq!(outbox.pending(), vec![hash(1)]);
+    assert_eq!(std::fs::read_to_string(&path).unwrap(), intact);
+
+    // appending goes on after the last complete line
+    outbox
+        .store(revision(hash(2), Some(hash(1))), context(hash(1)))
+        .await
+        .unwrap();
+    drop(outbox);
+    let outbox = Outbox::open(flaky.clone(), &path).unwrap();
+    assert_eq!(outbox.pending(), vec![hash(1), hash(2)]);
+
+    // broken lines before the last one are corruption
+    std::fs::write(&path, format!("{{\"broken\n{intact}")).unwrap();
+    assert!(Outbox::open(flaky, &path).is_err());
+}
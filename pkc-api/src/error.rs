@@ -29,3 +29,14 @@ impl std::fmt::Display for ApiError {
     }
 }
 impl std::error::Error for ApiError {}
+
+impl Error {
+    /// lost connections and overloaded servers may recover, an answer the pkc did not like stays the same
+    pub fn is_transient(&self) -> bool {
+        match self {
+            Error::Io(_) | Error::Http(_) => true,
+            Error::Api(e) => e.http_code >= 500 || e.http_code == 429,
+            Error::Json(_) | Error::Other(_) => false,
+        }
+    }
+}
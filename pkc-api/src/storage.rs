@@ -5,6 +5,7 @@ use crate::da::*;
 use guardian_common::custom_types::*;
 #[cfg(test)]
 use guardian_common::storage::Storage;
+use guardian_common::storage::{StorageChange, StorageEvent};
 
 #[derive(Debug, serde::Deserialize, serde::Serialize, Clone)]
 pub struct RevContext {
@@ -14,9 +15,19 @@ pub struct RevContext {
     pub domain_id: String,
 }
 
+impl guardian_common::storage::PageContext for RevContext {
+    fn namespace(&self) -> i32 {
+        self.namespace
+    }
+    fn title(&self) -> &str {
+        &self.name
+    }
+}
+
 impl guardian_common::storage::Storage for Pkc {
     type Error = error::Error;
     type Context = RevContext;
+    type Cursor = chrono::NaiveDateTime;
 
     /// hardcoded because there is currently no way for us to get the context and it is irrelevant as long as all the names are different
     async fn get_context(&self, hash: Hash) -> Result<Self::Context> {
@@ -83,31 +94,83 @@ impl guardian_common::storage::Storage for Pkc {
         Ok(x.into_keys().collect())
     }
 
-    /// gets all of the recent changes from the pkc api and puts them into a given function
-    /// this process is repeated every second
-    async fn update_handler<F: Fn(Hash, String) + Send + Sync>(
+    /// polls the recent changes of the pkc api every second, starting at `since` or at the creation of this [`Pkc`]
+    ///
+    /// the cursor is the time of a poll. the pkc reports changes as a set, so resuming from the middle of a
+    /// poll repeats the whole poll.
+    fn changes(
         &self,
-        f: F,
-    ) -> Result<std::convert::Infallible> {
-        let mut last = self.creation;
-
-        let mut interval = tokio::time::interval(std::time::Duration::from_secs(1));
-        loop {
-            interval.tick().await;
-            let now = chrono::Utc::now().naive_utc();
-            dbg!(now);
-            let Ok(rc) = self.da_get_recent_changes(last.into(), true).await else {
-                eprintln!("error while trying to get recent changes... retrying");
-                continue;
-            };
-            dbg!(&rc);
-            let fref = &f;
-            rc.iter()
-                .for_each(|(hash, kind)| fref(*hash, kind.to_string()));
-            eprintln!("done with this update, waiting for next tick");
-            last = now;
-        }
+        since: Option<Self::Cursor>,
+    ) -> impl futures::Stream<Item = Result<StorageChange<Self::Cursor>>> + Send + '_ {
+        let last = since.unwrap_or(self.creation);
+        let interval = tokio::time::interval(std::time::Duration::from_secs(1));
+        let state = (last, std::collections::VecDeque::new(), interval);
+
+        futures::stream::unfold(
+            state,
+            move |(mut last, mut pending, mut interval)| async move {
+                loop {
+                    if let Some(change) = pending.pop_front() {
+                        return Some((Ok(change), (last, pending, interval)));
+                    }
+                    interval.tick().await;
+                    let now = chrono::Utc::now().naive_utc();
+                    let rc = match self.da_get_recent_changes(last.into(), true).await {
+                        Ok(rc) => rc,
+                        Err(e) => return Some((Err(e), (last, pending, interval))),
+                    };
+                    let count = rc.len();
+                    pending.extend(rc.into_iter().enumerate().map(|(i, (hash, kind))| {
+                        // the revision may be new all the same, looking it up is harmless if it is not
+                        let event = event_from_kind(&kind).unwrap_or_else(|| {
+                            eprintln!("[{hash}]: unknown recent change kind {kind:?}, treating it as created");
+                            StorageEvent::Created
+                        });
+                        StorageChange {
+                            hash,
+                            event,
+                            // only once the whole poll went through it is done
+                            cursor: if i + 1 == count { now } else { last },
+                        }
+                    }));
+                    last = now;
+                }
+            },
+        )
+    }
+
+    /// recent changes are looked up by time, so now is the end of them
+    async fn latest_cursor(&self) -> Result<Self::Cursor> {
+        Ok(chrono::Utc::now().naive_utc())
     }
+
+    fn is_transient(error: &error::Error) -> bool {
+        error.is_transient()
+    }
+}
+
+/// maps the `type` of a pkc recent change to a [`StorageEvent`], `None` for kinds not known to be emitted
+///
+/// the `recent_changes` endpoint of the data accounting extension ([api.yaml][api]) passes the MediaWiki change types
+/// `new` and `edit` (`RecentChange::parseFromRCType` in MediaWiki core) and `delete` for deleted pages, the one kind
+/// the guardian compared against before there were typed events. moves and imports have no known kind.
+///
+/// [api]: https://github.com/inblockio/mediawiki-extensions-Aqua/blob/63cbdd4047542bc75d5b43793482c3e405933b07/docs/api.yaml
+pub fn event_from_kind(kind: &str) -> Option<StorageEvent> {
+    match kind {
+        "new" | "edit" => Some(StorageEvent::Created),
+        "delete" => Some(StorageEvent::Deleted),
+        _ => None,
+    }
+}
+
+#[test]
+fn recent_change_kinds() {
+    assert_eq!(event_from_kind("delete"), Some(StorageEvent::Deleted));
+    assert_eq!(event_from_kind("new"), Some(StorageEvent::Created));
+    assert_eq!(event_from_kind("edit"), Some(StorageEvent::Created));
+    assert_eq!(event_from_kind("move"), None);
+    assert_eq!(event_from_kind("import"), None);
 }
 
 #[tokio::test]
@@ -36,7 +36,7 @@ impl From<&Message> for libsecp256k1::Message {
         let s = value.to_string();
         let mut msg = crypt::Keccak256::default();
         msg.update("\x19Ethereum Signed Message:\n");
-        msg.update(format!("{}", s.as_bytes().len()));
+        msg.update(format!("{}", s.len()));
         msg.update(s.as_bytes());
         libsecp256k1::Message::parse(&msg.finalize().into())
     }
@@ -181,7 +181,7 @@ pub async fn login<S: guardian_common::signing::Signer>(
             .collect();
         let mut query = siwe_sign_in_url.query_pairs_mut();
         query.clear();
-        query.extend_pairs(it.into_iter());
+        query.extend_pairs(it);
         drop(query);
         siwe_sign_in_url
     };
@@ -0,0 +1,246 @@
+//! What is shared of every chain, kept once per chain instead of on each of its revisions.
+//!
+//! A chain is a genesis with everything after it. Each revision lists the [`chains`](StateNode::chains) it continues,
+//! more than one below merges. An agreement shares the revisions before and after each page revision it names, those
+//! page revisions are its bounds on the chains they continue.
+//!
+//! Checking whether a revision is shared looks up its chains and the bounds of the agreements found there. On chains
+//! that never forked every revision comes before or after every bound, so no revisions are walked. Forked chains fall
+//! back to walking the ancestors between the revision and a bound.
+
+use std::{
+    collections::{HashMap, HashSet},
+    sync::{Arc, Weak},
+};
+
+use contract_interpreter::Principal;
+use guardian_common::prelude::*;
+use parking_lot::RwLock;
+
+use crate::{ContractNode, GuardianState, RwWeaakMap, StateNode};
+
+/// the agreements sharing revisions of a chain, the revisions without children and how often it forks
+#[derive(Debug, Default)]
+pub struct ChainIndex {
+    /// mapping receivers to the agreements with them to their bounds on this chain
+    pub shares: RwLock<HashMap<Principal, HashMap<Hash, Share>>>,
+    /// revisions continuing the chain without child revisions
+    pub tips: RwWeaakMap<Hash, StateNode>,
+    /// revisions continuing the chain with more than one child revision
+    pub forks: RwLock<usize>,
+}
+
+/// an agreement sharing revisions of a chain
+#[derive(Debug, Clone)]
+pub struct Share {
+    pub contract: Weak<ContractNode>,
+    /// the page revisions it names on this chain, revisions before and after them are shared
+    pub bounds: HashMap<Hash, Weak<StateNode>>,
+}
+
+/// whether `ancestor` is `node` or lies before it, following merges
+///
+/// only revisions at least as far from the genesis as `ancestor` are walked.
+pub fn is_ancestor(ancestor: &StateNode, node: &StateNode) -> bool {
+    if node.hash == ancestor.hash {
+        return true;
+    }
+    let mut seen = HashSet::new();
+    let mut stack: Vec<Arc<StateNode>> = node.parents().collect();
+    while let Some(node) = stack.pop() {
+        if node.hash == ancestor.hash {
+            return true;
+        }
+        stack.extend(
+            node.parents()
+                .filter(|parent| parent.height >= ancestor.height && seen.insert(parent.hash)),
+        );
+    }
+    false
+}
+
+/// whether `node` comes before or after one of `bounds`, all on a chain of `node`
+fn within_bounds(node: &StateNode, forked: bool, bounds: &HashMap<Hash, Weak<StateNode>>) -> bool {
+    bounds.values().filter_map(Weak::upgrade).any(|bound| {
+        // the revisions of a chain that never forked lie on a line
+        !forked || is_ancestor(&bound, node) || is_ancestor(node, &bound)
+    })
+}
+
+impl<S> GuardianState<S> {
+    /// registers a new revision as a tip of its chains, its parents no longer are
+    pub(crate) fn index_node(&self, node: &Arc<StateNode>) {
+        for parent in node.parents() {
+            for chain in &parent.chains {
+                let Some(index) = self.access_index.get(chain) else {
+                    continue;
+                };
+                index.tips.write().remove(&parent.hash);
+                if parent.leafs.len() == 2 {
+                    *index.forks.write() += 1;
+                }
+            }
+        }
+        for chain in &node.chains {
+            self.access_index
+                .entry(*chain)
+                .or_default()
+                .tips
+                .write()
+                .insert(node.hash, node.clone());
+        }
+    }
+
+    /// undoes [`index_node`](Self::index_node) for a revision taken out of the state
+    pub(crate) fn unindex_node(&self, node: &Arc<StateNode>) {
+        for chain in &node.chains {
+            if let Some(index) = self.access_index.get(chain) {
+                index.tips.write().remove(&node.hash);
+            }
+        }
+        for parent in node.parents() {
+            for chain in &parent.chains {
+                let Some(index) = self.access_index.get(chain) else {
+                    continue;
+                };
+                match parent.leafs.len() {
+                    0 => {
+                        index.tips.write().insert(parent.hash, parent.clone());
+                    }
+                    1 => {
+                        let mut forks = index.forks.write();
+                        *forks = forks.saturating_sub(1);
+                    }
+                    _ => {}
+                }
+            }
+        }
+        if node.prev.upgrade().is_none() {
+            self.access_index.remove(&node.hash);
+        }
+    }
+
+    /// shares the revisions before and after `page` to `receiver`
+    pub(crate) fn index_share(
+        &self,
+        contract: (Hash, Arc<ContractNode>),
+        receiver: Principal,
+        page: &Arc<StateNode>,
+    ) {
+        let (contract_hash, contract_node) = contract;
+        for chain in &page.chains {
+            self.access_index
+                .entry(*chain)
+                .or_default()
+                .shares
+                .write()
+                .entry(receiver)
+                .or_default()
+                .entry(contract_hash)
+                .or_insert_with(|| Share {
+                    contract: Arc::downgrade(&contract_node),
+                    bounds: HashMap::new(),
+                })
+                .bounds
+                .insert(page.hash, Arc::downgrade(page));
+            contract_node.chains.write().insert(*chain);
+        }
+    }
+
+    /// takes back what `contract_hash` shares, by the page revisions `unshared` only or all of it
+    pub(crate) fn unindex_share(
+        &self,
+        contract_hash: Hash,
+        contract_node: &ContractNode,
+        unshared: Option<&HashSet<Hash>>,
+    ) {
+        let chains: Vec<Hash> = contract_node.chains.read().iter().copied().collect();
+        for chain in chains {
+            let Some(index) = self.access_index.get(&chain) else {
+                continue;
+            };
+            let mut still_shared = false;
+            for shares in index.shares.write().values_mut() {
+                if let Some(share) = shares.get_mut(&contract_hash) {
+                    if let Some(unshared) = unshared {
+                        share.bounds.retain(|hash, _| !unshared.contains(hash));
+                    } else {
+                        share.bounds.clear();
+                    }
+                    if share.bounds.is_empty() {
+                        shares.remove(&contract_hash);
+                    } else {
+                        still_shared = true;
+                    }
+                }
+            }
+            drop(index);
+            if !still_shared {
+                contract_node.chains.write().remove(&chain);
+            }
+        }
+    }
+
+    /// the agreements sharing `node` to `receiver`
+    pub fn shared_with(
+        &self,
+        node: &StateNode,
+        receiver: &Principal,
+    ) -> Vec<(Hash, Arc<ContractNode>)> {
+        let mut shared: Vec<(Hash, Arc<ContractNode>)> = vec![];
+        for chain in &node.chains {
+            let Some(index) = self.access_index.get(chain) else {
+                continue;
+            };
+            let forked = *index.forks.read() > 0;
+            let shares = index.shares.read();
+            let Some(shares) = shares.get(receiver) else {
+                continue;
+            };
+            for (contract_hash, share) in shares {
+                if shared.iter().any(|(hash, _contract)| hash == contract_hash) {
+                    continue;
+                }
+                let Some(contract) = share.contract.upgrade() else {
+                    continue;
+                };
+                if within_bounds(node, forked, &share.bounds) {
+                    shared.push((*contract_hash, contract));
+                }
+            }
+        }
+        shared
+    }
+
+    /// the revisions without children the agreement `contract_hash` shares
+    pub fn shared_latests(
+        &self,
+        contract_hash: Hash,
+        contract_node: &ContractNode,
+    ) -> Vec<Arc<StateNode>> {
+        let chains: Vec<Hash> = contract_node.chains.read().iter().copied().collect();
+        let mut latests: Vec<Arc<StateNode>> = vec![];
+        for chain in chains {
+            let Some(index) = self.access_index.get(&chain) else {
+                continue;
+            };
+            let forked = *index.forks.read() > 0;
+            let tips: Vec<Arc<StateNode>> = index.tips.read().values().collect();
+            let bounds: HashMap<Hash, Weak<StateNode>> = index
+                .shares
+                .read()
+                .values()
+                .filter_map(|shares| shares.get(&contract_hash))
+                .flat_map(|share| share.bounds.clone())
+                .collect();
+            drop(index);
+            for tip in tips {
+                let known = latests.iter().any(|latest| latest.hash == tip.hash);
+                if !known && within_bounds(&tip, forked, &bounds) {
+                    latests.push(tip);
+                }
+            }
+        }
+        latests
+    }
+}
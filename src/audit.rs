@@ -0,0 +1,181 @@
+//! An append-only log of every access decision made for remote guardians.
+//!
+//! Every entry carries the hash of the entry before it, so removing or changing an entry breaks the chain from there
+//! on, which [`verify`] points out. The log is kept as JSON Lines, one [`AuditEntry`] per line.
+
+use std::{
+    io::{BufRead, Write},
+    path::{Path, PathBuf},
+};
+
+use guardian_common::prelude::*;
+use parking_lot::Mutex;
+
+/// what a remote guardian asked for
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AuditRequest {
+    List,
+    GetBranch(Hash),
+    GetRevision(Hash),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AuditOutcome {
+    Allowed,
+    Denied,
+}
+
+/// a single access decision, as it is handed to [`AuditLog::record`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AuditRecord {
+    /// the DER bytes of the certificate the remote guardian connected with
+    pub peer_cert: Vec<u8>,
+    /// the user the remote guardian serves, if it is known
+    pub user: Option<Address>,
+    pub request: AuditRequest,
+    pub outcome: AuditOutcome,
+    /// the contracts that allowed the request, for re-shared revisions the whole delegation
+    pub contracts: Vec<Hash>,
+}
+
+/// a line of the audit log
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct AuditEntry {
+    pub seqno: u64,
+    pub time: chrono::NaiveDateTime,
+    /// sha3-512 of the DER bytes of the peer certificate
+    pub peer: Hash,
+    pub user: Option<Address>,
+    pub request: AuditRequest,
+    pub outcome: AuditOutcome,
+    pub contracts: Vec<Hash>,
+    /// the hash of the previous entry, zero for the first one
+    pub prev: Hash,
+    /// sha3-512 of this entry in JSON, with this field set to zero
+    pub hash: Hash,
+}
+
+impl AuditEntry {
+    /// the hash this entry should have
+    pub fn compute_hash(&self) -> Hash {
+        let unhashed = AuditEntry {
+            hash: Hash::default(),
+            ..self.clone()
+        };
+        let json = serde_json::to_vec(&unhashed).expect("audit entries always serialize");
+        Hash::from(crypt::Hasher::digest(json))
+    }
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum AuditError {
+    #[error("io error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("json error: {0}")]
+    Json(#[from] serde_json::Error),
+    #[error("audit log tampered with at entry {0}")]
+    Broken(u64),
+}
+
+#[derive(Debug)]
+struct Head {
+    file: std::fs::File,
+    seqno: u64,
+    hash: Hash,
+}
+
+/// The audit log of a guardian, appending to a JSON Lines file
+#[derive(Debug)]
+pub struct AuditLog {
+    path: PathBuf,
+    head: Mutex<Head>,
+}
+
+impl AuditLog {
+    /// continues the log at `path`, refusing to append to a broken chain
+    pub fn open(path: impl Into<PathBuf>) -> Result<Self, AuditError> {
+        let path = path.into();
+        let (seqno, hash) = match read(&path) {
+            Ok(entries) => {
+                check_chain(&entries)?;
+                entries
+                    .last()
+                    .map_or((0, Hash::default()), |last| (last.seqno + 1, last.hash))
+            }
+            Err(AuditError::Io(e)) if e.kind() == std::io::ErrorKind::NotFound => {
+                (0, Hash::default())
+            }
+            Err(e) => return Err(e),
+        };
+        let file = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&path)?;
+        Ok(AuditLog {
+            path,
+            head: Mutex::new(Head { file, seqno, hash }),
+        })
+    }
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// appends the decision to the log, it is on disk once this returns
+    pub fn record(&self, record: AuditRecord) -> Result<AuditEntry, AuditError> {
+        let mut head = self.head.lock();
+        let mut entry = AuditEntry {
+            seqno: head.seqno,
+            time: chrono::Utc::now().naive_utc(),
+            peer: Hash::from(crypt::Hasher::digest(&record.peer_cert)),
+            user: record.user,
+            request: record.request,
+            outcome: record.outcome,
+            contracts: record.contracts,
+            prev: head.hash,
+            hash: Hash::default(),
+        };
+        entry.hash = entry.compute_hash();
+
+        let mut line = serde_json::to_vec(&entry)?;
+        line.push(b'\n');
+        head.file.write_all(&line)?;
+        head.file.sync_data()?;
+
+        head.seqno += 1;
+        head.hash = entry.hash;
+        Ok(entry)
+    }
+}
+
+/// all entries of the log at `path`, without checking them
+pub fn read(path: impl AsRef<Path>) -> Result<Vec<AuditEntry>, AuditError> {
+    let file = std::io::BufReader::new(std::fs::File::open(path)?);
+    let mut entries = vec![];
+    for line in file.lines() {
+        let line = line?;
+        if !line.trim().is_empty() {
+            entries.push(serde_json::from_str(&line)?);
+        }
+    }
+    Ok(entries)
+}
+
+/// checks the chain of the log at `path`, returning how many entries it has
+pub fn verify(path: impl AsRef<Path>) -> Result<usize, AuditError> {
+    let entries = read(path)?;
+    check_chain(&entries)?;
+    Ok(entries.len())
+}
+
+fn check_chain(entries: &[AuditEntry]) -> Result<(), AuditError> {
+    let mut prev = Hash::default();
+    for (seqno, entry) in (0..).zip(entries) {
+        if entry.seqno != seqno || entry.prev != prev || entry.hash != entry.compute_hash() {
+            return Err(AuditError::Broken(seqno));
+        }
+        prev = entry.hash;
+    }
+    Ok(())
+}
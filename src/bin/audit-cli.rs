@@ -0,0 +1,73 @@
+use std::io::Write;
+
+use clap::{Parser, Subcommand};
+use guardian::audit;
+use guardian_common::custom_types::*;
+
+#[derive(Parser)]
+#[command(author, version, about, long_about = None)]
+struct Cli {
+    /// The audit log of the guardian, see `AUDIT_LOG_PATH`
+    #[arg(short, long, default_value = "audit.jsonl")]
+    log: std::path::PathBuf,
+    #[command(subcommand)]
+    command: Commands,
+}
+
+#[derive(Debug, Subcommand)]
+enum Commands {
+    /// Checks that no entry was changed or removed
+    Verify {},
+    /// Prints the entries as JSON Lines
+    Export {
+        /// only decisions about this user
+        #[arg(short, long)]
+        user: Option<ethaddr::Address>,
+        /// only decisions about the peer with this certificate hash
+        #[arg(short, long)]
+        peer: Option<String>,
+        /// only decisions since this time, e.g. `20240525000123`
+        #[arg(short, long)]
+        since: Option<String>,
+    },
+}
+
+fn main() {
+    let args = Cli::parse();
+    match args.command {
+        Commands::Verify {} => match audit::verify(&args.log) {
+            Ok(count) => println!("audit log intact, {count} entries"),
+            Err(e) => {
+                eprintln!("{e}");
+                std::process::exit(1);
+            }
+        },
+        Commands::Export { user, peer, since } => {
+            let peer: Option<Hash> =
+                peer.map(|peer| peer.parse().expect("given peer could not be parsed"));
+            let since: Option<chrono::NaiveDateTime> = since.map(|since| {
+                since
+                    .parse::<Timestamp>()
+                    .expect("given time could not be parsed")
+                    .into()
+            });
+
+            if let Err(e) = audit::verify(&args.log) {
+                eprintln!("warning: {e}");
+            }
+            let entries = audit::read(&args.log).expect("failed to read audit log");
+
+            let mut stdout = std::io::stdout().lock();
+            for entry in entries {
+                if user.is_some_and(|user| entry.user != Some(user))
+                    || peer.is_some_and(|peer| entry.peer != peer)
+                    || since.is_some_and(|since| entry.time < since)
+                {
+                    continue;
+                }
+                serde_json::to_writer(&mut stdout, &entry).unwrap();
+                writeln!(stdout).unwrap();
+            }
+        }
+    }
+}
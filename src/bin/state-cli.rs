@@ -0,0 +1,60 @@
+use clap::{Parser, Subcommand};
+use guardian::{snapshot::Snapshot, GuardianState};
+use guardian_common::custom_types::*;
+use local_storage::prelude::*;
+
+#[derive(Parser)]
+#[command(author, version, about, long_about = None)]
+struct Cli {
+    /// The snapshot of the guardian state, see `SNAPSHOT_PATH`
+    #[arg(short, long, default_value = "snapshot.json")]
+    snapshot: std::path::PathBuf,
+    #[command(subcommand)]
+    command: Commands,
+}
+
+#[derive(Debug, Subcommand)]
+enum Commands {
+    /// Explains whether a user can access a revision, and why not
+    ExplainAccess {
+        /// the owner the guardian shares for, `ADMIN_USER` if not given
+        #[arg(short, long)]
+        owner: Option<ethaddr::Address>,
+        user: ethaddr::Address,
+        hash: String,
+    },
+    /// Lists the forked chains, with their fork points, tips and resolutions
+    Forks {},
+}
+
+fn main() {
+    dotenv::dotenv().ok();
+    let args = Cli::parse();
+    let Some(snapshot) =
+        Snapshot::<serde_json::Value>::load(&args.snapshot).expect("failed to read snapshot")
+    else {
+        eprintln!("no snapshot at {}", args.snapshot.display());
+        std::process::exit(1);
+    };
+    let (state, _cursor) = GuardianState::restore(MemoryStorage::new(), snapshot);
+
+    match args.command {
+        Commands::ExplainAccess { owner, user, hash } => {
+            let owner = owner.unwrap_or_else(|| {
+                std::env::var("ADMIN_USER")
+                    .expect("no owner given and ADMIN_USER not set")
+                    .parse()
+                    .expect("failed to parse ADMIN_USER")
+            });
+            let hash: Hash = hash.parse().expect("given hash could not be parsed");
+            let explanation = state.explain_access(user, hash, owner);
+            println!("{}", serde_json::to_string_pretty(&explanation).unwrap());
+            if !explanation.is_granted() {
+                std::process::exit(2);
+            }
+        }
+        Commands::Forks {} => {
+            println!("{}", serde_json::to_string_pretty(&state.forks()).unwrap());
+        }
+    }
+}
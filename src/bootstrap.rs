@@ -0,0 +1,141 @@
+//! Building the state from a storage, for starts without a snapshot.
+//!
+//! The branches of all latest revisions are listed first, then every chain is added from its genesis up. Chains
+//! sharing a genesis are one chain here, so forks are added by the same task. At most `concurrency` branches are
+//! listed and chains added at once, and a chain failing to load does not keep the others from it.
+
+use std::{
+    collections::BTreeMap,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use futures::StreamExt;
+use guardian_common::{
+    prelude::*,
+    storage::{PageContext, Storage},
+};
+
+use crate::{Error, GuardianState};
+
+/// how many branches are listed and chains added at once unless configured otherwise
+pub const DEFAULT_BOOTSTRAP_CONCURRENCY: usize = 16;
+
+/// reported every time a chain is done
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Progress {
+    pub chains_done: usize,
+    pub chains_total: usize,
+    pub revisions_verified: usize,
+}
+
+/// a branch that could not be added, the revisions before the failing one are in the state
+#[derive(Debug)]
+pub struct ChainFailure<S: Storage> {
+    /// the genesis of the chain, or the latest revision if its branch could not be listed
+    pub chain: Hash,
+    /// the latest revision of the failed branch
+    pub latest: Hash,
+    pub error: Error<S>,
+}
+
+#[derive(Debug)]
+pub struct BootstrapSummary<S: Storage> {
+    /// chains with their branches listed, failed ones included
+    pub chains: usize,
+    pub revisions_verified: usize,
+    pub failures: Vec<ChainFailure<S>>,
+}
+
+impl<S: Storage> GuardianState<S>
+where
+    S::Context: PageContext,
+{
+    /// adds the chains of `latests` and everything they are merged with, see [`crate::bootstrap`]
+    pub async fn bootstrap(
+        &self,
+        latests: Vec<Hash>,
+        concurrency: usize,
+        mut progress: impl FnMut(Progress),
+    ) -> BootstrapSummary<S> {
+        let concurrency = concurrency.max(1);
+        let mut failures = vec![];
+
+        // genesis first, grouped by genesis
+        let mut chains: BTreeMap<Hash, Vec<(Hash, Vec<Hash>)>> = BTreeMap::new();
+        let mut branches = futures::stream::iter(latests)
+            .map(|latest| async move { (latest, self.storage.get_branch(latest).await) })
+            .buffer_unordered(concurrency);
+        while let Some((latest, branch)) = branches.next().await {
+            let mut hashes = match branch {
+                Ok(branch) => branch.hashes,
+                Err(e) => {
+                    failures.push(ChainFailure {
+                        chain: latest,
+                        latest,
+                        error: Error::Storage(e),
+                    });
+                    continue;
+                }
+            };
+            hashes.reverse();
+            let Some(genesis) = hashes.first().copied() else {
+                failures.push(ChainFailure {
+                    chain: latest,
+                    latest,
+                    error: Error::EmptyBranch,
+                });
+                continue;
+            };
+            chains.entry(genesis).or_default().push((latest, hashes));
+        }
+        drop(branches);
+
+        let revisions_verified = AtomicUsize::new(0);
+        let revisions_verified_ref = &revisions_verified;
+        let chains_total = chains.len();
+        let mut done = futures::stream::iter(chains)
+            .map(|(genesis, branches)| async move {
+                let mut failures = vec![];
+                for (latest, hashes) in branches {
+                    for hash in hashes {
+                        if self.get_node(&hash).is_some() {
+                            continue;
+                        }
+                        // the branch of a merged revision is not listed, it comes along here
+                        match self.add_with_ancestors(hash).await {
+                            Ok(added) => {
+                                revisions_verified_ref.fetch_add(added.len(), Ordering::Relaxed);
+                            }
+                            Err(error) => {
+                                failures.push(ChainFailure {
+                                    chain: genesis,
+                                    latest,
+                                    error,
+                                });
+                                break;
+                            }
+                        }
+                    }
+                }
+                failures
+            })
+            .buffer_unordered(concurrency);
+        let mut chains_done = 0;
+        while let Some(chain_failures) = done.next().await {
+            failures.extend(chain_failures);
+            chains_done += 1;
+            progress(Progress {
+                chains_done,
+                chains_total,
+                revisions_verified: revisions_verified.load(Ordering::Relaxed),
+            });
+        }
+        drop(done);
+
+        BootstrapSummary {
+            chains: chains_total,
+            revisions_verified: revisions_verified.into_inner(),
+            failures,
+        }
+    }
+}
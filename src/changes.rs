@@ -0,0 +1,225 @@
+//! Following the changes of the storage without getting stuck on one of them.
+//!
+//! A change is handled again while it fails for a reason that may go away, at most [`Retry::attempts`] times and
+//! waiting longer every time. Changes that cannot be handled end up in the [`DeadLetters`], a JSON Lines file, and
+//! the guardian carries on with the next change.
+
+use std::{future::Future, io::Write, path::Path, sync::Arc, time::Duration};
+
+use guardian_common::{
+    prelude::*,
+    storage::{PageContext, Storage, StorageChange, StorageEvent},
+};
+use parking_lot::Mutex;
+
+use crate::{Error, GuardianState, StateNode};
+
+/// why a change could not be handled
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ChangeFailure {
+    /// trying again may succeed, like after a lost connection
+    Transient(String),
+    /// trying again fails the same way, like for a revision that is gone
+    Permanent(String),
+}
+
+impl std::fmt::Display for ChangeFailure {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ChangeFailure::Transient(e) => write!(f, "{e}"),
+            ChangeFailure::Permanent(e) => write!(f, "{e} (permanent)"),
+        }
+    }
+}
+
+impl<S: Storage> From<Error<S>> for ChangeFailure {
+    fn from(e: Error<S>) -> Self {
+        if e.is_transient() {
+            ChangeFailure::Transient(e.to_string())
+        } else {
+            ChangeFailure::Permanent(e.to_string())
+        }
+    }
+}
+
+impl<S: Storage> Error<S> {
+    /// only storage errors may go away, the storage tells which ones. a revision refused once is refused again
+    pub fn is_transient(&self) -> bool {
+        match self {
+            Error::Storage(e) => S::is_transient(e),
+            _ => false,
+        }
+    }
+}
+
+/// how often a change failing transiently is handled before it is given up
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Retry {
+    /// how often a change is handled at most, the first time included
+    pub attempts: u32,
+    /// the wait before the second attempt, it doubles for every attempt after that
+    pub backoff: Duration,
+    pub max_backoff: Duration,
+}
+
+impl Default for Retry {
+    fn default() -> Self {
+        Retry {
+            attempts: 5,
+            backoff: Duration::from_secs(1),
+            max_backoff: Duration::from_secs(60),
+        }
+    }
+}
+
+/// a change that was given up, a line of the [`DeadLetters`]
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct DeadLetter {
+    pub time: chrono::NaiveDateTime,
+    pub hash: Hash,
+    pub event: StorageEvent,
+    pub error: String,
+    /// how often the change was handled before it was given up
+    pub attempts: u32,
+}
+
+/// the changes given up on, appended to a JSON Lines file to be looked into by hand
+#[derive(Debug)]
+pub struct DeadLetters {
+    file: Mutex<std::fs::File>,
+}
+
+impl DeadLetters {
+    pub fn open(path: impl AsRef<Path>) -> std::io::Result<Self> {
+        let file = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)?;
+        Ok(DeadLetters {
+            file: Mutex::new(file),
+        })
+    }
+
+    /// appends the letter, it is on disk once this returns
+    pub fn record(&self, letter: &DeadLetter) -> std::io::Result<()> {
+        let mut line = serde_json::to_vec(letter)?;
+        line.push(b'\n');
+        let mut file = self.file.lock();
+        file.write_all(&line)?;
+        file.sync_data()
+    }
+}
+
+/// all letters in the file at `path`
+pub fn read(path: impl AsRef<Path>) -> std::io::Result<Vec<DeadLetter>> {
+    std::fs::read_to_string(path)?
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| serde_json::from_str(line).map_err(std::io::Error::from))
+        .collect()
+}
+
+/// hands `change` to `handle` until it succeeds, fails permanently or runs out of attempts
+///
+/// returns whether the cursor may move past the change, which is only refused if the change failed and could not be
+/// put into the dead letters either.
+pub async fn handle_change<C, F, Fut>(
+    change: &StorageChange<C>,
+    retry: &Retry,
+    dead_letters: &DeadLetters,
+    mut handle: F,
+) -> bool
+where
+    F: FnMut(Hash, StorageEvent) -> Fut,
+    Fut: Future<Output = Result<(), ChangeFailure>>,
+{
+    let mut backoff = retry.backoff;
+    let mut attempts = 0;
+    let failure = loop {
+        attempts += 1;
+        match handle(change.hash, change.event).await {
+            Ok(()) => return true,
+            Err(ChangeFailure::Transient(e)) if attempts < retry.attempts => {
+                eprintln!(
+                    "[{}]: failed to handle change, retrying in {backoff:?}: {e}",
+                    change.hash
+                );
+                tokio::time::sleep(backoff).await;
+                backoff = (backoff * 2).min(retry.max_backoff);
+            }
+            Err(e) => break e,
+        }
+    };
+
+    eprintln!(
+        "[{}]: giving up on change after {attempts} attempts: {failure}",
+        change.hash
+    );
+    let letter = DeadLetter {
+        time: chrono::Utc::now().naive_utc(),
+        hash: change.hash,
+        event: change.event,
+        error: failure.to_string(),
+        attempts,
+    };
+    match dead_letters.record(&letter) {
+        Ok(()) => true,
+        Err(e) => {
+            eprintln!("[{}]: failed to write dead letter: {e}", change.hash);
+            false
+        }
+    }
+}
+
+impl<S: Storage> GuardianState<S>
+where
+    S::Context: PageContext,
+{
+    /// brings the state up to date with a change of the storage, returning the nodes it added
+    ///
+    /// a revision of the branch the state refuses ends the update there but is no failure, the state stays the same
+    /// no matter how often it is tried.
+    pub async fn apply_change(
+        &self,
+        hash: Hash,
+        event: StorageEvent,
+    ) -> Result<Vec<Arc<StateNode>>, Error<S>> {
+        // pattern agreements follow the page to its new title
+        if event == StorageEvent::Moved {
+            self.refresh_page(hash).await?;
+        }
+        if event == StorageEvent::Deleted {
+            let mut node = self.get_node(&hash);
+            while let Some(sn) = node.filter(|sn| sn.leafs.is_empty()) {
+                self.rm(sn.hash);
+                node = sn.prev.upgrade();
+            }
+            return Ok(vec![]);
+        }
+
+        let hashes = self
+            .storage
+            .get_branch(hash)
+            .await
+            .map_err(Error::Storage)?
+            .hashes;
+        // everything before the latest revision already in the state is in it as well
+        let known = hashes
+            .iter()
+            .position(|hash| self.get_node(hash).is_some())
+            .unwrap_or(hashes.len());
+        let mut added = vec![];
+        for need in hashes[..known].iter().rev() {
+            // merges bring the branch they merge along
+            match self.add_with_ancestors(*need).await {
+                Ok(nodes) => added.extend(nodes),
+                Err(e @ Error::Storage(_)) => return Err(e),
+                Err(e) => {
+                    eprintln!("[{need}]: not added to the state: {e}");
+                    break;
+                }
+            }
+        }
+        Ok(added)
+    }
+}
@@ -1,3 +1,4 @@
+use contract_interpreter::{ContractKind, TemplateRegistry, UnconfiguredTemplate};
 use ethaddr::Address;
 use guardian_common::custom_types::{Revision, RevisionContent, Timestamp};
 use verifier::v1_1::hashes::{metadata_hash, verification_hash};
@@ -19,7 +20,7 @@ fn make_new_cert(
 
 fn make_genesis(content: RevisionContent, time: Timestamp, domain_id: String) -> Revision {
     use guardian_common::prelude::*;
-    let metadata_hash = metadata_hash(&domain_id, &time, None);
+    let metadata_hash = metadata_hash(&domain_id, &time, None, None);
 
     let verification_hash = verification_hash(&content.content_hash, &metadata_hash, None, None);
 
@@ -29,6 +30,7 @@ fn make_genesis(content: RevisionContent, time: Timestamp, domain_id: String) ->
             time_stamp: time.clone(),
             verification_hash,
             previous_verification_hash: None,
+            merge_verification_hash: None,
             metadata_hash,
             domain_id,
         },
@@ -90,6 +92,7 @@ fn signed_revision_v1_1<S: guardian_common::signing::Signer>(
         &domain_id,
         &time_stamp,
         Some(&rev.metadata.verification_hash),
+        None,
     );
     let verification_hash = verifier::v1_1::hashes::verification_hash(
         &content_hash,
@@ -108,6 +111,7 @@ fn signed_revision_v1_1<S: guardian_common::signing::Signer>(
             time_stamp,
             verification_hash,
             previous_verification_hash: Some(rev.metadata.verification_hash),
+            merge_verification_hash: None,
             metadata_hash,
         },
         signature: Some(sig),
@@ -131,12 +135,13 @@ pub fn make_guardian_cert<S: guardian_common::signing::Signer>(
             port,
             cert: cert.der().to_vec().into(),
             guardian,
+            template_version: ContractKind::TlsIdentityClaim.current_version(),
         })
-        .make_content();
+        .make_content(&TemplateRegistry::builtin())
+        .expect("built-in template");
 
     let genesis = make_genesis(contract_content, now.into(), guardian.to_string());
-    let guardian_signed =
-        signed_revision_v1_1(&genesis, s, guardian.to_string(), now.into());
+    let guardian_signed = signed_revision_v1_1(&genesis, s, guardian.to_string(), now.into());
 
     Ok((cert, vec![genesis, guardian_signed]))
 }
@@ -156,12 +161,13 @@ pub fn make_tls_cert_contract<S: guardian_common::signing::Signer>(
             port,
             cert: cert.to_vec().into(),
             guardian,
+            template_version: ContractKind::TlsIdentityClaim.current_version(),
         })
-        .make_content();
+        .make_content(&TemplateRegistry::builtin())
+        .expect("built-in template");
 
     let genesis = make_genesis(contract_content, now.into(), guardian.to_string());
-    let guardian_signed =
-        signed_revision_v1_1(&genesis, s, guardian.to_string(), now.into());
+    let guardian_signed = signed_revision_v1_1(&genesis, s, guardian.to_string(), now.into());
     // genesis.signature = Some(sig); // this seems to no longer be required? see make_guardian_cert
     Ok(vec![genesis, guardian_signed])
 }
@@ -173,15 +179,97 @@ pub fn make_guardian_servitude<S: guardian_common::signing::Signer>(
     let guardian = Address::from(s.identity());
     let now = chrono::Utc::now().naive_utc();
     let contract_content = contract_interpreter::Contract::GuardianServitude(
-        contract_interpreter::GuardianServitude { guardian, user },
+        contract_interpreter::GuardianServitude {
+            guardian,
+            user,
+            template_version: ContractKind::GuardianServitude.current_version(),
+        },
     )
-    .make_content();
+    .make_content(&TemplateRegistry::builtin())
+    .expect("built-in template");
     let genesis = make_genesis(contract_content, now.into(), guardian.to_string());
-    let guardian_signed =
-        signed_revision_v1_1(&genesis, s, guardian.to_string(), now.into());
+    let guardian_signed = signed_revision_v1_1(&genesis, s, guardian.to_string(), now.into());
     vec![genesis, guardian_signed]
 }
 
+/// makes a chain declaring `contract` and signing it by `s`
+///
+/// further signatures, like the one of the receiver of an agreement, are added by [`sign_revision`].
+pub fn make_contract<S: guardian_common::signing::Signer>(
+    contract: &contract_interpreter::Contract,
+    templates: &TemplateRegistry,
+    s: S,
+) -> Result<Vec<Revision>, UnconfiguredTemplate> {
+    let signer = Address::from(s.identity());
+    let now = chrono::Utc::now().naive_utc();
+    let genesis = make_genesis(
+        contract.make_content(templates)?,
+        now.into(),
+        signer.to_string(),
+    );
+    let signed = signed_revision_v1_1(&genesis, s, signer.to_string(), now.into());
+    Ok(vec![genesis, signed])
+}
+
+/// signs `rev` by `s` in a new revision after it
+pub fn sign_revision<S: guardian_common::signing::Signer>(rev: &Revision, s: S) -> Revision {
+    let signer = Address::from(s.identity());
+    let now = chrono::Utc::now().naive_utc();
+    signed_revision_v1_1(rev, s, signer.to_string(), now.into())
+}
+
+/// revokes the access agreement with the genesis hash `agreement`, `s` has to be its sender
+///
+/// the template has no built-in hash yet, so it has to be in `templates`.
+pub fn make_access_revocation<S: guardian_common::signing::Signer>(
+    agreement: guardian_common::custom_types::Hash,
+    templates: &TemplateRegistry,
+    s: S,
+) -> Result<Vec<Revision>, UnconfiguredTemplate> {
+    let revocation =
+        contract_interpreter::Contract::AccessRevocation(contract_interpreter::AccessRevocation {
+            sender: Address::from(s.identity()),
+            agreement,
+            template_version: ContractKind::AccessRevocation.current_version(),
+        });
+    make_contract(&revocation, templates, s)
+}
+
+/// resolves the fork at the revision `fork` by picking `tip`, `s` has to be the owner of the chain
+///
+/// the template has no built-in hash yet, so it has to be in `templates`.
+pub fn make_fork_resolution<S: guardian_common::signing::Signer>(
+    fork: guardian_common::custom_types::Hash,
+    tip: guardian_common::custom_types::Hash,
+    templates: &TemplateRegistry,
+    s: S,
+) -> Result<Vec<Revision>, UnconfiguredTemplate> {
+    let resolution =
+        contract_interpreter::Contract::ForkResolution(contract_interpreter::ForkResolution {
+            sender: Address::from(s.identity()),
+            fork,
+            tip,
+            template_version: ContractKind::ForkResolution.current_version(),
+        });
+    make_contract(&resolution, templates, s)
+}
+
+/// forms a group of `members`, `s` becomes its owner
+///
+/// the template has no built-in hash yet, so it has to be in `templates`.
+pub fn make_group<S: guardian_common::signing::Signer>(
+    members: Vec<Address>,
+    templates: &TemplateRegistry,
+    s: S,
+) -> Result<Vec<Revision>, UnconfiguredTemplate> {
+    let group = contract_interpreter::Contract::Group(contract_interpreter::Group {
+        owner: Address::from(s.identity()),
+        members,
+        template_version: ContractKind::Group.current_version(),
+    });
+    make_contract(&group, templates, s)
+}
+
 #[test]
 fn generate_contracts() {
     make_new_cert(
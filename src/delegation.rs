@@ -0,0 +1,126 @@
+//! Re-sharing of received revisions, by agreements whose sender received the pages themselves.
+//!
+//! A receiver may pass on what they got if the agreement they got it by has
+//! [`may_reshare`](contract_interpreter::AccessAgreement::may_reshare) set. Their own agreement to a third party then
+//! only gives access as long as the agreement they received the revisions by still holds, which in turn may stem from
+//! a re-sharing agreement. [`Delegation`] is that chain of agreements, down to the one by the original owner.
+//!
+//! Only agreements in this state count as proof. A sender who received a revision by none of them is taken to be its
+//! original owner.
+
+use std::sync::Arc;
+
+use contract_interpreter::{AccessAgreementEffects, ContractEffect};
+use guardian_common::{prelude::*, storage::Storage};
+
+use crate::{ContractNode, GuardianState, StateNode};
+
+/// how often revisions may be shared onward unless configured otherwise
+pub const DEFAULT_MAX_DELEGATION_DEPTH: usize = 3;
+
+/// the agreements a revision is shared by, from the one sharing it to the user down to the one by its original owner
+///
+/// holds on to the [`ContractNode`]s, so they are kept as proof for as long as the delegation is.
+#[derive(Debug, Clone)]
+pub struct Delegation {
+    pub links: Vec<(Hash, Arc<ContractNode>)>,
+}
+
+impl Delegation {
+    /// the hashes of the agreements, the one sharing to the user first
+    pub fn contracts(&self) -> Vec<Hash> {
+        self.links.iter().map(|(hash, _contract)| *hash).collect()
+    }
+    /// how often the revision was shared onward, zero if it was shared by its owner
+    pub fn depth(&self) -> usize {
+        self.links.len().saturating_sub(1)
+    }
+}
+
+#[derive(thiserror::Error, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DelegationError {
+    #[error("the sender received the revision by no agreement allowing to share it onward")]
+    ReshareNotAllowed,
+    #[error("the revision was shared onward more than {0} times")]
+    TooDeep(usize),
+}
+
+impl<S: Storage> GuardianState<S> {
+    /// the chain of agreements giving `contract` the right to share `node`, see [`crate::delegation`]
+    ///
+    /// agreements by the original owner of `node` are a chain of their own.
+    pub fn delegation(
+        &self,
+        node: &StateNode,
+        contract: (Hash, Arc<ContractNode>),
+    ) -> Result<Delegation, DelegationError> {
+        let now = chrono::Utc::now().naive_utc();
+        self.delegation_from(node, contract, 0, now)
+            .map(|links| Delegation { links })
+    }
+
+    fn delegation_from(
+        &self,
+        node: &StateNode,
+        contract: (Hash, Arc<ContractNode>),
+        depth: usize,
+        now: chrono::NaiveDateTime,
+    ) -> Result<Vec<(Hash, Arc<ContractNode>)>, DelegationError> {
+        let ContractEffect::AccessAgreement((aa, _)) = &contract.1.effect else {
+            return Ok(vec![contract]);
+        };
+        let received = self.received_by(node, aa.sender, now);
+        if received.is_empty() {
+            return Ok(vec![contract]);
+        }
+        // also ends cycles of agreements sharing the revision back and forth
+        if depth >= self.max_delegation_depth {
+            return Err(DelegationError::TooDeep(self.max_delegation_depth));
+        }
+        let mut error = DelegationError::ReshareNotAllowed;
+        for upstream in received {
+            let may_reshare = matches!(
+                &upstream.1.effect,
+                ContractEffect::AccessAgreement((aa, _)) if aa.may_reshare
+            );
+            if !may_reshare {
+                continue;
+            }
+            match self.delegation_from(node, upstream, depth + 1, now) {
+                Ok(mut links) => {
+                    links.insert(0, contract);
+                    return Ok(links);
+                }
+                Err(e) => error = e,
+            }
+        }
+        Err(error)
+    }
+
+    /// the effective agreements sharing `node` to `receiver`
+    fn received_by(
+        &self,
+        node: &StateNode,
+        receiver: Address,
+        now: chrono::NaiveDateTime,
+    ) -> Vec<(Hash, Arc<ContractNode>)> {
+        // as a member of a group as well
+        let principals = self.principals(receiver);
+        let mut received: Vec<(Hash, Arc<ContractNode>)> = principals
+            .iter()
+            .flat_map(|principal| self.shared_with(node, principal))
+            .filter(|(_hash, contract)| {
+                use AccessAgreementEffects::*;
+                matches!(
+                    &contract.effect,
+                    ContractEffect::AccessAgreement((aa, Granted | Accepted))
+                        if principals.contains(&aa.receiver) && aa.is_valid_at(now)
+                )
+            })
+            .collect();
+        // the same chain every time
+        received.sort_by_key(|(hash, _contract)| *hash);
+        received.dedup_by_key(|(hash, _contract)| *hash);
+        received
+    }
+}
@@ -0,0 +1,338 @@
+//! Explains why a user can or cannot access a revision, for when a partner cannot see a page.
+//!
+//! The verdict is the one [`get_rev_access`](GuardianState::get_rev_access) comes to, the explanation lists the
+//! access agreements involved and every condition that failed on the way.
+
+use std::{
+    collections::{BTreeMap, HashSet},
+    sync::Arc,
+};
+
+use contract_interpreter::{AccessAgreementEffects, ContractEffect, Principal};
+use guardian_common::{prelude::*, storage::Storage};
+
+use crate::{
+    delegation::DelegationError, ContractNode, GuardianState, IterDownTree, StateNode, POISONED,
+};
+
+/// a condition that keeps a user from accessing a revision
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+#[serde(tag = "reason", rename_all = "snake_case")]
+pub enum Denial {
+    /// the revision is not in the state
+    RevisionUnknown,
+    /// no access agreement with the user involves the revision
+    NoAgreement,
+    /// the contract is no access agreement
+    NotAnAgreement,
+    /// the agreement has terms the receiver did not sign yet, only the agreement itself is shared
+    OnlyOffered,
+    /// the agreement is about the revision, but does not share it to the user
+    NotShared,
+    /// the agreement was sent by someone else than the owner this guardian shares for
+    SenderNotOwner {
+        sender: Address,
+    },
+    NotYetValid {
+        valid_from: chrono::NaiveDateTime,
+    },
+    Expired {
+        valid_until: chrono::NaiveDateTime,
+    },
+    /// the sender withdrew the agreement
+    Revoked,
+    /// the sender received the revision by no agreement allowing to share it onward, see [`crate::delegation`]
+    ReshareNotAllowed,
+    /// the revision was shared onward more often than [`max_delegation_depth`](GuardianState::max_delegation_depth)
+    DelegationTooDeep {
+        max_depth: usize,
+    },
+    /// the chain forked and the owner did not pick the branch of the revision, see [`crate::fork`]
+    Forked {
+        fork: Hash,
+    },
+    /// no guardian serves the user, so there is no one to hand the revision to
+    ServitudeMissing,
+    /// the guardian claims to serve itself and therefore serves no one
+    ServitudePoisoned {
+        guardian: Address,
+    },
+    /// the user accepted servitudes of several guardians, so none of them serves the user
+    ServitudeContested {
+        guardians: Vec<Address>,
+    },
+    /// the guardian has no certificate to connect with
+    CertificateMissing {
+        guardian: Address,
+    },
+    /// the certificate of the guardian is claimed by another guardian as well
+    CertificatePoisoned {
+        guardian: Address,
+    },
+}
+
+/// an access agreement involving the revision and the user
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+pub struct ContractExplanation {
+    /// the hash of the revision that made the contract effective
+    pub contract: Hash,
+    pub sender: Option<Address>,
+    pub receiver: Option<Principal>,
+    pub effect: Option<AccessAgreementEffects>,
+    /// whether the sender is the owner this guardian shares for
+    pub sender_is_owner: bool,
+    /// whether the contract is in the [`shared`](StateNode::shared) of the revision for the user
+    pub shared: bool,
+    /// why this contract gives no access, `None` if it does
+    pub denial: Option<Denial>,
+}
+
+/// a guardian that accepted to serve the user
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+pub struct GuardianExplanation {
+    pub guardian: Address,
+    /// why the guardian cannot fetch from us, `None` if it can
+    pub denial: Option<Denial>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+pub struct AccessExplanation {
+    pub user: Address,
+    pub hash: Hash,
+    pub owner: Address,
+    /// the contract access is granted by, `None` if it is denied
+    pub granted_by: Option<Hash>,
+    pub contracts: Vec<ContractExplanation>,
+    pub guardians: Vec<GuardianExplanation>,
+    /// every condition that failed, empty if the user's guardian can fetch the revision
+    pub denials: Vec<Denial>,
+}
+
+impl AccessExplanation {
+    pub fn is_granted(&self) -> bool {
+        self.granted_by.is_some()
+    }
+}
+
+/// all revisions before and after `node`, including itself
+fn tree_of(node: &Arc<StateNode>) -> HashSet<Hash> {
+    let mut tree: HashSet<Hash> = IterDownTree::from(Arc::downgrade(node))
+        .topological()
+        .map(|ancestor| ancestor.hash)
+        .collect();
+    let mut childstack = vec![node.clone()];
+    while let Some(decendent) = childstack.pop() {
+        tree.insert(decendent.hash);
+        childstack.extend(decendent.leafs.iter().map(|leaf| leaf.value().clone()));
+    }
+    tree
+}
+
+impl<S: Storage> GuardianState<S> {
+    /// explains whether `user` can access the revision `hash` shared by `owner`, and why
+    pub fn explain_access(&self, user: Address, hash: Hash, owner: Address) -> AccessExplanation {
+        let guardians = self.explain_guardians(user);
+        let mut explanation = AccessExplanation {
+            user,
+            hash,
+            owner,
+            granted_by: None,
+            contracts: vec![],
+            denials: vec![],
+            guardians,
+        };
+
+        match self.get_node(&hash) {
+            Some(node) => {
+                explanation.granted_by = self
+                    .get_rev_access(user, hash, owner)
+                    .map(|(_node, contract_hash)| contract_hash);
+                explanation.contracts = self.explain_contracts(&node, user, owner);
+                if explanation.granted_by.is_none() {
+                    if let Some(fork) = self.withholding_fork(&node, owner) {
+                        explanation
+                            .denials
+                            .push(Denial::Forked { fork: fork.point });
+                    }
+                    if explanation.contracts.is_empty() {
+                        explanation.denials.push(Denial::NoAgreement);
+                    }
+                    for denial in explanation
+                        .contracts
+                        .iter()
+                        .filter_map(|c| c.denial.clone())
+                    {
+                        if !explanation.denials.contains(&denial) {
+                            explanation.denials.push(denial);
+                        }
+                    }
+                }
+            }
+            None => explanation.denials.push(Denial::RevisionUnknown),
+        }
+
+        if explanation.guardians.is_empty() {
+            explanation.denials.push(Denial::ServitudeMissing);
+        } else if explanation.guardians.iter().all(|g| g.denial.is_some()) {
+            explanation.denials.extend(
+                explanation
+                    .guardians
+                    .iter()
+                    .filter_map(|g| g.denial.clone()),
+            );
+        }
+        explanation
+    }
+
+    /// the agreements shared to the user on `node`, and the ones with the user about revisions of its tree
+    fn explain_contracts(
+        &self,
+        node: &Arc<StateNode>,
+        user: Address,
+        owner: Address,
+    ) -> Vec<ContractExplanation> {
+        let principals = self.principals(user);
+        let shared: BTreeMap<Hash, Arc<ContractNode>> = principals
+            .iter()
+            .flat_map(|principal| self.shared_with(node, principal))
+            .collect();
+
+        let tree = tree_of(node);
+        let page = self.page_of(node);
+        let mut involved = shared.clone();
+        for (contract_hash, contract_node) in self.contracts.read().iter() {
+            let ContractEffect::AccessAgreement((aa, _)) = &contract_node.effect else {
+                continue;
+            };
+            let with_user = principals.contains(&aa.receiver) || aa.sender == user;
+            let about_tree = tree.contains(contract_hash)
+                || aa.pages.iter().any(|(_name, page)| tree.contains(page))
+                || page
+                    .as_ref()
+                    .zip(aa.page_pattern.as_ref())
+                    .is_some_and(|(page, pattern)| page.matches(pattern));
+            if with_user && about_tree {
+                involved.insert(*contract_hash, contract_node);
+            }
+        }
+
+        let now = chrono::Utc::now().naive_utc();
+        involved
+            .into_iter()
+            .map(|(contract_hash, contract_node)| {
+                let is_shared = shared.contains_key(&contract_hash);
+                let ContractEffect::AccessAgreement((aa, effect)) = &contract_node.effect else {
+                    return ContractExplanation {
+                        contract: contract_hash,
+                        sender: None,
+                        receiver: None,
+                        effect: None,
+                        sender_is_owner: false,
+                        shared: is_shared,
+                        denial: Some(Denial::NotAnAgreement),
+                    };
+                };
+
+                use AccessAgreementEffects::*;
+                let denial = if self.is_revoked(contract_hash, aa.sender) {
+                    Some(Denial::Revoked)
+                } else if let Some(valid_from) = aa.valid_from.filter(|from| now < *from) {
+                    Some(Denial::NotYetValid { valid_from })
+                } else if let Some(valid_until) = aa.valid_until.filter(|_| aa.is_expired_at(now)) {
+                    Some(Denial::Expired { valid_until })
+                } else if !is_shared {
+                    match effect {
+                        Offered => Some(Denial::OnlyOffered),
+                        _ => Some(Denial::NotShared),
+                    }
+                } else {
+                    // the same as get_rev_access
+                    match effect {
+                        Granted | Accepted if aa.sender == owner => self
+                            .delegation(node, (contract_hash, contract_node.clone()))
+                            .err()
+                            .map(|e| match e {
+                                DelegationError::ReshareNotAllowed => Denial::ReshareNotAllowed,
+                                DelegationError::TooDeep(max_depth) => {
+                                    Denial::DelegationTooDeep { max_depth }
+                                }
+                            }),
+                        Offered if aa.sender == owner => None,
+                        Accepted if aa.receiver == owner => None,
+                        _ => Some(Denial::SenderNotOwner { sender: aa.sender }),
+                    }
+                };
+
+                ContractExplanation {
+                    contract: contract_hash,
+                    sender: Some(aa.sender),
+                    receiver: Some(aa.receiver),
+                    effect: Some(*effect),
+                    sender_is_owner: aa.sender == owner,
+                    shared: is_shared,
+                    denial,
+                }
+            })
+            .collect()
+    }
+
+    /// the guardians with an accepted servitude for `user`, and whether they can connect
+    fn explain_guardians(&self, user: Address) -> Vec<GuardianExplanation> {
+        use contract_interpreter::{GuardianServitudeEffects, TlsIdentityClaimEffects};
+
+        let contracts: Vec<Arc<ContractNode>> =
+            self.contracts.read().iter().map(|(_h, c)| c).collect();
+        let mut guardians: Vec<Address> = contracts
+            .iter()
+            .filter_map(|contract| match &contract.effect {
+                ContractEffect::GuardianServitude((gs, GuardianServitudeEffects::Accepted))
+                    if gs.user == user =>
+                {
+                    Some(gs.guardian)
+                }
+                _ => None,
+            })
+            .collect();
+        guardians.sort();
+        guardians.dedup();
+
+        guardians
+            .into_iter()
+            .map(|guardian| {
+                let contested = self.user_guardians(user);
+                let denial = if self.is_guardian_poisoned(guardian) {
+                    Some(Denial::ServitudePoisoned { guardian })
+                } else if contested.len() > 1 {
+                    Some(Denial::ServitudeContested {
+                        guardians: contested,
+                    })
+                } else if !self.guardian_users(guardian).contains(&user) {
+                    // outdated, the servitude is gone
+                    Some(Denial::ServitudeMissing)
+                } else {
+                    let identities = self.guardian_identities.read();
+                    let claimed: Vec<Option<Address>> = contracts
+                        .iter()
+                        .filter_map(|contract| match &contract.effect {
+                            ContractEffect::TlsIdentityClaim((
+                                tic,
+                                TlsIdentityClaimEffects::IdentityClaimed,
+                            )) if tic.guardian == guardian => {
+                                Some(identities.get(&tic.cert[..]).map(|(addr, _url)| *addr))
+                            }
+                            _ => None,
+                        })
+                        .collect();
+                    if claimed.contains(&Some(guardian)) {
+                        None
+                    } else if claimed.contains(&Some(POISONED)) {
+                        Some(Denial::CertificatePoisoned { guardian })
+                    } else {
+                        Some(Denial::CertificateMissing { guardian })
+                    }
+                };
+                GuardianExplanation { guardian, denial }
+            })
+            .collect()
+    }
+}
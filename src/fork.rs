@@ -0,0 +1,128 @@
+//! Forks of Aqua-Chains, a revision whose children lead to more than one live tip.
+//!
+//! Forks are not prevented, [`StateNode::leafs`] can hold any number of children. They are found when asked for, so
+//! merges joining the branches again and removed revisions are always accounted for. With
+//! [`refuse_forks`](GuardianState::refuse_forks) set, a forked chain is not shared until the owner picks a tip with a
+//! [`ForkResolution`], and after that only the picked branch is.
+
+use std::{collections::HashSet, sync::Arc};
+
+use contract_interpreter::ForkResolution;
+use guardian_common::{prelude::*, storage::Storage};
+
+use crate::{GuardianState, IterDownTree, StateNode};
+
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+pub struct Fork {
+    /// the genesis of the forked chain
+    pub genesis: Hash,
+    /// the revision the chain forked at
+    pub point: Hash,
+    /// the live tips after the fork point, sorted
+    pub tips: Vec<Hash>,
+    /// the latest effective resolution of the fork, no matter who signed it
+    pub resolution: Option<ForkResolution>,
+}
+
+impl Fork {
+    /// the tip picked by `owner`, `None` while they have not resolved the fork
+    pub fn picked_by(&self, owner: Address) -> Option<Hash> {
+        self.resolution
+            .as_ref()
+            .filter(|resolution| resolution.sender == owner)
+            .map(|resolution| resolution.tip)
+    }
+}
+
+/// `node` and all revisions after it, each once
+fn descendants(node: &Arc<StateNode>) -> Vec<Arc<StateNode>> {
+    let mut visited = HashSet::new();
+    let mut found = vec![];
+    let mut stack = vec![node.clone()];
+    while let Some(decendent) = stack.pop() {
+        if visited.insert(decendent.hash) {
+            stack.extend(decendent.leafs.iter().map(|leaf| leaf.value().clone()));
+            found.push(decendent);
+        }
+    }
+    found
+}
+
+impl<S: Storage> GuardianState<S> {
+    /// all forks of all chains
+    pub fn forks(&self) -> Vec<Fork> {
+        let genesi: Vec<Hash> = self
+            .genesis_map
+            .iter()
+            .map(|genesis| *genesis.key())
+            .collect();
+        genesi
+            .into_iter()
+            .flat_map(|genesis| self.chain_forks(genesis))
+            .collect()
+    }
+
+    /// the forks of the chain starting at `genesis`
+    pub fn chain_forks(&self, genesis: Hash) -> Vec<Fork> {
+        let Some(genesis_node) = self.genesis_map.get(&genesis).map(|g| g.value().clone()) else {
+            return vec![];
+        };
+        let mut forks = vec![];
+        for node in descendants(&genesis_node) {
+            if node.leafs.len() < 2 {
+                continue;
+            }
+            let mut tips: Vec<Hash> = descendants(&node)
+                .into_iter()
+                .filter(|decendent| decendent.leafs.is_empty())
+                .map(|tip| tip.hash)
+                .collect();
+            // branches merged again are no fork
+            if tips.len() < 2 {
+                continue;
+            }
+            tips.sort();
+            let resolution = self.fork_resolutions.get(&node.hash).and_then(|r| {
+                let (resolution, contract) = r.value();
+                contract.upgrade().map(|_| resolution.clone())
+            });
+            forks.push(Fork {
+                genesis,
+                point: node.hash,
+                tips,
+                resolution,
+            });
+        }
+        forks.sort_by_key(|fork| fork.point);
+        forks
+    }
+
+    /// the fork keeping `node` from being shared by `owner`, if [`refuse_forks`](Self::refuse_forks) is set
+    ///
+    /// an unresolved fork withholds its whole chain, a resolved one only the branches that were not picked.
+    pub fn withholding_fork(&self, node: &Arc<StateNode>, owner: Address) -> Option<Fork> {
+        if !self.refuse_forks {
+            return None;
+        }
+        let genesis = IterDownTree::from(Arc::downgrade(node)).last()?;
+        self.chain_forks(genesis.hash).into_iter().find(|fork| {
+            let Some(tip) = fork.picked_by(owner) else {
+                return true;
+            };
+            let Some(point) = self.get_node(&fork.point) else {
+                return false;
+            };
+            let after_point =
+                node.hash != point.hash && descendants(&point).iter().any(|d| d.hash == node.hash);
+            let Some(tip) = self.get_node(&tip) else {
+                // the picked tip is gone, nothing after the fork is picked
+                return after_point;
+            };
+            let on_picked_branch = IterDownTree::from(Arc::downgrade(&tip))
+                .topological()
+                .chain(descendants(&tip))
+                .any(|picked| picked.hash == node.hash);
+            after_point && !on_picked_branch
+        })
+    }
+}
@@ -0,0 +1,60 @@
+//! Membership of [`Group`](contract_interpreter::Group)s, resolved whenever access is checked.
+//!
+//! Agreements with a group are shared to its genesis hash as [`Principal::Group`], users reach them through the
+//! groups they are currently a member of. Changing the members therefore changes access without touching any
+//! agreement.
+
+use std::sync::Arc;
+
+use contract_interpreter::{ContractEffect, Principal};
+use guardian_common::{prelude::*, storage::Storage};
+
+use crate::{GuardianState, IterDownTree};
+
+impl<S: Storage> GuardianState<S> {
+    /// the current members of the group with the genesis hash `group`, empty if it is not formed
+    ///
+    /// the latest revision signed by the owner counts, on forked group chains the one furthest from the genesis.
+    pub fn group_members(&self, group: Hash) -> Vec<Address> {
+        let Some(revisions) = self.groups.get(&group) else {
+            return vec![];
+        };
+        let latest = revisions
+            .read()
+            .iter()
+            .filter_map(|(hash, contract)| {
+                let node = self.get_node(hash)?;
+                let depth = IterDownTree::from(Arc::downgrade(&node)).count();
+                Some(((depth, *hash), contract))
+            })
+            .max_by_key(|(key, _contract)| *key);
+        let Some((_, contract)) = latest else {
+            return vec![];
+        };
+        let ContractEffect::Group((group, _)) = &contract.effect else {
+            return vec![];
+        };
+        let mut members = group.members.clone();
+        members.sort();
+        members.dedup();
+        members
+    }
+
+    /// the genesis hashes of the groups `user` currently is a member of, sorted
+    pub fn groups_of(&self, user: Address) -> Vec<Hash> {
+        let groups: Vec<Hash> = self.groups.iter().map(|group| *group.key()).collect();
+        let mut groups: Vec<Hash> = groups
+            .into_iter()
+            .filter(|group| self.group_members(*group).contains(&user))
+            .collect();
+        groups.sort();
+        groups
+    }
+
+    /// `user` and the groups they are a member of, everything shared to one of them is shared to `user`
+    pub fn principals(&self, user: Address) -> Vec<Principal> {
+        std::iter::once(Principal::User(user))
+            .chain(self.groups_of(user).into_iter().map(Principal::Group))
+            .collect()
+    }
+}
@@ -0,0 +1,173 @@
+//! Answering the requests of remote guardians.
+//!
+//! A remote guardian is known by the certificate it connects with, it gets what the users it serves have access to.
+//! Access is evaluated against the agreements of every owner this guardian serves, the admin and the
+//! `SERVED_USERS`, and every decision is recorded in the [`AuditLog`].
+
+use std::{
+    collections::{HashMap, HashSet},
+    fmt::Debug,
+    sync::Arc,
+};
+
+use guardian_api::ApiHandler;
+use guardian_common::{prelude::*, storage::Storage};
+use webpki::types::CertificateDer;
+
+use crate::{
+    audit::{AuditLog, AuditOutcome, AuditRecord, AuditRequest},
+    Error, GuardianState,
+};
+
+/// the [`ApiHandler`] for a single connection of a remote guardian
+#[derive(Clone)]
+pub struct Handler<S> {
+    pub state: Arc<GuardianState<S>>,
+    /// the certificate the remote guardian connected with
+    pub cert: CertificateDer<'static>,
+    /// the users whose pages this guardian serves, the admin first
+    pub owners: Vec<Address>,
+    pub audit: Arc<AuditLog>,
+}
+impl<S: Storage> Handler<S> {
+    /// the users served by the guardian on the other end, access is evaluated for each of them
+    fn get_users(&self) -> Result<Vec<Address>, Error<S>> {
+        let Some((guardian_addr, _)) = self
+            .state
+            .guardian_identities
+            .read()
+            .get(&self.cert)
+            .cloned()
+        else {
+            return Err(Error::Denied);
+        };
+        let users = self.state.guardian_users(guardian_addr);
+        if users.is_empty() {
+            return Err(Error::Denied);
+        }
+        Ok(users)
+    }
+    /// records the decision, nothing is handed out that did not make it into the audit log
+    fn audit(
+        &self,
+        user: Option<Address>,
+        request: AuditRequest,
+        outcome: AuditOutcome,
+        contracts: Vec<Hash>,
+    ) -> Result<(), Error<S>> {
+        let record = AuditRecord {
+            peer_cert: self.cert.to_vec(),
+            user,
+            request,
+            outcome,
+            contracts,
+        };
+        self.audit.record(record).map(|_entry| ()).map_err(|e| {
+            eprintln!("failed to write audit log, denying: {e}");
+            Error::Denied
+        })
+    }
+    /// [`get_users`](Self::get_users), recording the denial if the peer is unknown
+    fn get_users_audited(&self, request: AuditRequest) -> Result<Vec<Address>, Error<S>> {
+        let users = self.get_users();
+        if users.is_err() {
+            self.audit(None, request, AuditOutcome::Denied, vec![])?;
+        }
+        users
+    }
+    /// the first of the served users with access to the revision `hash` and the owner who granted it, every
+    /// decision is recorded
+    fn get_rev_access_audited(
+        &self,
+        request: AuditRequest,
+        hash: Hash,
+    ) -> Result<(Address, Address), Error<S>> {
+        for user in self.get_users_audited(request)? {
+            // the whole delegation is recorded, as proof the revision could be shared onward
+            let access = self.owners.iter().find_map(|&owner| {
+                self.state
+                    .get_rev_delegation(user, hash, owner)
+                    .map(|(_, delegation)| (owner, delegation.contracts()))
+            });
+            let owner = access.as_ref().map(|(owner, _)| *owner);
+            match self.audit_access(user, request, access.map(|(_, contracts)| contracts)) {
+                Ok(()) => return Ok((user, owner.expect("access was allowed"))),
+                Err(Error::Denied) => continue,
+                Err(e) => return Err(e),
+            }
+        }
+        Err(Error::Denied)
+    }
+    /// records whether `user` gets `request` and by which contracts
+    fn audit_access(
+        &self,
+        user: Address,
+        request: AuditRequest,
+        contracts: Option<Vec<Hash>>,
+    ) -> Result<(), Error<S>> {
+        let outcome = match contracts {
+            Some(_) => AuditOutcome::Allowed,
+            None => AuditOutcome::Denied,
+        };
+        let allowed = contracts.is_some();
+        self.audit(Some(user), request, outcome, contracts.unwrap_or_default())?;
+        allowed.then_some(()).ok_or(Error::Denied)
+    }
+}
+impl<S: Storage + Debug + Send + Sync> ApiHandler for Handler<S> {
+    type Error = Error<S>;
+    type Context = S::Context;
+
+    ///lists all hashes avalilabe for remote user
+    async fn list(&self) -> Result<HashSet<Hash>, Self::Error> {
+        let mut latests = HashSet::new();
+        for user in self.get_users_audited(AuditRequest::List)? {
+            let mut by_contract: HashMap<Hash, HashSet<Hash>> = HashMap::new();
+            for &owner in &self.owners {
+                for (contract, latests) in
+                    self.state.get_accessible_latests_by_contract(user, owner)
+                {
+                    by_contract.entry(contract).or_default().extend(latests);
+                }
+            }
+            self.audit(
+                Some(user),
+                AuditRequest::List,
+                AuditOutcome::Allowed,
+                by_contract.keys().copied().collect(),
+            )?;
+            latests.extend(by_contract.into_values().flatten());
+        }
+        Ok(latests)
+    }
+    /// returns hashes of a branch if the branch is available to the remote user
+    async fn get_branch(&self, hash: Hash) -> Result<Branch<Self::Context>, Self::Error> {
+        let (user, owner) = self.get_rev_access_audited(AuditRequest::GetBranch(hash), hash)?;
+        let Some(hashes) = self.state.get_accessible_branch(user, hash, owner) else {
+            return Err(Self::Error::Denied);
+        };
+        let context = self
+            .state
+            .storage
+            .get_context(hash)
+            .await
+            .map_err(Error::Storage)?;
+        let branch = Branch {
+            metadata: context,
+            hashes,
+        };
+        Ok(branch)
+    }
+    /// returns revision if it is available to the remote user
+    async fn get_revision(&self, hash: Hash) -> Result<Revision, Self::Error> {
+        let _access = self.get_rev_access_audited(AuditRequest::GetRevision(hash), hash)?;
+        eprintln!("Debug read: get_revision");
+        let rev = self
+            .state
+            .storage
+            .read(hash)
+            .await
+            .map_err(Error::Storage)?;
+        Ok(rev)
+    }
+}
@@ -19,11 +19,28 @@
 //! - [`ethereum-lookup`][`node-eth-lookup`] for looking up witness hashes and block time
 //! - [`guardian-api`][`guardian_api`] for interaction between guardians
 //!
-pub mod contract_generation;
+pub mod access_index;
+pub mod audit;
+pub mod bootstrap;
 pub mod certificate_generation;
+pub mod changes;
+pub mod contract_generation;
+pub mod delegation;
+pub mod explain;
+pub mod fork;
+pub mod group;
+pub mod handler;
+pub mod page_pattern;
+pub mod snapshot;
+pub mod templates;
 
-use contract_interpreter::{Contract, ContractEffect, SequencedContract};
-use guardian_common::{prelude::*, storage::Storage};
+use contract_interpreter::{
+    Contract, ContractDiagnostic, ContractEffect, Principal, SequencedContract,
+};
+use guardian_common::{
+    prelude::*,
+    storage::{PageContext, Storage},
+};
 use parking_lot::RwLock;
 use std::{
     fmt::Debug,
@@ -97,12 +114,27 @@ pub struct StateNode {
     pub hash: Hash,
     // prev: Option<Hash>,
     pub prev: Weak<StateNode>,
+    /// the second parent of a merge revision, empty for all others
+    pub merge: Weak<StateNode>,
     /// marks that this was detected as a contract
     pub contract: Option<ContractInfo>,
-    /// this thing is shared to \<address\> by \<contracts\>
-    pub shared: dashmap::DashMap<Address, RwWeaakMap<Hash, ContractNode>>,
+    /// the genesis hashes of the chains this continues, its own first. what they share is kept in [`access_index`]
+    pub chains: Vec<Hash>,
+    /// the longest distance to a genesis, following merges
+    pub height: usize,
     /// this thing has child revisions
     pub leafs: dashmap::DashMap<Hash, Arc<StateNode>>,
+    /// the page of the chain, only kept on genesis revisions. see [`page_pattern`]
+    pub page: RwLock<Option<page_pattern::Page>>,
+}
+
+impl StateNode {
+    /// the previous revision and, for merges, the merged one
+    pub fn parents(&self) -> impl Iterator<Item = Arc<StateNode>> {
+        [self.prev.upgrade(), self.merge.upgrade()]
+            .into_iter()
+            .flatten()
+    }
 }
 
 impl contract_interpreter::ContractInfo for StateNode {
@@ -117,7 +149,8 @@ impl contract_interpreter::ContractInfo for StateNode {
 #[derive(Debug)]
 pub struct ContractNode {
     pub effect: contract_interpreter::ContractEffect,
-    pub latests: RwWeaakMap<Hash, StateNode>,
+    /// the genesis hashes of the chains it shares revisions of, see [`access_index`]
+    pub chains: RwLock<std::collections::HashSet<Hash>>,
 }
 
 #[derive(Debug)]
@@ -132,17 +165,39 @@ pub struct GuardianState<Storage> {
     /// mapping finished contract's revision hashes to data about them ([`ContractNode`])
     pub contracts: RwWeaakMap<Hash, ContractNode>,
     /// mapping revision hashes of shared revisions to mappings of user+contract_hash to the [`ContractNode`]
-    pub shared_revs: dashmap::DashMap<Hash, RwWeaakMap<(Address, Hash), ContractNode>>,
-    /// maps guardians to the users they serve
+    pub shared_revs: dashmap::DashMap<Hash, RwWeaakMap<(Principal, Hash), ContractNode>>,
+    /// maps genesis hashes to what is shared of their chains, see [`access_index`]
+    pub access_index: dashmap::DashMap<Hash, access_index::ChainIndex>,
+    /// maps guardians to their accepted servitudes by the hash of the revision accepting them
     ///
-    /// when a guardian tries to serve multiple users, its served user is [`POISONED`].
-    /// valid only as long as the weak ref exists, must be checked on access
-    pub guardian_servitude: dashmap::DashMap<Address, (Address, Weak<ContractNode>)>,
+    /// entries are dropped with the servitude they stem from, a user stays served as long as one servitude is left.
+    /// see [`guardian_users`](Self::guardian_users) for how conflicting servitudes are resolved.
+    pub guardian_servitude: dashmap::DashMap<Address, RwWeaakMap<Hash, ContractNode>>,
     /// mapping certificates to the owning guardian
     ///
     /// the bytes which are used as a key are CertificateDer bytes.
     pub guardian_identities: RwLock<weak_table::WeakKeyHashMap<Weak<[u8]>, (Address, url::Url)>>,
-    pub user_lookup: dashmap::DashMap<Address, RwWeaakMap<Hash, ContractNode>>,
+    /// maps users and groups to the agreements with them, members reach a group's through [`principals`](Self::principals)
+    pub user_lookup: dashmap::DashMap<Principal, RwWeaakMap<Hash, ContractNode>>,
+    /// maps genesis hashes of groups to their revisions signed by the owner, see [`group`]
+    pub groups: dashmap::DashMap<Hash, RwWeaakMap<Hash, ContractNode>>,
+    /// the template versions revisions are recognized as contracts by, see [`templates`]
+    pub templates: RwLock<contract_interpreter::TemplateRegistry>,
+    /// the granted and accepted agreements sharing by a [`PagePattern`](contract_interpreter::PagePattern)
+    pub page_patterns: RwWeaakMap<Hash, ContractNode>,
+    /// maps genesis hashes of revoked access agreements to the sender who revoked them
+    ///
+    /// valid only as long as the weak ref exists, must be checked on access
+    pub revocations: dashmap::DashMap<Hash, (Address, Weak<ContractNode>)>,
+    /// maps fork points to the latest resolution picking one of their tips
+    ///
+    /// valid only as long as the weak ref exists, must be checked on access
+    pub fork_resolutions:
+        dashmap::DashMap<Hash, (contract_interpreter::ForkResolution, Weak<ContractNode>)>,
+    /// withhold forked chains until their owner resolves the fork, see [`fork`]
+    pub refuse_forks: bool,
+    /// how often revisions may be shared onward by their receivers, see [`delegation`]
+    pub max_delegation_depth: usize,
 }
 
 /// The address given to conflicting entries
@@ -154,12 +209,18 @@ pub enum Error<Storage: guardian_common::storage::Storage> {
     Storage(Storage::Error),
     #[error("previous not in state")]
     PrevNotInState,
+    #[error("merged revision not in state")]
+    MergeNotInState,
+    #[error("genesis revision cannot be a merge")]
+    MergeWithoutPrev,
     #[error("verify failed: {0:?}")]
     Verifier(flagset::FlagSet<verifier::RevisionIntegrity>),
     #[error("contract-interpreter: {0}")]
-    ContractInterpreter(#[from] contract_interpreter::ContractParseError),
+    ContractInterpreter(#[from] Box<contract_interpreter::InvalidContract>),
     #[error("who are you???")]
     Denied,
+    #[error("branch without revisions")]
+    EmptyBranch,
 }
 
 // mod sealed {
@@ -188,6 +249,42 @@ impl Iterator for IterDownTree {
         Some(current)
     }
 }
+impl IterDownTree {
+    /// walks all ancestors, following merges as well, every revision before its parents
+    ///
+    /// unlike iterating, which only follows the previous revisions, this yields every ancestor exactly once.
+    fn topological(self) -> std::vec::IntoIter<Arc<StateNode>> {
+        let Some(start) = self.next.upgrade() else {
+            return vec![].into_iter();
+        };
+        // count the children every ancestor has within the walk
+        let mut children: std::collections::HashMap<Hash, usize> = Default::default();
+        let mut seen = std::collections::HashSet::from([start.hash]);
+        let mut stack = vec![start.clone()];
+        while let Some(node) = stack.pop() {
+            for parent in node.parents() {
+                *children.entry(parent.hash).or_default() += 1;
+                if seen.insert(parent.hash) {
+                    stack.push(parent);
+                }
+            }
+        }
+        // a revision is ready once all of its children were walked
+        let mut order = vec![];
+        let mut ready = vec![start];
+        while let Some(node) = ready.pop() {
+            for parent in node.parents() {
+                let count = children.get_mut(&parent.hash).expect("counted above");
+                *count -= 1;
+                if *count == 0 {
+                    ready.push(parent);
+                }
+            }
+            order.push(node);
+        }
+        order.into_iter()
+    }
+}
 impl From<Weak<StateNode>> for IterDownTree {
     fn from(next: Weak<StateNode>) -> Self {
         IterDownTree { next }
@@ -202,9 +299,17 @@ impl<S> GuardianState<S> {
             state_forest: Default::default(),
             contracts: Default::default(),
             shared_revs: Default::default(),
+            access_index: Default::default(),
             guardian_identities: Default::default(),
             guardian_servitude: Default::default(),
             user_lookup: Default::default(),
+            groups: Default::default(),
+            templates: Default::default(),
+            page_patterns: Default::default(),
+            revocations: Default::default(),
+            fork_resolutions: Default::default(),
+            refuse_forks: false,
+            max_delegation_depth: delegation::DEFAULT_MAX_DELEGATION_DEPTH,
         }
     }
     pub fn get_node(&self, hash: &Hash) -> Option<Arc<StateNode>> {
@@ -216,11 +321,11 @@ impl<S> GuardianState<S> {
 }
 
 impl<S: Storage> GuardianState<S> {
-    fn add_contract_to(&self, contract: (Hash, Arc<ContractNode>), to: (Address, Hash)) {
+    fn add_contract_to(&self, contract: (Hash, Arc<ContractNode>), to: (Principal, Hash)) {
         let (contract_hash, contract_node) = contract;
         let (addr, page_hash) = to;
         // insert into shared_revs
-         eprintln!("Debug write: add contract to shared_revs");
+        eprintln!("Debug write: add contract to shared_revs");
         self.shared_revs
             .entry(page_hash)
             .or_default()
@@ -228,45 +333,16 @@ impl<S: Storage> GuardianState<S> {
             .insert((addr, contract_hash), contract_node.clone());
 
         if let Some(node) = self.get_node(&page_hash) {
-            // add down to root,
-            let mut prev = node.prev.clone();
-            while let Some(aprev) = prev.upgrade() {
-                 eprintln!("Debug write: certverifier change");
-                aprev
-                    .shared
-                    .entry(addr)
-                    .or_default()
-                    .write()
-                    .insert(contract_hash, contract_node.clone());
-                prev = aprev.prev.clone();
-            }
-            // add to self,
-            // add to all children
-            let mut childstack: Vec<_> = vec![node];
-            while let Some(decendent) = childstack.pop() {
-                eprintln!("Debug write: add contract to decendent.shared");
-                decendent
-                    .shared
-                    .entry(addr)
-                    .or_default()
-                    .write()
-                    .insert(contract_hash, contract_node.clone());
-                if !decendent.leafs.is_empty() {
-                    childstack.extend(decendent.leafs.iter().map(|a| a.value().clone()));
-                } else {
-                     eprintln!("Debug write: add contract to contract_node.latest");
-                    // > latest children get listed under latests
-                    contract_node
-                        .latests
-                        .write()
-                        .insert(decendent.hash, decendent);
-                }
-            }
+            // everything before and after it, once for every chain it continues
+            self.index_share((contract_hash, contract_node), addr, &node);
         }
     }
 
     //for shared add pkc: pkc_api::Pkc
-    pub async fn add(&self, hash: Hash, revision: Revision) -> Result<Arc<StateNode>, Error<S>> {
+    pub async fn add(&self, hash: Hash, revision: Revision) -> Result<Arc<StateNode>, Error<S>>
+    where
+        S::Context: PageContext,
+    {
         let prev = match &revision.metadata.previous_verification_hash {
             Some(prev) => {
                 let prev_node = self.get_node(prev).ok_or(Error::PrevNotInState)?;
@@ -276,10 +352,28 @@ impl<S: Storage> GuardianState<S> {
             }
             None => None,
         };
-        // check if the revision is a genesis
-        let is_genesis = prev.is_none();
-
+        let merge = match &revision.metadata.merge_verification_hash {
+            Some(_) if prev.is_none() => return Err(Error::MergeWithoutPrev),
+            Some(merge) => {
+                let merge_node = self.get_node(merge).ok_or(Error::MergeNotInState)?;
+                let merge_ref = self.storage.read(*merge).await.map_err(Error::Storage)?;
+                Some((merge_node, merge_ref))
+            }
+            None => None,
+        };
+        // a chain is matched against page patterns by the page of its genesis
+        let page = match &prev {
+            Some(_) => None,
+            None => match self.storage.get_context(hash).await {
+                Ok(context) => Some(page_pattern::Page::of(&context)),
+                Err(e) => {
+                    eprintln!("[{hash}]: no page for the genesis: {e}");
+                    None
+                }
+            },
+        };
         let prev_v1_1 = prev.as_ref().map(|(_node, prev)| prev);
+        let merge_v1_1 = merge.as_ref().map(|(_node, merge)| merge);
         let integrity = verifier::v1_1::revision_integrity(&revision, prev_v1_1);
 
         let integrity = verifier::v1_1::ignore_absent(integrity);
@@ -294,18 +388,103 @@ impl<S: Storage> GuardianState<S> {
             return Ok(already_here);
         }
 
-        let rev_v1_2 = verifier::v1_2::rev_v1_1_to_rev_v1_2(&revision, prev_v1_1, None);
+        let rev_v1_2 = verifier::v1_2::rev_v1_1_to_rev_v1_2(&revision, prev_v1_1, merge_v1_1);
+
+        // edits of templates are recognized right away
+        let chain_page = match (&page, &prev) {
+            (Some(page), _) => Some(page.clone()),
+            (None, Some((prev_node, _))) => self.page_of(prev_node),
+            (None, None) => None,
+        };
+        if let Some(chain_page) = chain_page {
+            self.register_if_latest(&chain_page, hash).await?;
+        }
+
+        let contract = match Contract::from_revision(&rev_v1_2, &self.templates.read()) {
+            Ok(contract) => {
+                let contract_seq = contract.sequence_number(&rev_v1_2);
+                Some((contract, contract_seq))
+            }
+            Err(ContractDiagnostic::NotAContract(_)) => None,
+            Err(ContractDiagnostic::Invalid(invalid)) => {
+                return Err(Error::ContractInterpreter(invalid))
+            }
+        };
+
+        Ok(self.insert_node(
+            hash,
+            prev.map(|(prev_node, _)| prev_node),
+            merge.map(|(merge_node, _)| merge_node),
+            contract,
+            page,
+        ))
+    }
+
+    /// adds the revision `hash` from storage, and before it all of its ancestors that are not in the state yet
+    ///
+    /// returns the added nodes in the order they were added. the branch of a merged revision is not reachable by
+    /// [`get_branch`](Storage::get_branch) of the merge, so this is how to get it into the state.
+    pub async fn add_with_ancestors(&self, hash: Hash) -> Result<Vec<Arc<StateNode>>, Error<S>>
+    where
+        S::Context: PageContext,
+    {
+        let mut added = vec![];
+        let mut stack = vec![hash];
+        while let Some(&hash) = stack.last() {
+            if self.get_node(&hash).is_some() {
+                stack.pop();
+                continue;
+            }
+            let revision = self.storage.read(hash).await.map_err(Error::Storage)?;
+            let missing: Vec<Hash> = [
+                revision.metadata.previous_verification_hash,
+                revision.metadata.merge_verification_hash,
+            ]
+            .into_iter()
+            .flatten()
+            .filter(|parent| self.get_node(parent).is_none())
+            .collect();
+            if missing.is_empty() {
+                stack.pop();
+                added.push(self.add(hash, revision).await?);
+            } else if missing.iter().any(|parent| stack.contains(parent)) {
+                // a revision cannot be its own ancestor
+                return Err(Error::PrevNotInState);
+            } else {
+                stack.extend(missing);
+            }
+        }
+        Ok(added)
+    }
+
+    /// puts a verified revision into the state, `contract` being the contract it holds and its sequence number
+    ///
+    /// `merge` is only honored together with `prev`, contracts are only ever continued along `prev`. `page` is only
+    /// kept for genesis revisions.
+    ///
+    /// everything derived from the revision (effective contracts, shares, servitudes, identities) is updated here,
+    /// so restoring a [`Snapshot`](snapshot::Snapshot) ends up with the same state as adding the revisions did.
+    fn insert_node(
+        &self,
+        hash: Hash,
+        prev: Option<Arc<StateNode>>,
+        merge: Option<Arc<StateNode>>,
+        contract: Option<(Contract, Option<u8>)>,
+        page: Option<page_pattern::Page>,
+    ) -> Arc<StateNode> {
+        // check if the revision is a genesis
+        let is_genesis = prev.is_none();
 
         // create a weak reference to the previous node so that we can reference it
-        let prev_weak = prev
-            .map(|(prev_node, _)| Arc::downgrade(&prev_node))
+        let prev_weak = prev.as_ref().map(Arc::downgrade).unwrap_or_default();
+        let merge_weak = merge
+            .filter(|_| !is_genesis)
+            .as_ref()
+            .map(Arc::downgrade)
             .unwrap_or_default();
 
         // create contract info if the revision is a contract
-        let contract_info = if let Some(res) = Contract::from_revision(&rev_v1_2) {
-            let contract = res?;
-            let contract_seq = contract.sequence_number(&rev_v1_2);
-
+        let contract_info = if let Some((contract, contract_seq)) = contract {
             // create an iterator down the tree to check if the contract is effective
             let iter = {
                 let first_as_once_iter: std::iter::Once<
@@ -325,10 +504,10 @@ impl<S: Storage> GuardianState<S> {
                 // create arc contract_node so that we can reference it and put it into other data structures
                 let contract_node = Arc::new(ContractNode {
                     effect: effect.clone(),
-                    latests: RwLock::default().into(),
+                    chains: Default::default(),
                 });
                 // add it to the list of effective contracts
-                 eprintln!("Debug write: add to list of effective contracts");
+                eprintln!("Debug write: add to list of effective contracts");
                 self.contracts.write().insert(hash, contract_node.clone());
 
                 // store the only owned copy away in the info on the revision so it gets deleted when revision gets deleted
@@ -344,65 +523,73 @@ impl<S: Storage> GuardianState<S> {
             None
         };
 
-        let shared = {
-            let map: dashmap::DashMap<Address, RwWeaakMap<Hash, ContractNode>> =
-                dashmap::DashMap::default();
-            if let Some(ref prev_node) = prev_weak.upgrade() {
-                // let prev_shared = prev_node.shared.read();
-                // apply all contracts from parent node to new leaf
-                for prev_shared_map_ref in prev_node.shared.iter() {
-                    // todo! check if contracts should be applied to child as well
-                    // map.insert((*user, *contract_hash), contract);
-                    eprintln!("Debug read: New applicable contracts");
-                    let new_applicable_contracts: WeakValueHashMap<_, _> = prev_shared_map_ref
-                        .read()
-                        .iter()
-                        .filter(|(_contract_hash, _contract)| {
-                            // todo! check if contracts should be applied to child as well
-                            // for now just default to yes
-                            true
-                        })
-                        .map(|(h, c)| (*h, c))
-                        .collect();
-
-                    map.insert(
-                        *prev_shared_map_ref.key(),
-                        RwLock::new(new_applicable_contracts).into(),
-                    );
+        // the chains of both parents for merges
+        let mut chains: Vec<Hash> = vec![];
+        for parent in [prev_weak.upgrade(), merge_weak.upgrade()]
+            .into_iter()
+            .flatten()
+        {
+            for chain in &parent.chains {
+                if !chains.contains(chain) {
+                    chains.push(*chain);
                 }
-                //for
             }
-            map
-        };
+        }
+        if is_genesis {
+            chains.push(hash);
+        }
+        let height = [prev_weak.upgrade(), merge_weak.upgrade()]
+            .into_iter()
+            .flatten()
+            .map(|parent| parent.height + 1)
+            .max()
+            .unwrap_or_default();
 
         let state_node = Arc::new(StateNode {
             hash,
             prev: prev_weak.clone(),
+            merge: merge_weak.clone(),
             leafs: Default::default(),
             contract: contract_info,
-            shared,
+            chains,
+            height,
+            page: RwLock::new(page.filter(|_| is_genesis)),
         });
 
         // now we need to store the only real arc somewhere to not get deleted at end of scope
         if is_genesis {
             // store in genesis map
             self.genesis_map.insert(hash, state_node.clone());
-        } else if let Some(prev_node) = prev_weak.upgrade() {
-            // insert ourselves into the previous node's children
-            prev_node.leafs.insert(hash, state_node.clone());
+        } else {
+            // insert ourselves into the parent nodes' children
+            for prev_node in state_node.parents() {
+                prev_node.leafs.insert(hash, state_node.clone());
+                if prev_node.leafs.len() > 1 {
+                    eprintln!("[{hash}]: chain forks at {}", prev_node.hash);
+                }
+            }
         }
         // insert ourself into the state_forest so that we can be found by hash
         eprintln!("Debug write: insert into state_forest");
         self.state_forest.write().insert(hash, state_node.clone());
+        self.index_node(&state_node);
 
         // check what we ourselves are shared by (from shared_revs), aka: a contract which shares us existed before us
-        if let Some(x) = self.shared_revs.get(&hash) {
-            eprintln!("Debug read: check what we share");
-            for ((addr, contract_hash), contract_node) in x.read().iter() {
-                self.add_contract_to((*contract_hash, contract_node), (*addr, hash));
-            }
+        // collected first, adding writes to the same entry of shared_revs
+        let shared_by: Vec<((Principal, Hash), Arc<ContractNode>)> = self
+            .shared_revs
+            .get(&hash)
+            .map(|x| x.read().iter().map(|(key, node)| (*key, node)).collect())
+            .unwrap_or_default();
+        eprintln!("Debug read: check what we share");
+        for ((addr, contract_hash), contract_node) in shared_by {
+            self.add_contract_to((contract_hash, contract_node), (addr, hash));
+        }
+        // and what agreements made before us share by our page
+        if is_genesis {
+            self.share_by_patterns(&state_node);
         }
-        
+
         // find shared latests
         if let Some(ContractInfo {
             effective: Some(contract_node),
@@ -410,6 +597,11 @@ impl<S: Storage> GuardianState<S> {
         }) = &state_node.contract
         {
             match &contract_node.effect {
+                contract_interpreter::ContractEffect::AccessAgreement((aa, _))
+                    if self.is_revoked(hash, aa.sender) =>
+                {
+                    eprintln!("[{hash}]: access agreement was revoked, not sharing");
+                }
                 contract_interpreter::ContractEffect::AccessAgreement((aa, e)) => {
                     use contract_interpreter::AccessAgreementEffects::*;
                     if matches!(e, Granted | Accepted) {
@@ -419,6 +611,7 @@ impl<S: Storage> GuardianState<S> {
                                 (aa.receiver, *page),
                             );
                         }
+                        self.share_pattern((hash, contract_node.clone()));
                         eprintln!("Debug write: contract_node effect Granted/Accepted user lookup");
                         self.user_lookup
                             .entry(aa.receiver)
@@ -441,11 +634,11 @@ impl<S: Storage> GuardianState<S> {
                     if matches!(e, Accepted) {
                         self.add_contract_to(
                             (hash, contract_node.clone()),
-                            (aa.sender, state_node.hash),
+                            (aa.sender.into(), state_node.hash),
                         );
                         eprintln!("Debug write: contract_node effect Accepted user lookup");
                         self.user_lookup
-                            .entry(aa.sender)
+                            .entry(aa.sender.into())
                             .or_default()
                             .write()
                             .insert(hash, contract_node.clone());
@@ -455,21 +648,24 @@ impl<S: Storage> GuardianState<S> {
                     use contract_interpreter::GuardianServitudeEffects::*;
 
                     if matches!(e, Accepted) {
-                        self.guardian_servitude.entry(gs.guardian)
-                        .and_modify(|addr| {
-                            if *addr.0 != gs.user.0 {
-                                eprintln!("address collision! guardian {} wants to serve both {} and {}, bad. this must not happen. guardian now serves no one.", gs.guardian, gs.user, addr.0);
-                                *addr = (POISONED, Weak::default());
-                            }
-                        })
-                        .or_insert((gs.user, Arc::downgrade(contract_node)));
+                        // serving several users is fine, vouching for itself is not
+                        if gs.user == gs.guardian {
+                            eprintln!("guardian {} claims to serve itself, bad. guardian now serves no one.", gs.guardian);
+                        }
+                        self.guardian_servitude
+                            .entry(gs.guardian)
+                            .or_default()
+                            .write()
+                            .insert(hash, contract_node.clone());
                     }
                 }
                 contract_interpreter::ContractEffect::TlsIdentityClaim((tic, e)) => {
                     use contract_interpreter::TlsIdentityClaimEffects::*;
                     if matches!(e, IdentityClaimed) {
                         use weak_table::weak_key_hash_map::Entry::*;
-                        eprintln!("Debug write: contract node tls identity claim matches identites");
+                        eprintln!(
+                            "Debug write: contract node tls identity claim matches identites"
+                        );
                         match self.guardian_identities.write().entry(tic.cert.clone()) {
                             Occupied(mut o) => {
                                 let (addr, url) = o.get();
@@ -479,60 +675,118 @@ impl<S: Storage> GuardianState<S> {
                                 }
                             }
                             Vacant(v) => {
-                                if let Ok(url) = format!("https://{}:{}", tic.host, tic.port).parse() {
+                                if let Ok(url) =
+                                    format!("https://{}:{}", tic.host, tic.port).parse()
+                                {
                                     v.insert((tic.guardian, url));
                                 }
                             }
                         }
                     }
                 }
+                contract_interpreter::ContractEffect::AccessRevocation((ar, e)) => {
+                    use contract_interpreter::AccessRevocationEffects::*;
+                    if matches!(e, Revoked) {
+                        // remembered, so the agreement is not shared again when it arrives or grows later
+                        self.revocations
+                            .insert(ar.agreement, (ar.sender, Arc::downgrade(contract_node)));
+                        self.revoke(ar);
+                    }
+                }
+                contract_interpreter::ContractEffect::ForkResolution((fr, e)) => {
+                    use contract_interpreter::ForkResolutionEffects::*;
+                    if matches!(e, Resolved) {
+                        self.fork_resolutions
+                            .insert(fr.fork, (fr.clone(), Arc::downgrade(contract_node)));
+                    }
+                }
+                contract_interpreter::ContractEffect::Group((_group, e)) => {
+                    use contract_interpreter::GroupEffects::*;
+                    if matches!(e, Formed) {
+                        // members are looked up when needed, the latest signed revision of the chain counts
+                        if let Some(genesis) =
+                            IterDownTree::from(Arc::downgrade(&state_node)).last()
+                        {
+                            self.groups
+                                .entry(genesis.hash)
+                                .or_default()
+                                .write()
+                                .insert(hash, contract_node.clone());
+                        }
+                    }
+                }
             }
         };
 
-        // possibly remove previous node from contracts we are the latest in now
-        {
-            let prev_hash_to_be_removed = prev_weak
-                .upgrade()
-                .and_then(|prev_node| prev_node.leafs.is_empty().then_some(prev_node.hash));
-
-            for refmulti in state_node.shared.iter() {
-                eprintln!("Debug read: rm previous from latest");
-                for contract in refmulti.read().iter().map(|(_h, arc)| arc) {
-                    eprintln!("Debug write: remove delete latest");
-                    let mut contract_mut = contract.latests.write();
-
-                    // remove previous node from latests if we are single child
-                    if let Some(prev_hash) = prev_hash_to_be_removed {
-                        contract_mut.remove(&prev_hash);
-                    }
+        state_node
+    }
+    /// whether the access agreement the revision `hash` belongs to was revoked by its `sender`
+    fn is_revoked(&self, hash: Hash, sender: Address) -> bool {
+        let Some(genesis) = self
+            .get_node(&hash)
+            .and_then(|node| IterDownTree::from(Arc::downgrade(&node)).last())
+        else {
+            return false;
+        };
+        self.revocations
+            .get(&genesis.hash)
+            .is_some_and(|revocation| {
+                let (revoked_by, contract) = revocation.value();
+                *revoked_by == sender && contract.upgrade().is_some()
+            })
+    }
 
-                    contract_mut.insert(hash, state_node.clone());
+    /// takes back everything shared by the access agreement a revocation refers to, if it was sent by the revoking sender
+    fn revoke(&self, revocation: &contract_interpreter::AccessRevocation) {
+        let Some(genesis) = self
+            .genesis_map
+            .get(&revocation.agreement)
+            .map(|genesis| genesis.value().clone())
+        else {
+            return;
+        };
+        let mut stack = vec![genesis];
+        while let Some(node) = stack.pop() {
+            if let Some(ContractInfo {
+                effective: Some(contract_node),
+                ..
+            }) = &node.contract
+            {
+                if let ContractEffect::AccessAgreement((aa, _)) = &contract_node.effect {
+                    if aa.sender == revocation.sender {
+                        self.unshare_contract(node.hash, contract_node);
+                    }
                 }
             }
+            stack.extend(node.leafs.iter().map(|leaf| leaf.value().clone()));
+        }
+    }
+
+    /// undoes [`add_contract_to`](Self::add_contract_to) for every page the contract was added to
+    fn unshare_contract(&self, contract_hash: Hash, contract_node: &ContractNode) {
+        for shared in self.shared_revs.iter() {
+            shared
+                .write()
+                .retain(|(_addr, hash), _| *hash != contract_hash);
         }
+        self.unindex_share(contract_hash, contract_node, None);
 
-        Ok(state_node)
+        for lookup in self.user_lookup.iter() {
+            lookup.write().remove(&contract_hash);
+        }
+        self.page_patterns.write().remove(&contract_hash);
     }
+
     /// removes a node from the data store, though make sure to delete the extracted node as quickly as you can
     pub fn rm(&self, hash: Hash) -> Option<Arc<StateNode>> {
         eprintln!("Debug read: remove function");
         let state_node = self.state_forest.read().get(&hash)?;
 
         match state_node.prev.upgrade() {
-            Some(prev_node) => {
-                // remove own node from parent node
-                prev_node.leafs.remove(&hash);
-                // if we were the only leaf, make parent a latest of all its contracts
-                if prev_node.leafs.is_empty() {
-                    for refmulti in prev_node.shared.iter() {
-                        for (_hash, contract_node) in refmulti.read().iter() {
-                            eprintln!("Debug write: remove from contract_node.latest");
-                            contract_node
-                                .latests
-                                .write()
-                                .insert(prev_node.hash, prev_node.clone());
-                        }
-                    }
+            Some(_) => {
+                for prev_node in state_node.parents() {
+                    // remove own node from parent node
+                    prev_node.leafs.remove(&hash);
                 }
             }
             None => {
@@ -540,6 +794,8 @@ impl<S: Storage> GuardianState<S> {
                 self.genesis_map.remove(&hash)?;
             }
         }
+        // if we were the only leaf, the parent is a latest of its chains again
+        self.unindex_node(&state_node);
 
         Some(state_node)
     }
@@ -550,26 +806,69 @@ impl<S: Storage> GuardianState<S> {
         user: Address,
         owner: Address,
     ) -> std::collections::HashSet<Hash> {
-        let Some(applicable_contracts) = self.user_lookup.get(&user) else {
-            return Default::default();
-        };
+        let set: std::collections::HashSet<Hash> = self
+            .get_accessible_latests_by_contract(user, owner)
+            .into_values()
+            .flatten()
+            .collect();
+        eprintln!("set: {:?}", set);
+        set
+    }
 
-        let mut set = std::collections::HashSet::new();
+    /// the same as [`get_accessible_latests`](Self::get_accessible_latests), grouped by the contract that shares them
+    pub fn get_accessible_latests_by_contract(
+        &self,
+        user: Address,
+        owner: Address,
+    ) -> std::collections::HashMap<Hash, std::collections::HashSet<Hash>> {
+        let applicable_contracts: Vec<(Hash, Arc<ContractNode>)> = self
+            .principals(user)
+            .into_iter()
+            .filter_map(|principal| self.user_lookup.get(&principal))
+            .flat_map(|lookup| {
+                lookup
+                    .read()
+                    .iter()
+                    .map(|(h, c)| (*h, c))
+                    .collect::<Vec<_>>()
+            })
+            .collect();
+
+        let mut map: std::collections::HashMap<Hash, std::collections::HashSet<Hash>> =
+            Default::default();
+        let now = chrono::Utc::now().naive_utc();
 
         //eprintln!("Debug read: get accessible latest");
         //eprintln!("{:#?}", &state);
-        for (contract_hash, contract) in applicable_contracts.read().iter() {
+        for (contract_hash, contract) in &applicable_contracts {
             match &contract.effect {
+                ContractEffect::AccessAgreement((aa, _)) if !aa.is_valid_at(now) => {
+                    // outside of its time window
+                }
                 ContractEffect::AccessAgreement((aa, e)) => {
                     use contract_interpreter::AccessAgreementEffects::*;
+                    let set = map.entry(*contract_hash).or_default();
+                    let contract_withheld = || {
+                        self.get_node(contract_hash)
+                            .is_some_and(|node| self.withholding_fork(&node, owner).is_some())
+                    };
                     if matches!(e, Granted | Accepted) && aa.sender == owner {
                         eprintln!("Debug read: contract if granted or accepted");
-                        set.extend(contract.latests.read().keys().copied());
+                        set.extend(
+                            self.shared_latests(*contract_hash, contract)
+                                .iter()
+                                .filter(|node| self.withholding_fork(node, owner).is_none())
+                                .filter(|node| {
+                                    self.delegation(node, (*contract_hash, contract.clone()))
+                                        .is_ok()
+                                })
+                                .map(|node| node.hash),
+                        );
                     }
-                    if matches!(e, Offered) && aa.sender == owner {
+                    if matches!(e, Offered) && aa.sender == owner && !contract_withheld() {
                         set.insert(*contract_hash);
                     }
-                    if matches!(e, Accepted) && aa.receiver == owner {
+                    if matches!(e, Accepted) && aa.receiver == owner && !contract_withheld() {
                         set.insert(*contract_hash);
                     }
                 }
@@ -578,6 +877,15 @@ impl<S: Storage> GuardianState<S> {
                 }
                 ContractEffect::TlsIdentityClaim(_) => {
                     // nothing
+                }
+                ContractEffect::AccessRevocation(_) => {
+                    // nothing, revoked agreements are no longer in the lookup
+                }
+                ContractEffect::ForkResolution(_) => {
+                    // nothing
+                }
+                ContractEffect::Group(_) => {
+                    // nothing, groups are resolved in the lookup
                 } // _ => {
                   //     eprintln!(
                   //         "unhandled contract, skipping while trying to share to {}",
@@ -586,8 +894,8 @@ impl<S: Storage> GuardianState<S> {
                   // }
             }
         }
-        eprintln!("set: {:?}",set);
-        set
+        map.retain(|_contract_hash, set| !set.is_empty());
+        map
     }
 
     pub fn get_rev_accessible(
@@ -596,16 +904,51 @@ impl<S: Storage> GuardianState<S> {
         hash: Hash,
         owner: Address,
     ) -> Option<Arc<StateNode>> {
+        self.get_rev_access(user, hash, owner)
+            .map(|(state_node, _contract_hash)| state_node)
+    }
+
+    /// the same as [`get_rev_accessible`](Self::get_rev_accessible), with the hash of the contract that shares it
+    pub fn get_rev_access(
+        &self,
+        user: Address,
+        hash: Hash,
+        owner: Address,
+    ) -> Option<(Arc<StateNode>, Hash)> {
+        self.get_rev_delegation(user, hash, owner)
+            .map(|(state_node, delegation)| (state_node, delegation.links[0].0))
+    }
+
+    /// the same as [`get_rev_access`](Self::get_rev_access), with the whole chain of agreements that shares it
+    pub fn get_rev_delegation(
+        &self,
+        user: Address,
+        hash: Hash,
+        owner: Address,
+    ) -> Option<(Arc<StateNode>, delegation::Delegation)> {
         let state_node = self.get_node(&hash)?;
-        let applicable_contracts = state_node.shared.get(&user)?;
+        if let Some(fork) = self.withholding_fork(&state_node, owner) {
+            eprintln!("[{hash}]: withheld, chain forked at {}", fork.point);
+            return None;
+        }
+        let principals = self.principals(user);
+        let applicable_contracts: Vec<(Hash, Arc<ContractNode>)> = principals
+            .iter()
+            .flat_map(|principal| self.shared_with(&state_node, principal))
+            .collect();
+        let now = chrono::Utc::now().naive_utc();
         eprintln!("Debug read: get rev acccessible");
-        for (_contract_hash, contract_node) in applicable_contracts.read().iter() {
+        for (contract_hash, contract_node) in &applicable_contracts {
+            let mut delegation = delegation::Delegation {
+                links: vec![(*contract_hash, contract_node.clone())],
+            };
             match &contract_node.effect {
                 ContractEffect::AccessAgreement((aa, e)) => {
                     use contract_interpreter::AccessAgreementEffects::*;
                     match e {
+                        _ if !aa.is_valid_at(now) => continue,
                         Granted | Accepted if aa.sender == owner => {
-                            assert_eq!(aa.receiver, user);
+                            assert!(principals.contains(&aa.receiver));
                             // dirty
                             eprintln!("Debug read: DAA Granted / Accepted rev accessible");
                             let rdr = self.state_forest.read();
@@ -614,9 +957,20 @@ impl<S: Storage> GuardianState<S> {
                                     continue;
                                 }
                             }
+                            drop(rdr);
+                            // received revisions are only shared onward if the agreements they came by allow it
+                            match self
+                                .delegation(&state_node, (*contract_hash, contract_node.clone()))
+                            {
+                                Ok(chain) => delegation = chain,
+                                Err(e) => {
+                                    eprintln!("[{hash}]: not shared by {contract_hash}: {e}");
+                                    continue;
+                                }
+                            }
                         }
                         Offered if aa.sender == owner => {
-                            assert_eq!(aa.receiver, user);
+                            assert!(principals.contains(&aa.receiver));
                             // shares itself and previous
                         }
                         Accepted if aa.receiver == owner => {
@@ -628,8 +982,11 @@ impl<S: Storage> GuardianState<S> {
                 }
                 ContractEffect::GuardianServitude(_) => continue,
                 ContractEffect::TlsIdentityClaim(_) => continue,
+                ContractEffect::AccessRevocation(_) => continue,
+                ContractEffect::ForkResolution(_) => continue,
+                ContractEffect::Group(_) => continue,
             }
-            return Some(state_node.clone());
+            return Some((state_node.clone(), delegation));
         }
         None
     }
@@ -645,20 +1002,91 @@ impl<S: Storage> GuardianState<S> {
             IterDownTree {
                 next: Arc::downgrade(&rev),
             }
+            .topological()
             .map(|node| node.hash)
             .collect(),
         )
     }
 
-    pub fn guardian_servitude(&self, guardian: Address) -> Option<Address> {
-        self.guardian_servitude.get(&guardian).and_then(|r| {
-            let (user, contract) = r.value();
-            contract.upgrade().map(|_| *user)
-        })
+    /// drops access agreements that expired before `now` from [`user_lookup`](Self::user_lookup), returns how many
+    ///
+    /// they are not listed anyways, this only keeps the lookup from growing with every agreement that ever existed.
+    pub fn sweep_expired(&self, now: chrono::NaiveDateTime) -> usize {
+        let mut swept = 0;
+        for lookup in self.user_lookup.iter() {
+            lookup.write().retain(|_contract_hash, contract| {
+                let expired = matches!(
+                    &contract.effect,
+                    ContractEffect::AccessAgreement((aa, _)) if aa.is_expired_at(now)
+                );
+                swept += usize::from(expired);
+                !expired
+            });
+        }
+        swept
+    }
+
+    /// the users of the accepted servitudes of the guardian, sorted. a servitude of the guardian for itself is
+    /// listed as [`POISONED`]
+    fn servitude_users(&self, guardian: Address) -> Vec<Address> {
+        let Some(servitudes) = self.guardian_servitude.get(&guardian) else {
+            return vec![];
+        };
+        let mut users: Vec<Address> = servitudes
+            .read()
+            .iter()
+            .filter_map(|(_hash, contract)| match &contract.effect {
+                ContractEffect::GuardianServitude((gs, _)) if gs.user == gs.guardian => {
+                    Some(POISONED)
+                }
+                ContractEffect::GuardianServitude((gs, _)) => Some(gs.user),
+                _ => None,
+            })
+            .collect();
+        users.sort();
+        users.dedup();
+        users
+    }
+
+    /// the users the guardian serves, sorted. empty if it serves no one or is [`POISONED`]
+    ///
+    /// a user who accepted servitudes of several guardians is served by none of them until all but one servitude
+    /// are gone, as no one can tell which guardian acts for the user. poisoned guardians serve no one and do not count.
+    pub fn guardian_users(&self, guardian: Address) -> Vec<Address> {
+        let users = self.servitude_users(guardian);
+        if users.contains(&POISONED) {
+            return vec![];
+        }
+        users
+            .into_iter()
+            .filter(|user| self.user_guardians(*user) == [guardian])
+            .collect()
+    }
+
+    /// the guardians which are not poisoned and have an accepted servitude for the user, sorted
+    ///
+    /// more than one means the servitudes conflict and none of the guardians serves the user.
+    pub fn user_guardians(&self, user: Address) -> Vec<Address> {
+        let mut guardians: Vec<Address> = self
+            .guardian_servitude
+            .iter()
+            .map(|entry| *entry.key())
+            .filter(|guardian| {
+                let users = self.servitude_users(*guardian);
+                users.contains(&user) && !users.contains(&POISONED)
+            })
+            .collect();
+        guardians.sort();
+        guardians
+    }
+
+    /// whether a servitude made the guardian serve no one
+    pub fn is_guardian_poisoned(&self, guardian: Address) -> bool {
+        self.servitude_users(guardian).contains(&POISONED)
     }
 
     pub fn guardian_identity(&self, cert_bytes: &[u8]) -> Option<Address> {
         eprintln!("Debug read: Guardian Identity");
-        self.guardian_identities.read().get(cert_bytes).map(|a|a.0)
+        self.guardian_identities.read().get(cert_bytes).map(|a| a.0)
     }
 }
@@ -3,12 +3,12 @@
 use std::{fmt::Debug, io::Read, net::IpAddr, sync::Arc};
 
 use futures::StreamExt;
-use guardian::GuardianState;
+use guardian::{snapshot::Snapshot, GuardianState};
 use guardian_api::{
     server::{cert_verifier::CertVerifier, ServerInfo},
-    ApiClient, ApiHandler, ApiServer,
+    ApiClient, ApiServer,
 };
-use guardian_common::{custom_types::Hash, signing::Signer, storage::Storage};
+use guardian_common::{signing::Signer, storage::Storage};
 use pkc_api::storage::RevContext;
 use webpki::types::CertificateDer;
 
@@ -19,10 +19,6 @@ async fn main() {
         .expect("no private key")
         .parse()
         .expect("failed to parse private key");
-    let pkc_url: url::Url = std::env::var("PKC_URL")
-        .expect("no pkc url")
-        .parse()
-        .expect("not a valid url");
     let admin_user: ethaddr::Address = std::env::var("ADMIN_USER")
         .expect("no admin user")
         .parse()
@@ -42,24 +38,23 @@ async fn main() {
         // Try open the PEM file
         // make new one if not found
         match std::fs::File::open("identity.pem") {
-            Ok(_file) => { 
-            }
+            Ok(_file) => {}
             _ => {
-                    println!("failed to open identity.pem. would you like to generate it? [y/n]");
-                    let mut ans = String::new();
-                    std::io::stdin()
-                        .read_line(&mut ans)
-                        .expect("Failed to read line");
-                    match ans.trim().to_lowercase().starts_with('y') {
-                        true => {
-                            println!("writing privatekey and certificate.");
-                            guardian::certificate_generation::gen_identity();
-                        }
-                        false => {
-                            panic!("aborting guardian setup");
-                        }
+                println!("failed to open identity.pem. would you like to generate it? [y/n]");
+                let mut ans = String::new();
+                std::io::stdin()
+                    .read_line(&mut ans)
+                    .expect("Failed to read line");
+                match ans.trim().to_lowercase().starts_with('y') {
+                    true => {
+                        println!("writing privatekey and certificate.");
+                        guardian::certificate_generation::gen_identity();
+                    }
+                    false => {
+                        panic!("aborting guardian setup");
                     }
                 }
+            }
         }
         let pem_file: std::fs::File = std::fs::File::open("identity.pem").unwrap();
 
@@ -87,7 +82,7 @@ async fn main() {
                 IpAddr::V4(addr) => host_ip = addr.octets().to_vec(),
                 IpAddr::V6(addr) => host_ip = addr.octets().to_vec(),
             };
-            
+
             // check if any of the hostnames correspond to the one in .env
             if let Some(ip) = altname.ipaddress() {
                 if ip == host_ip {
@@ -113,51 +108,195 @@ async fn main() {
         }
     }
 
-    let client = siwe_oidc_auth::login(&private_key, &pkc_url).await;
-    let pkc = pkc_api::Pkc::new_with_options(chrono::Utc::now().naive_utc(), pkc_url, client);
+    // without a PKC the revisions are kept in a SQLite database or a plain directory
+    match (
+        std::env::var("STORAGE_SQLITE"),
+        std::env::var("STORAGE_DIR"),
+    ) {
+        (Ok(storage_sqlite), _) => {
+            let storage = local_storage::sqlite::SqliteStorage::new(storage_sqlite)
+                .expect("failed to open storage database");
+            run(storage, private_key, admin_user, host, port).await
+        }
+        (_, Ok(storage_dir)) => {
+            let storage = local_storage::fs::FsStorage::new(storage_dir)
+                .expect("failed to open storage directory");
+            run(storage, private_key, admin_user, host, port).await
+        }
+        _ => {
+            let pkc_url: url::Url = std::env::var("PKC_URL")
+                .expect("no pkc url")
+                .parse()
+                .expect("not a valid url");
+            let client = siwe_oidc_auth::login(&private_key, &pkc_url).await;
+            let pkc =
+                pkc_api::Pkc::new_with_options(chrono::Utc::now().naive_utc(), pkc_url, client);
+            run(pkc, private_key, admin_user, host, port).await
+        }
+    }
+}
+
+/// runs the guardian on top of any storage that speaks [`RevContext`], so it can sync with guardians on a PKC
+async fn run<S>(
+    storage: S,
+    private_key: guardian_common::signing::SimpleSigner,
+    admin_user: ethaddr::Address,
+    host: IpAddr,
+    port: u16,
+) where
+    S: Storage<Context = RevContext> + Clone + Debug + Send + Sync + 'static,
+    S::Error: Send + Sync,
+    S::Cursor: serde::Serialize + serde::de::DeserializeOwned + Sync,
+{
+    // building the state and syncing read the same revisions over and over
+    let storage = local_storage::cached::CachedStorage::new(storage);
+    // keep what we cannot store right now and try again later
+    let storage = local_storage::outbox::Outbox::open(
+        storage,
+        std::env::var("OUTBOX_PATH").unwrap_or_else(|_| "outbox.jsonl".to_string()),
+    )
+    .expect("failed to open outbox");
+    tokio::spawn(
+        storage
+            .clone()
+            .replay_every(std::time::Duration::from_secs(5)),
+    );
+
+    // template versions contracts are recognized by besides the built-in ones
+    let templates = match std::env::var("TEMPLATE_REGISTRY_PATH") {
+        Ok(path) => contract_interpreter::TemplateRegistry::load(path)
+            .expect("couldn't load the template registry"),
+        Err(_) => contract_interpreter::TemplateRegistry::builtin(),
+    };
+    let concurrency = match std::env::var("BOOTSTRAP_CONCURRENCY") {
+        Ok(concurrency) => concurrency
+            .parse()
+            .expect("BOOTSTRAP_CONCURRENCY is no number"),
+        Err(_) => guardian::bootstrap::DEFAULT_BOOTSTRAP_CONCURRENCY,
+    };
+
+    // restarting from a snapshot only has to catch up on the changes since it was taken
+    let snapshot_path =
+        std::env::var("SNAPSHOT_PATH").unwrap_or_else(|_| "snapshot.json".to_string());
+    let snapshot = Snapshot::<S::Cursor>::load(&snapshot_path).unwrap_or_else(|e| {
+        eprintln!("failed to load snapshot, rebuilding the state: {e}");
+        None
+    });
+    let (mut state, cursor) = match snapshot {
+        Some(snapshot) => {
+            eprintln!(
+                "restoring state from snapshot taken at {}",
+                snapshot.taken_at
+            );
+            let (state, cursor) = GuardianState::restore(storage.clone(), snapshot);
+            *state.templates.write() = templates;
+            let latests = storage.list().await.expect("couldn't get all pages");
+            state
+                .discover_templates(&latests, concurrency)
+                .await
+                .expect("couldn't discover templates");
+            (state, cursor)
+        }
+        None => {
+            // taken before listing, so nothing happening while the state is built gets lost
+            let cursor = storage
+                .latest_cursor()
+                .await
+                .expect("couldn't get the latest change");
+            let latests = storage.list().await.expect("couldn't get all pages");
+
+            let state = GuardianState::new(storage.clone());
+            *state.templates.write() = templates;
+            // contracts are only recognized once their template versions are known
+            state
+                .discover_templates(&latests, concurrency)
+                .await
+                .expect("couldn't discover templates");
+            let summary = state
+                .bootstrap(latests, concurrency, |progress| {
+                    eprintln!(
+                        "bootstrap: {}/{} chains, {} revisions verified",
+                        progress.chains_done, progress.chains_total, progress.revisions_verified
+                    );
+                })
+                .await;
+            for failure in &summary.failures {
+                eprintln!(
+                    "failed to add chain {} up to {}: {}",
+                    failure.chain, failure.latest, failure.error
+                );
+            }
+            eprintln!(
+                "bootstrap done: {} chains, {} revisions verified, {} failed branches",
+                summary.chains,
+                summary.revisions_verified,
+                summary.failures.len()
+            );
+            (state, cursor)
+        }
+    };
 
-    let latests = pkc.list().await.expect("couldn't get all pages");
+    // templates without a published hash are only known once they are discovered or configured
+    for unconfigured in state.templates.read().unconfigured() {
+        eprintln!("{unconfigured}, contracts of it are not recognized");
+    }
 
-    let fire = Campfire::new(pkc.clone());
-    let genesi = fire
-        .build(latests)
-        .await
-        .expect("failed to build revision forest");
-    let afire = ::std::sync::Arc::new(fire);
-    for err in afire.clone().burn(genesi).await {
-        eprintln!("error while burning forest: {err}");
+    // forked chains are only shared once the owner picked a tip
+    state.refuse_forks = std::env::var("REFUSE_FORKED_CHAINS").is_ok_and(|v| v == "true");
+    // received revisions are shared onward at most this often
+    if let Ok(depth) = std::env::var("MAX_DELEGATION_DEPTH") {
+        state.max_delegation_depth = depth.parse().expect("MAX_DELEGATION_DEPTH is no number");
     }
-    let Campfire { state, .. } = ::std::sync::Arc::try_unwrap(afire).expect("what?");
 
     eprintln!("{:#?}", &state);
 
-    if let Some(user) = state.guardian_servitude(private_key.identity().into()) {
-        if user != admin_user {
-            panic!("Servitude INVALID: it was signed by invalid user :(");
+    // the admin and the team members this guardian fronts as well
+    let served_users: Vec<ethaddr::Address> = std::env::var("SERVED_USERS")
+        .map(|users| {
+            users
+                .split(',')
+                .filter(|user| !user.trim().is_empty())
+                .map(|user| user.trim().parse().expect("failed to parse SERVED_USERS"))
+                .collect()
+        })
+        .unwrap_or_default();
+    let guardian_addr: ethaddr::Address = private_key.identity().into();
+    // the agreements of all of them are served to other guardians
+    let owners: Vec<ethaddr::Address> = std::iter::once(admin_user).chain(served_users).collect();
+    for &user in &owners {
+        let guardians = state.user_guardians(user);
+        if guardians == [guardian_addr] {
+            println!("{user} has accepted my servitude :).");
+            continue;
         }
+        if guardians.contains(&guardian_addr) {
+            println!("{user} has accepted servitudes of other guardians as well, I will not serve them until they are gone: {guardians:?}");
+            continue;
+        }
+        println!("{user} has not accepted my request yet, sending a new one. Please sign the Servitude contract in the PKC with the Wallet key of {user} (authoritative key = admin).");
 
-        println!("{admin_user} has accepted my servitude :).");
-    } else {
-        println!("Admin has not accepted my request yet, sending a new one. Please sign the Servitude contract in the PKC with the Wallet key used to deploy the PKC (authoritative key = admin).");
-
-        let gs = guardian::contract_generation::make_guardian_servitude(admin_user, &private_key);
+        let gs = guardian::contract_generation::make_guardian_servitude(user, &private_key);
 
         let genesis_hash = gs.first().unwrap().metadata.verification_hash;
+        // the admin's servitude keeps its page name from when there was only one
+        let name = if user == admin_user {
+            format!("Servitude:{guardian_addr}")
+        } else {
+            format!("Servitude:{guardian_addr}:{user}")
+        };
         for thing2 in gs {
-            pkc.store(
-                thing2,
-                RevContext {
-                    namespace: 0,
-                    name: format!(
-                        "Servitude:{}",
-                        ethaddr::Address::from(private_key.identity())
-                    ),
-                    genesis_hash,
-                    domain_id: ethaddr::Address::from(private_key.identity()).to_string(),
-                },
-            )
-            .await
-            .expect("failed to store");
+            storage
+                .store(
+                    thing2,
+                    RevContext {
+                        namespace: 0,
+                        name: name.clone(),
+                        genesis_hash,
+                        domain_id: guardian_addr.to_string(),
+                    },
+                )
+                .await
+                .expect("failed to store or queue servitude");
         }
     }
 
@@ -191,17 +330,18 @@ async fn main() {
 
         let genesis_hash = tls_cert.first().unwrap().metadata.verification_hash;
         for thing2 in tls_cert {
-            pkc.store(
-                thing2,
-                RevContext {
-                    namespace: 0,
-                    name: format!("TlsCert:{}", ethaddr::Address::from(private_key.identity())),
-                    genesis_hash,
-                    domain_id: ethaddr::Address::from(private_key.identity()).to_string(),
-                },
-            )
-            .await
-            .expect("failed to store TLS Certificate");
+            storage
+                .store(
+                    thing2,
+                    RevContext {
+                        namespace: 0,
+                        name: format!("TlsCert:{}", ethaddr::Address::from(private_key.identity())),
+                        genesis_hash,
+                        domain_id: ethaddr::Address::from(private_key.identity()).to_string(),
+                    },
+                )
+                .await
+                .expect("failed to store or queue TLS Certificate");
         }
     }
 
@@ -239,14 +379,25 @@ async fn main() {
     let astate = std::sync::Arc::new(state);
     let bstate = astate.clone();
 
+    // every access decision for other guardians ends up here
+    let audit = Arc::new(
+        guardian::audit::AuditLog::open(
+            std::env::var("AUDIT_LOG_PATH").unwrap_or_else(|_| "audit.jsonl".to_string()),
+        )
+        .expect("failed to open audit log"),
+    );
+
     let get_handler = move |info: &[webpki::types::CertificateDer<'static>]| {
         let cert = info.first().expect("shit's broken").to_owned();
         let bstate = bstate.clone();
+        let audit = audit.clone();
+        let owners = owners.clone();
         async move {
-            Handler {
+            guardian::handler::Handler {
                 state: bstate.clone(),
                 cert,
-                admin_user,
+                owners,
+                audit,
             }
         }
     };
@@ -257,7 +408,7 @@ async fn main() {
     let identity = reqwest::Identity::from_pem(&std::fs::read("identity.pem").unwrap())
         .expect("identity not found help");
     let bstate = astate.clone();
-    let pkc2 = pkc.clone();
+    let storage2 = storage.clone();
     let run_client = move |cert: Arc<[u8]>, url: url::Url| async move {
         let cert_ta;
 
@@ -312,7 +463,7 @@ async fn main() {
                         };
                         let prev_rev =
                             if let Some(prev_hash) = rev.metadata.previous_verification_hash {
-                                let Ok(prev_rev) = pkc2.read(prev_hash).await else {
+                                let Ok(prev_rev) = storage2.read(prev_hash).await else {
                                     break 'fail;
                                 };
                                 Some(prev_rev)
@@ -329,7 +480,7 @@ async fn main() {
                             );
                             break 'untrust;
                         }
-                        let Ok(_) = pkc2.store(rev, branch.metadata.clone()).await else {
+                        let Ok(_) = storage2.store(rev, branch.metadata.clone()).await else {
                             break 'fail;
                         };
                     }
@@ -357,271 +508,108 @@ async fn main() {
         }
     }
 
-    let pkc2 = pkc.clone();
-    pkc.update_handler(move |hash, kind| {
+    let cstate = astate.clone();
+    let handle = move |hash, event| {
         dbg!(hash);
-        let pkc2 = pkc2.clone();
         let run_client = run_client.clone();
-        let astate = astate.clone();
+        let astate = cstate.clone();
         tokio::spawn(async move {
-            let del = "delete";
-            if kind != del {
-                eprintln!("kind: {:?}",kind);
-                let branch = pkc2.get_branch(hash).await.unwrap();
-                let hashes = branch.hashes;
-                let mut have = None;
-                dbg!(&hashes);
-                for hash in &hashes {
-                    if astate.get_node(hash).is_some() {
-                        have = Some(*hash);
-                        break;
-                    }
-                }
-                dbg!(&have);
-                for need in hashes.into_iter().rev() {
-                    if let Some(has) = have {
-                        if need == has {
-                            have = None;
-                        }
-                        continue;
-                    }
-                    eprintln!("Debug read: Update Handler");
-                    let x = pkc2
-                        .read(need)
-                        .await
-                        .expect("failed getting promised branch revision");
-                    let added = astate
-                        .add(need, x)
-                        .await
-                        .expect("failed adding promised branch rev");
-                    if let Some(guardian::ContractInfo {effective: Some(eff), .. }) = &added.contract {
-                        let guardian::ContractNode{ effect, .. } = eff.as_ref();
-                        eprintln!("effect: {:?}",effect);
-                        match effect {
-                            contract_interpreter::ContractEffect::AccessAgreement((_aa, _e)) => {},
-                            contract_interpreter::ContractEffect::GuardianServitude((_gs, _e)) => {},
-                            contract_interpreter::ContractEffect::TlsIdentityClaim((tic, e)) => {
-                                match e {
-                                    contract_interpreter::TlsIdentityClaimEffects::IdentityClaimed => {
-                                        if tic.guardian != ethaddr {
-                                            eprintln!("Debug read: Identity Claim");
-                                            if let Some((_addr, url)) = astate.guardian_identities.read().get(&tic.cert) {
-                                                tokio::spawn(run_client.clone()(tic.cert.clone(), url.clone()));
-                                            }
-                                        }
-                                    },
+            eprintln!("event: {:?}", event);
+            for added in astate.apply_change(hash, event).await? {
+                if let Some(guardian::ContractInfo {
+                    effective: Some(eff),
+                    ..
+                }) = &added.contract
+                {
+                    let guardian::ContractNode { effect, .. } = eff.as_ref();
+                    eprintln!("effect: {:?}", effect);
+                    match effect {
+                        contract_interpreter::ContractEffect::AccessAgreement((_aa, _e)) => {}
+                        contract_interpreter::ContractEffect::GuardianServitude((_gs, _e)) => {}
+                        contract_interpreter::ContractEffect::AccessRevocation((_ar, _e)) => {}
+                        contract_interpreter::ContractEffect::ForkResolution((_fr, _e)) => {}
+                        contract_interpreter::ContractEffect::Group((_group, _e)) => {}
+                        contract_interpreter::ContractEffect::TlsIdentityClaim((tic, e)) => match e
+                        {
+                            contract_interpreter::TlsIdentityClaimEffects::IdentityClaimed => {
+                                if tic.guardian != ethaddr {
+                                    eprintln!("Debug read: Identity Claim");
+                                    if let Some((_addr, url)) =
+                                        astate.guardian_identities.read().get(&tic.cert)
+                                    {
+                                        tokio::spawn(run_client.clone()(
+                                            tic.cert.clone(),
+                                            url.clone(),
+                                        ));
+                                    }
                                 }
-                            },
-                        }
-                    }
-                }
-            } else if let Some(mut sn) = astate.get_node(&hash) {
-                loop {
-                    let sn2 = sn;
-                    if sn2.leafs.is_empty() {
-                        astate.rm(sn2.hash);
-                    } else {
-                        break;
-                    }
-                    if let Some(sns) = sn2.prev.upgrade() {
-                        sn = sns;
-                    } else {
-                        break;
+                            }
+                        },
                     }
                 }
-            };
+            }
             eprintln!("{:#?}", &astate);
-        });
-    })
-    .await
-    .expect("update handler failed");
-}
-
-#[derive(Debug)]
-struct Campfire<Storage> {
-    forest: dashmap::DashMap<Hash, Vec<Hash>>,
-    storage: Storage,
-    state: GuardianState<Storage>,
-}
+            Ok::<(), guardian::Error<_>>(())
+        })
+    };
 
-impl<S: Clone + Storage + Sync + Send + 'static> Campfire<S> {
-    pub fn new(storage: S) -> Self {
-        Self {
-            forest: dashmap::DashMap::new(),
-            state: GuardianState::<S>::new(storage.clone()),
-            storage,
+    // expired access agreements are not listed anyways, but should not pile up in the lookup
+    let sweeping = astate.clone();
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(std::time::Duration::from_secs(60));
+        loop {
+            interval.tick().await;
+            let swept = sweeping.sweep_expired(chrono::Utc::now().naive_utc());
+            if swept > 0 {
+                eprintln!("swept {swept} expired access agreements");
+            }
         }
-    }
-    /// build the forest
-    async fn build(&self, latests: Vec<Hash>) -> Result<dashmap::DashSet<Hash>, S::Error> {
-        let genesis_set = dashmap::DashSet::new();
-        let genesis_set_ref = &genesis_set;
-
-        let requests = latests.into_iter().map(|latest| async move {
-            let mut iter = self.storage.get_branch(latest).await?.hashes.into_iter();
-            let mut next = iter.next().expect("thing broken");
-
-            // insert leaf
-            self.forest.entry(next).or_default();
-
-            // insert path to bottom
-            for prev in iter {
-                match self.forest.entry(prev) {
-                    dashmap::mapref::entry::Entry::Occupied(mut o) => {
-                        o.get_mut().push(next);
-                    }
-                    dashmap::mapref::entry::Entry::Vacant(v) => {
-                        v.insert(vec![next]);
+    });
+
+    // changes that cannot be handled are put aside here, so they do not hold up the ones after them
+    let dead_letters = guardian::changes::DeadLetters::open(
+        std::env::var("DEAD_LETTER_PATH").unwrap_or_else(|_| "dead_letters.jsonl".to_string()),
+    )
+    .expect("failed to open dead letters");
+    let retry = guardian::changes::Retry::default();
+
+    // a change only moves the cursor once it is in the state or given up, so a snapshot never skips one
+    let mut cursor = cursor;
+    let mut snapshot_interval = tokio::time::interval(std::time::Duration::from_secs(60));
+    let mut changes = std::pin::pin!(storage.changes(Some(cursor.clone())));
+    loop {
+        tokio::select! {
+            change = changes.next() => match change {
+                Some(Ok(change)) => {
+                    let handled = guardian::changes::handle_change(&change, &retry, &dead_letters, |hash, event| {
+                        let task = handle(hash, event);
+                        async move {
+                            match task.await {
+                                Ok(result) => result.map_err(guardian::changes::ChangeFailure::from),
+                                Err(e) => Err(guardian::changes::ChangeFailure::Permanent(e.to_string())),
+                            }
+                        }
+                    })
+                    .await;
+                    if handled {
+                        cursor = change.cursor;
+                    } else {
+                        // resume at the failed change, so it is handled again
+                        tokio::time::sleep(std::time::Duration::from_secs(5)).await;
+                        changes.set(storage.changes(Some(cursor.clone())));
                     }
                 }
-                next = prev;
-            }
-
-            genesis_set_ref.insert(next);
-
-            Ok(())
-        });
-        let mut req_stream = futures::stream::FuturesUnordered::from_iter(requests);
-
-        while let Some(answer) = req_stream.next().await {
-            answer?;
-        }
-        drop(req_stream);
-
-        Ok(genesis_set)
-    }
-
-    /// light the fire
-    async fn burn(
-        self: ::std::sync::Arc<Self>,
-        genesis_set: dashmap::DashSet<Hash>,
-    ) -> impl Iterator<Item = guardian::Error<S>>
-    where
-        <S as guardian_common::storage::Storage>::Error: Send + Sync,
-    {
-        let (sendr, recvr) = crossbeam::channel::unbounded();
-        for genesis_hash in genesis_set {
-            tokio::spawn(self.clone().burn_elem(genesis_hash, sendr.clone()));
-        }
-        drop(sendr);
-        recvr.into_iter()
-    }
-    #[allow(clippy::manual_async_fn)]
-    fn burn_elem(
-        self: ::std::sync::Arc<Self>,
-        hash: Hash,
-        sendr: crossbeam::channel::Sender<guardian::Error<S>>,
-    ) -> impl futures::Future<Output = ()> + Send + Sync
-    where
-        <S as guardian_common::storage::Storage>::Error: Send + Sync,
-    {
-        eprintln!("Debug read: burn element");
-        async move {
-            if let Err(e) = async {
-                let rev = self
-                    .storage
-                    .read(hash)
-                    .await
-                    .map_err(guardian::Error::Storage)?;
-                let _handle = self.state.add(hash, rev).await?;
-                Ok(())
-            }
-            .await
-            {
-                sendr.send(e).ok();
-            }
-            for leaf in self
-                .forest
-                .remove(&hash)
-                .into_iter()
-                .flat_map(|(_hash, v)| v.into_iter())
-            {
-                eprintln!("starting {leaf}");
-                tokio::spawn(self.clone().burn_elem(leaf, sendr.clone()));
+                Some(Err(e)) => eprintln!("failed to get changes: {e}"),
+                None => {
+                    eprintln!("changes ended, resuming");
+                    changes.set(storage.changes(Some(cursor.clone())));
+                }
+            },
+            _ = snapshot_interval.tick() => {
+                if let Err(e) = astate.snapshot(cursor.clone()).save(&snapshot_path) {
+                    eprintln!("failed to save snapshot: {e}");
+                }
             }
         }
     }
 }
-
-#[derive(Clone)]
-struct Handler<S> {
-    state: Arc<GuardianState<S>>,
-    cert: CertificateDer<'static>,
-    admin_user: ethaddr::Address,
-}
-impl<S: guardian_common::storage::Storage> Handler<S> {
-    fn get_addr(&self) -> Result<ethaddr::Address, guardian::Error<S>> {
-        let Some((guardian_addr, _)) = self
-            .state
-            .guardian_identities
-            .read()
-            .get(&self.cert)
-            .cloned()
-        else {
-            return Err(guardian::Error::Denied);
-        };
-        self.state
-            .guardian_servitude
-            .get(&guardian_addr)
-            .ok_or(guardian::Error::Denied)
-            .map(|a| a.0)
-    }
-}
-impl<S: Storage + Debug + Send + Sync> ApiHandler for Handler<S> {
-    type Error = guardian::Error<S>;
-    type Context = S::Context;
-
-    ///lists all hashes avalilabe for remote user
-    async fn list(&self) -> Result<std::collections::HashSet<Hash>, Self::Error> {
-        let user = self.get_addr()?;
-        let hashes = self.state.get_accessible_latests(user, self.admin_user);
-        Ok(hashes)
-    }
-    /// returns hashes of a branch if the branch is available to the remote user
-    async fn get_branch(
-        &self,
-        hash: Hash,
-    ) -> Result<guardian_common::prelude::Branch<Self::Context>, Self::Error> {
-        let user = self.get_addr()?;
-        let Some(hashes) = self
-            .state
-            .get_accessible_branch(user, hash, self.admin_user)
-        else {
-            return Err(Self::Error::Denied);
-        };
-        let context = self
-            .state
-            .storage
-            .get_context(hash)
-            .await
-            .map_err(guardian::Error::Storage)?;
-        let branch = guardian_common::custom_types::Branch {
-            metadata: context,
-            hashes,
-        };
-        Ok(branch)
-    }
-    /// returns revision if it is available to the remote user
-    async fn get_revision(
-        &self,
-        hash: Hash,
-    ) -> Result<guardian_common::prelude::Revision, Self::Error> {
-        let user = self.get_addr()?;
-        if self
-            .state
-            .get_rev_accessible(user, hash, self.admin_user)
-            .is_some()
-        {
-            eprintln!("Debug read: get_revision");
-            let rev = self
-                .state
-                .storage
-                .read(hash)
-                .await
-                .map_err(guardian::Error::Storage)?;
-            Ok(rev)
-        } else {
-            Err(Self::Error::Denied)
-        }
-    }
-}
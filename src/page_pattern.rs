@@ -0,0 +1,179 @@
+//! Agreements sharing every page matching a [`PagePattern`], the ones created later included.
+//!
+//! The page of a chain is taken from the [`PageContext`] of its genesis when the genesis is added and kept on its
+//! [`StateNode`]. Effective agreements with a pattern are kept in [`page_patterns`](GuardianState::page_patterns), so
+//! chains added or moved later are checked against them as well.
+
+use std::{collections::HashSet, sync::Arc};
+
+use contract_interpreter::{ContractEffect, PagePattern};
+use guardian_common::{
+    prelude::*,
+    storage::{PageContext, Storage},
+};
+
+use crate::{ContractNode, Error, GuardianState, IterDownTree, StateNode};
+
+/// the page a chain belongs to
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct Page {
+    pub namespace: i32,
+    pub title: String,
+}
+
+impl Page {
+    pub fn of(context: &impl PageContext) -> Self {
+        Page {
+            namespace: context.namespace(),
+            title: context.title().to_string(),
+        }
+    }
+
+    pub fn matches(&self, pattern: &PagePattern) -> bool {
+        pattern.matches(self.namespace, &self.title)
+    }
+}
+
+/// `genesis` and all revisions after it
+fn chain_of(genesis: &Arc<StateNode>) -> Vec<Arc<StateNode>> {
+    let mut visited = HashSet::new();
+    let mut chain = vec![];
+    let mut stack = vec![genesis.clone()];
+    while let Some(node) = stack.pop() {
+        if visited.insert(node.hash) {
+            stack.extend(node.leafs.iter().map(|leaf| leaf.value().clone()));
+            chain.push(node);
+        }
+    }
+    chain
+}
+
+impl<S: Storage> GuardianState<S> {
+    /// the page of the chain `node` belongs to, if its genesis had a context
+    pub fn page_of(&self, node: &Arc<StateNode>) -> Option<Page> {
+        let genesis = IterDownTree::from(Arc::downgrade(node)).last()?;
+        let page = genesis.page.read().clone();
+        page
+    }
+
+    /// shares every chain whose page matches the pattern of the granted or accepted agreement `contract`
+    pub(crate) fn share_pattern(&self, contract: (Hash, Arc<ContractNode>)) {
+        let (contract_hash, contract_node) = contract;
+        let ContractEffect::AccessAgreement((aa, _)) = &contract_node.effect else {
+            return;
+        };
+        let Some(pattern) = &aa.page_pattern else {
+            return;
+        };
+        self.page_patterns
+            .write()
+            .insert(contract_hash, contract_node.clone());
+
+        let genesi: Vec<Arc<StateNode>> = self
+            .genesis_map
+            .iter()
+            .map(|genesis| genesis.value().clone())
+            .collect();
+        for genesis in genesi {
+            if genesis
+                .page
+                .read()
+                .as_ref()
+                .is_some_and(|page| page.matches(pattern))
+            {
+                self.add_contract_to(
+                    (contract_hash, contract_node.clone()),
+                    (aa.receiver, genesis.hash),
+                );
+            }
+        }
+    }
+
+    /// shares the chain starting at `genesis` by every agreement whose pattern matches its page
+    pub(crate) fn share_by_patterns(&self, genesis: &Arc<StateNode>) {
+        let Some(page) = genesis.page.read().clone() else {
+            return;
+        };
+        let patterns: Vec<(Hash, Arc<ContractNode>)> = self
+            .page_patterns
+            .read()
+            .iter()
+            .map(|(hash, contract)| (*hash, contract))
+            .collect();
+        for (contract_hash, contract_node) in patterns {
+            let ContractEffect::AccessAgreement((aa, _)) = &contract_node.effect else {
+                continue;
+            };
+            if aa
+                .page_pattern
+                .as_ref()
+                .is_some_and(|pattern| page.matches(pattern))
+            {
+                self.add_contract_to(
+                    (contract_hash, contract_node.clone()),
+                    (aa.receiver, genesis.hash),
+                );
+            }
+        }
+    }
+
+    /// puts the chain starting at `genesis` on another page, it is shared by the patterns matching that page only
+    pub fn move_page(&self, genesis: Hash, page: Page) {
+        let Some(genesis) = self.genesis_map.get(&genesis).map(|g| g.value().clone()) else {
+            return;
+        };
+        *genesis.page.write() = Some(page.clone());
+
+        let patterns: Vec<(Hash, Arc<ContractNode>)> = self
+            .page_patterns
+            .read()
+            .iter()
+            .map(|(hash, contract)| (*hash, contract))
+            .collect();
+        let chain = chain_of(&genesis);
+        let in_chain: HashSet<Hash> = chain.iter().map(|node| node.hash).collect();
+        for (contract_hash, contract_node) in patterns {
+            let ContractEffect::AccessAgreement((aa, _)) = &contract_node.effect else {
+                continue;
+            };
+            let still_matches = aa
+                .page_pattern
+                .as_ref()
+                .is_some_and(|pattern| page.matches(pattern));
+            // pages listed by the agreement stay shared
+            let listed = aa.pages.iter().any(|(_name, page)| in_chain.contains(page));
+            if still_matches || listed {
+                continue;
+            }
+            for node in &chain {
+                if let Some(shared) = self.shared_revs.get(&node.hash) {
+                    shared
+                        .write()
+                        .retain(|(_addr, hash), _| *hash != contract_hash);
+                }
+            }
+            self.unindex_share(contract_hash, &contract_node, Some(&in_chain));
+        }
+        self.share_by_patterns(&genesis);
+    }
+
+    /// looks up the page of the chain `hash` belongs to again, for when the page was moved
+    pub async fn refresh_page(&self, hash: Hash) -> Result<(), Error<S>>
+    where
+        S::Context: PageContext,
+    {
+        let Some(genesis) = self
+            .get_node(&hash)
+            .and_then(|node| IterDownTree::from(Arc::downgrade(&node)).last())
+        else {
+            return Ok(());
+        };
+        let context = self
+            .storage
+            .get_context(genesis.hash)
+            .await
+            .map_err(Error::Storage)?;
+        self.move_page(genesis.hash, Page::of(&context));
+        Ok(())
+    }
+}
@@ -0,0 +1,150 @@
+//! Snapshots of a [`GuardianState`], so a restarting guardian does not have to download and verify every revision again.
+//!
+//! Only the revisions and the contracts they hold are kept. Everything derived from them (effective contracts, shares,
+//! [`guardian_servitude`](GuardianState::guardian_servitude), [`guardian_identities`](GuardianState::guardian_identities)
+//! and [`user_lookup`](GuardianState::user_lookup)) is rebuilt on [`restore`](GuardianState::restore) the same way
+//! [`add`](GuardianState::add) builds it, so it cannot drift from what adding the revisions would have done.
+
+use std::{path::Path, sync::Arc};
+
+use contract_interpreter::Contract;
+use guardian_common::prelude::*;
+
+use crate::{page_pattern::Page, GuardianState, StateNode};
+
+/// the format version written into new snapshots, snapshots of other versions are not loaded
+pub const VERSION: u32 = 2;
+
+/// a revision in a [`Snapshot`]
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct SnapshotNode {
+    pub hash: Hash,
+    pub prev: Option<Hash>,
+    /// the merged revision, if this is a merge
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub merge: Option<Hash>,
+    pub contract: Option<Contract>,
+    pub seqno: Option<u8>,
+    /// the page of the chain, for genesis revisions
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub page: Option<Page>,
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct Snapshot<Cursor> {
+    pub version: u32,
+    pub taken_at: chrono::NaiveDateTime,
+    /// where to continue with the [`changes`](guardian_common::storage::Storage::changes) of the storage
+    pub cursor: Cursor,
+    /// every revision of the state, previous and merged revisions come before the revisions building on them
+    pub nodes: Vec<SnapshotNode>,
+}
+
+impl<Cursor> Snapshot<Cursor>
+where
+    Cursor: serde::Serialize + serde::de::DeserializeOwned,
+{
+    /// writes the snapshot to a temporary file next to `path` first, so a crash never leaves half a snapshot behind
+    pub fn save(&self, path: impl AsRef<Path>) -> std::io::Result<()> {
+        let path = path.as_ref();
+        let tmp_path = path.with_extension("tmp");
+        std::fs::write(&tmp_path, serde_json::to_vec(self)?)?;
+        std::fs::rename(tmp_path, path)
+    }
+
+    /// `None` if there is no snapshot at `path` or it was written in another format version
+    pub fn load(path: impl AsRef<Path>) -> std::io::Result<Option<Self>> {
+        let data = match std::fs::read(path) {
+            Ok(data) => data,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e),
+        };
+
+        #[derive(serde::Deserialize)]
+        struct Versioned {
+            version: u32,
+        }
+        let Versioned { version } = serde_json::from_slice(&data)?;
+        if version != VERSION {
+            eprintln!("ignoring snapshot of version {version}, expected {VERSION}");
+            return Ok(None);
+        }
+
+        Ok(Some(serde_json::from_slice(&data)?))
+    }
+}
+
+impl<S: guardian_common::storage::Storage> GuardianState<S> {
+    /// captures all revisions of the state, `cursor` being the position in the changes of the storage they reflect
+    pub fn snapshot<Cursor>(&self, cursor: Cursor) -> Snapshot<Cursor> {
+        let mut nodes = vec![];
+        let mut stack: Vec<Arc<StateNode>> = self
+            .genesis_map
+            .iter()
+            .map(|genesis| genesis.value().clone())
+            .collect();
+        // a merge is a leaf of both its parents, it is written once the second of them was
+        let mut parents_left: std::collections::HashMap<Hash, usize> = Default::default();
+        while let Some(node) = stack.pop() {
+            nodes.push(SnapshotNode {
+                hash: node.hash,
+                prev: node.prev.upgrade().map(|prev| prev.hash),
+                merge: node.merge.upgrade().map(|merge| merge.hash),
+                contract: node.contract.as_ref().map(|info| info.data.clone()),
+                seqno: node.contract.as_ref().and_then(|info| info.seqno),
+                page: node.page.read().clone(),
+            });
+            for leaf in node.leafs.iter() {
+                let left = parents_left
+                    .entry(leaf.hash)
+                    .or_insert_with(|| leaf.parents().count());
+                *left -= 1;
+                if *left == 0 {
+                    stack.push(leaf.value().clone());
+                }
+            }
+        }
+
+        Snapshot {
+            version: VERSION,
+            taken_at: chrono::Utc::now().naive_utc(),
+            cursor,
+            nodes,
+        }
+    }
+
+    /// rebuilds the state from a snapshot without touching the storage, returns where to continue in its changes
+    pub fn restore<Cursor>(storage: S, snapshot: Snapshot<Cursor>) -> (Self, Cursor) {
+        let state = GuardianState::new(storage);
+        for node in snapshot.nodes {
+            let prev = match node.prev {
+                Some(prev) => match state.get_node(&prev) {
+                    Some(prev) => Some(prev),
+                    None => {
+                        eprintln!("[{}]: previous revision missing in snapshot", node.hash);
+                        continue;
+                    }
+                },
+                None => None,
+            };
+            let merge = match node.merge {
+                Some(merge) => match state.get_node(&merge) {
+                    Some(merge) => Some(merge),
+                    None => {
+                        eprintln!("[{}]: merged revision missing in snapshot", node.hash);
+                        continue;
+                    }
+                },
+                None => None,
+            };
+            state.insert_node(
+                node.hash,
+                prev,
+                merge,
+                node.contract.map(|c| (c, node.seqno)),
+                node.page,
+            );
+        }
+        (state, snapshot.cursor)
+    }
+}
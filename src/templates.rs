@@ -0,0 +1,89 @@
+//! Template versions contracts are recognized by, kept in [`templates`](GuardianState::templates).
+//!
+//! Besides the configured versions, the latest revision of a page in the template namespace named like a
+//! [`ContractKind`] is a hash of the current version of its template. Those are found when such revisions are added and by
+//! [`discover_templates`](GuardianState::discover_templates), which has to run before contracts using them are added.
+//! Older revisions may be older versions with other parameters, so they are only known if they are configured.
+
+use contract_interpreter::ContractKind;
+use futures::StreamExt;
+use guardian_common::{
+    prelude::*,
+    storage::{PageContext, Storage},
+};
+
+use crate::{page_pattern::Page, Error, GuardianState};
+
+/// the MediaWiki namespace of templates
+pub const TEMPLATE_NAMESPACE: i32 = 10;
+
+/// the kind of contracts `page` is the template of, if it is one
+///
+/// "Template:Group" is a common page unrelated to contracts, the hashes of the [`Group`](ContractKind::Group) template
+/// have to be configured.
+pub fn template_kind(page: &Page) -> Option<ContractKind> {
+    if page.namespace != TEMPLATE_NAMESPACE {
+        return None;
+    }
+    ContractKind::from_template_name(&page.title).filter(|kind| *kind != ContractKind::Group)
+}
+
+impl<S> GuardianState<S> {
+    /// registers `hash` as the current version of the template `page` is, if it is one. returns whether it was new
+    ///
+    /// `hash` has to be the latest revision of the page, hashes known already keep their version.
+    pub fn register_template(&self, page: &Page, hash: Hash) -> bool {
+        let Some(kind) = template_kind(page) else {
+            return false;
+        };
+        let version = kind.current_version();
+        let new = self.templates.write().insert(kind, version, hash);
+        if new {
+            eprintln!(
+                "[{hash}]: new hash of template {} version {version}",
+                kind.template_name()
+            );
+        }
+        new
+    }
+}
+
+impl<S: Storage> GuardianState<S>
+where
+    S::Context: PageContext,
+{
+    /// registers the template pages among `latests`, returns how many were new
+    ///
+    /// at most `concurrency` pages are looked at at once.
+    pub async fn discover_templates(
+        &self,
+        latests: &[Hash],
+        concurrency: usize,
+    ) -> Result<usize, Error<S>> {
+        let mut pages = futures::stream::iter(latests.iter().copied())
+            .map(|latest| async move {
+                let context = self.storage.get_context(latest).await?;
+                Ok((Page::of(&context), latest))
+            })
+            .buffer_unordered(concurrency.max(1));
+        let mut new = 0;
+        while let Some(page) = pages.next().await {
+            let (page, latest) = page.map_err(Error::Storage)?;
+            new += usize::from(self.register_template(&page, latest));
+        }
+        Ok(new)
+    }
+
+    /// registers the revision `hash` of `page` if it is a template and the latest revision of the page
+    pub(crate) async fn register_if_latest(
+        &self,
+        page: &Page,
+        hash: Hash,
+    ) -> Result<bool, Error<S>> {
+        if template_kind(page).is_none() {
+            return Ok(false);
+        }
+        let latests = self.storage.list().await.map_err(Error::Storage)?;
+        Ok(latests.contains(&hash) && self.register_template(page, hash))
+    }
+}
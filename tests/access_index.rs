@@ -0,0 +1,112 @@
+mod common;
+
+use common::*;
+use contract_interpreter::Contract;
+use guardian::{snapshot::SnapshotNode, GuardianState};
+use local_storage::prelude::*;
+
+const SENDER: ethaddr::Address = ethaddr::Address([1; 20]);
+const RECEIVER: ethaddr::Address = ethaddr::Address([2; 20]);
+
+/// a granted agreement (10 <- 11) sharing the page revision `page`
+fn shared(page: u8) -> Vec<SnapshotNode> {
+    let agreement = Contract::AccessAgreement(agreement(SENDER, RECEIVER, &[("Page", hash(page))]));
+    vec![
+        node(10, None, Some((agreement.clone(), 0))),
+        node(11, Some(10), Some((agreement, 1))),
+    ]
+}
+
+#[test]
+fn shared_once_per_chain() {
+    // 1 <- 2 <- 3 shared by 2, 4 added after the agreement
+    let nodes = [
+        node(1, None, None),
+        node(2, Some(1), None),
+        node(3, Some(2), None),
+    ]
+    .into_iter()
+    .chain(shared(2))
+    .chain([node(4, Some(3), None)])
+    .collect();
+    let state = restore(nodes);
+
+    for n in 1..=4 {
+        assert!(state
+            .get_rev_accessible(RECEIVER, hash(n), SENDER)
+            .is_some());
+    }
+    assert_eq!(state.get_node(&hash(4)).unwrap().chains, vec![hash(1)]);
+    let chain = state.access_index.get(&hash(1)).unwrap();
+    let shares = chain.shares.read();
+    let share = &shares[&RECEIVER.into()][&hash(11)];
+    assert_eq!(
+        share.bounds.keys().copied().collect::<Vec<_>>(),
+        vec![hash(2)]
+    );
+    drop(shares);
+    drop(chain);
+    assert_eq!(
+        state.get_accessible_latests(RECEIVER, SENDER),
+        [hash(4)].into()
+    );
+}
+
+#[test]
+fn forked_branches_stay_apart() {
+    // 1 <- 2 and 1 <- 3 with 2 shared, 2 <- 4 and 1 <- 5 added after the agreement
+    let nodes = [
+        node(1, None, None),
+        node(2, Some(1), None),
+        node(3, Some(1), None),
+    ]
+    .into_iter()
+    .chain(shared(2))
+    .chain([node(4, Some(2), None), node(5, Some(1), None)])
+    .collect();
+    let state = restore(nodes);
+
+    let accessible = |n: u8| {
+        state
+            .get_rev_accessible(RECEIVER, hash(n), SENDER)
+            .is_some()
+    };
+    assert!(accessible(1) && accessible(2) && accessible(4));
+    // neither before nor after the shared revision
+    assert!(!accessible(3) && !accessible(5));
+    assert_eq!(
+        state.get_accessible_latests(RECEIVER, SENDER),
+        [hash(4)].into()
+    );
+
+    drop(state.rm(hash(4)));
+    assert_eq!(
+        state.get_accessible_latests(RECEIVER, SENDER),
+        [hash(2)].into()
+    );
+}
+
+#[tokio::test]
+async fn signed_agreement_shares_revisions_added_later() {
+    let (sender, receiver) = (signer(1), signer(2));
+    let state = GuardianState::new(MemoryStorage::new());
+    let mut page = page(&["a", "b"]);
+    add_chain(&state, "Page", &page).await;
+    let agreement = Contract::AccessAgreement(agreement(
+        address(&sender),
+        address(&receiver),
+        &[("Page", genesis_of(&page))],
+    ));
+    let chain = signed_contract(&state, &agreement, &sender);
+    add_chain(&state, "Agreement", &chain).await;
+    let later = revision("c", page.last(), None);
+    page.push(later);
+    add_chain(&state, "Page", &page).await;
+
+    let latest = state.get_node(&latest_of(&page)).unwrap();
+    assert_eq!(latest.chains, vec![genesis_of(&page)]);
+    assert_eq!(
+        state.get_accessible_latests(address(&receiver), address(&sender)),
+        [latest_of(&page)].into()
+    );
+}
@@ -0,0 +1,157 @@
+mod common;
+
+use common::*;
+use contract_interpreter::{
+    AccessAgreement, AccessRevocation, Contract, ContractKind, TemplateRegistry,
+    UnconfiguredTemplate,
+};
+use guardian::{snapshot::SnapshotNode, GuardianState};
+use local_storage::prelude::*;
+
+const SENDER: ethaddr::Address = ethaddr::Address([1; 20]);
+const RECEIVER: ethaddr::Address = ethaddr::Address([2; 20]);
+
+/// a page (1 <- 2), an agreement sharing it (10 <- 11) and its revocation (20 <- 21)
+fn nodes() -> (Vec<SnapshotNode>, Vec<SnapshotNode>, Vec<SnapshotNode>) {
+    let agreement = Contract::AccessAgreement(AccessAgreement {
+        sender: SENDER,
+        receiver: RECEIVER.into(),
+        pages: vec![("Page".to_string(), hash(1))],
+        page_pattern: None,
+        terms: None,
+        valid_from: None,
+        valid_until: None,
+        may_reshare: false,
+        template_version: 2,
+    });
+    let revocation = Contract::AccessRevocation(AccessRevocation {
+        sender: SENDER,
+        agreement: hash(10),
+        template_version: 1,
+    });
+    (
+        vec![node(1, None, None), node(2, Some(1), None)],
+        vec![
+            node(10, None, Some((agreement.clone(), 0))),
+            node(11, Some(10), Some((agreement, 1))),
+        ],
+        vec![
+            node(20, None, Some((revocation.clone(), 0))),
+            node(21, Some(20), Some((revocation, 1))),
+        ],
+    )
+}
+
+#[test]
+fn revocation_takes_back_shares() {
+    let (page, agreement, revocation) = nodes();
+    let shared: Vec<SnapshotNode> = page.into_iter().chain(agreement).collect();
+    let state = restore(shared.clone());
+    assert_eq!(
+        state.get_accessible_latests(RECEIVER, SENDER),
+        [hash(2)].into()
+    );
+    assert!(state
+        .get_rev_accessible(RECEIVER, hash(1), SENDER)
+        .is_some());
+
+    let state = restore(shared.into_iter().chain(revocation).collect());
+    assert!(state.get_accessible_latests(RECEIVER, SENDER).is_empty());
+    assert!(state
+        .get_rev_accessible(RECEIVER, hash(1), SENDER)
+        .is_none());
+    assert!(state
+        .get_rev_accessible(RECEIVER, hash(2), SENDER)
+        .is_none());
+}
+
+#[test]
+fn revocation_before_agreement_prevents_sharing() {
+    let (page, agreement, revocation) = nodes();
+    let state = restore(
+        revocation
+            .into_iter()
+            .chain(page)
+            .chain(agreement)
+            .collect(),
+    );
+    assert!(state.get_accessible_latests(RECEIVER, SENDER).is_empty());
+    assert!(state
+        .get_rev_accessible(RECEIVER, hash(1), SENDER)
+        .is_none());
+}
+
+#[test]
+fn revocation_by_someone_else_is_ignored() {
+    let (page, agreement, _) = nodes();
+    let revocation = Contract::AccessRevocation(AccessRevocation {
+        sender: RECEIVER,
+        agreement: hash(10),
+        template_version: 1,
+    });
+    let state = restore(
+        page.into_iter()
+            .chain(agreement)
+            .chain([
+                node(20, None, Some((revocation.clone(), 0))),
+                node(21, Some(20), Some((revocation, 1))),
+            ])
+            .collect(),
+    );
+    assert_eq!(
+        state.get_accessible_latests(RECEIVER, SENDER),
+        [hash(2)].into()
+    );
+}
+
+/// signed revisions of a page, an agreement sharing it and its revocation, added like the guardian does
+#[tokio::test]
+async fn signed_revocation_takes_back_shares() {
+    let sender = signer(1);
+    let receiver = address(&signer(2));
+    let state = GuardianState::new(MemoryStorage::new());
+    // the template is not published, so its hash comes from the registry
+    state
+        .templates
+        .write()
+        .insert(ContractKind::AccessRevocation, 1, hash(99));
+
+    let page = page(&["a", "b"]);
+    add_chain(&state, "Page", &page).await;
+    let shared = agreement(address(&sender), receiver, &[("Page", genesis_of(&page))]);
+    let agreement = signed_contract(&state, &Contract::AccessAgreement(shared), &sender);
+    add_chain(&state, "Agreement", &agreement).await;
+    assert_eq!(
+        state.get_accessible_latests(receiver, address(&sender)),
+        [latest_of(&page)].into()
+    );
+
+    let revocation = guardian::contract_generation::make_access_revocation(
+        genesis_of(&agreement),
+        &state.templates.read(),
+        &sender,
+    )
+    .unwrap();
+    add_chain(&state, "Revocation", &revocation).await;
+    assert!(state
+        .get_accessible_latests(receiver, address(&sender))
+        .is_empty());
+    let revoked = state.revocations.get(&genesis_of(&agreement)).unwrap();
+    assert_eq!(revoked.0, address(&sender));
+}
+
+#[test]
+fn revocation_template_has_to_be_configured() {
+    let made = guardian::contract_generation::make_access_revocation(
+        hash(10),
+        &TemplateRegistry::builtin(),
+        signer(1),
+    );
+    assert_eq!(
+        made.unwrap_err(),
+        UnconfiguredTemplate {
+            kind: ContractKind::AccessRevocation,
+            version: 1
+        }
+    );
+}
@@ -0,0 +1,121 @@
+mod common;
+
+use common::*;
+use contract_interpreter::{AccessAgreement, Contract};
+use guardian::GuardianState;
+use local_storage::prelude::*;
+
+const SENDER: ethaddr::Address = ethaddr::Address([1; 20]);
+const RECEIVER: ethaddr::Address = ethaddr::Address([2; 20]);
+
+/// a page (1 <- 2) shared by a granted agreement (10 <- 11) valid between `from` and `until`, in days from now
+fn state(from: Option<i64>, until: Option<i64>) -> GuardianState<MemoryStorage> {
+    let now = chrono::Utc::now().naive_utc();
+    let agreement = Contract::AccessAgreement(AccessAgreement {
+        sender: SENDER,
+        receiver: RECEIVER.into(),
+        pages: vec![("Page".to_string(), hash(1))],
+        page_pattern: None,
+        terms: None,
+        valid_from: from.map(|days| now + chrono::Duration::days(days)),
+        valid_until: until.map(|days| now + chrono::Duration::days(days)),
+        may_reshare: false,
+        template_version: 2,
+    });
+    restore(vec![
+        node(1, None, None),
+        node(2, Some(1), None),
+        node(10, None, Some((agreement.clone(), 0))),
+        node(11, Some(10), Some((agreement, 1))),
+    ])
+}
+
+fn accessible(state: &GuardianState<MemoryStorage>) -> bool {
+    let listed = state
+        .get_accessible_latests(RECEIVER, SENDER)
+        .contains(&hash(2));
+    let readable = state
+        .get_rev_accessible(RECEIVER, hash(2), SENDER)
+        .is_some();
+    let branch = state.get_accessible_branch(RECEIVER, hash(2), SENDER);
+    assert_eq!(listed, readable);
+    assert_eq!(readable, branch.is_some());
+    readable
+}
+
+#[test]
+fn access_only_within_window() {
+    assert!(accessible(&state(None, None)));
+    assert!(accessible(&state(Some(-1), Some(1))));
+    assert!(!accessible(&state(Some(1), None)));
+    assert!(!accessible(&state(None, Some(-1))));
+}
+
+#[test]
+fn sweep_drops_expired_agreements() {
+    let expired = state(None, Some(-1));
+    assert_eq!(
+        expired
+            .user_lookup
+            .get(&RECEIVER.into())
+            .unwrap()
+            .read()
+            .len(),
+        1
+    );
+    assert_eq!(expired.sweep_expired(chrono::Utc::now().naive_utc()), 1);
+    assert_eq!(
+        expired
+            .user_lookup
+            .get(&RECEIVER.into())
+            .unwrap()
+            .read()
+            .len(),
+        0
+    );
+
+    // not yet valid is not expired
+    let upcoming = state(Some(1), Some(2));
+    assert_eq!(upcoming.sweep_expired(chrono::Utc::now().naive_utc()), 0);
+    assert_eq!(
+        upcoming
+            .user_lookup
+            .get(&RECEIVER.into())
+            .unwrap()
+            .read()
+            .len(),
+        1
+    );
+}
+
+/// the window of a signed agreement added like the guardian does is kept to the second
+#[tokio::test]
+async fn signed_agreement_only_within_window() {
+    let sender = signer(1);
+    let receiver = address(&signer(2));
+    let now = chrono::Utc::now().naive_utc();
+    let state = GuardianState::new(MemoryStorage::new());
+    let page = page(&["a", "b"]);
+    add_chain(&state, "Page", &page).await;
+    let window = |from: i64, until: i64| {
+        Contract::AccessAgreement(AccessAgreement {
+            valid_from: Some(now + chrono::Duration::days(from)),
+            valid_until: Some(now + chrono::Duration::days(until)),
+            ..agreement(address(&sender), receiver, &[("Page", genesis_of(&page))])
+        })
+    };
+
+    let expired = signed_contract(&state, &window(-2, -1), &sender);
+    add_chain(&state, "Expired", &expired).await;
+    assert!(state
+        .get_rev_accessible(receiver, latest_of(&page), address(&sender))
+        .is_none());
+
+    let current = signed_contract(&state, &window(-1, 1), &sender);
+    add_chain(&state, "Current", &current).await;
+    assert_eq!(
+        state.get_accessible_latests(receiver, address(&sender)),
+        [latest_of(&page)].into()
+    );
+    assert_eq!(state.sweep_expired(chrono::Utc::now().naive_utc()), 1);
+}
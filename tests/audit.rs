@@ -0,0 +1,61 @@
+use guardian::audit::*;
+use guardian_common::custom_types::Hash;
+
+fn record(n: u8, outcome: AuditOutcome) -> AuditRecord {
+    AuditRecord {
+        peer_cert: vec![n; 32],
+        user: Some(ethaddr::Address([n; 20])),
+        request: AuditRequest::GetRevision(Hash::from([n; 64])),
+        outcome,
+        contracts: match outcome {
+            AuditOutcome::Allowed => vec![Hash::from([n + 1; 64])],
+            AuditOutcome::Denied => vec![],
+        },
+    }
+}
+
+#[test]
+fn entries_are_chained_across_restarts() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("audit.jsonl");
+
+    let log = AuditLog::open(&path).unwrap();
+    let first = log.record(record(1, AuditOutcome::Allowed)).unwrap();
+    let second = log.record(record(2, AuditOutcome::Denied)).unwrap();
+    assert_eq!(first.prev, Hash::default());
+    assert_eq!(second.prev, first.hash);
+    drop(log);
+
+    let log = AuditLog::open(&path).unwrap();
+    let third = log.record(record(3, AuditOutcome::Allowed)).unwrap();
+    assert_eq!((third.seqno, third.prev), (2, second.hash));
+
+    assert_eq!(verify(&path).unwrap(), 3);
+    let entries = read(&path).unwrap();
+    assert_eq!(entries, vec![first, second, third]);
+}
+
+#[test]
+fn tampering_breaks_the_chain() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("audit.jsonl");
+
+    let log = AuditLog::open(&path).unwrap();
+    for n in 1..=3 {
+        log.record(record(n, AuditOutcome::Denied)).unwrap();
+    }
+    drop(log);
+
+    // claim the second request was allowed
+    let data = std::fs::read_to_string(&path).unwrap();
+    let mut lines: Vec<String> = data.lines().map(str::to_string).collect();
+    lines[1] = lines[1].replace("\"denied\"", "\"allowed\"");
+    std::fs::write(&path, lines.join("\n") + "\n").unwrap();
+    assert!(matches!(verify(&path), Err(AuditError::Broken(1))));
+    assert!(matches!(AuditLog::open(&path), Err(AuditError::Broken(1))));
+
+    // or drop it altogether
+    lines.remove(1);
+    std::fs::write(&path, lines.join("\n") + "\n").unwrap();
+    assert!(matches!(verify(&path), Err(AuditError::Broken(1))));
+}
@@ -0,0 +1,130 @@
+use guardian::{bootstrap::Progress, GuardianState};
+use guardian_common::custom_types::*;
+use local_storage::prelude::*;
+
+fn context(genesis_hash: Hash) -> RevContext {
+    RevContext {
+        namespace: 0,
+        name: "Main_Page".to_string(),
+        genesis_hash,
+        domain_id: "42".to_string(),
+    }
+}
+
+fn revision(text: &str, prev: Option<&Revision>) -> Revision {
+    use verifier::v1_1::hashes::*;
+    let content: std::collections::BTreeMap<String, String> =
+        [("main".to_string(), text.to_string())].into();
+    let content_hash = content_hash(&content);
+    let time_stamp: Timestamp = "20240601000000".parse().unwrap();
+    let previous_verification_hash = prev.map(|prev| prev.metadata.verification_hash);
+    let metadata_hash = metadata_hash("42", &time_stamp, previous_verification_hash.as_ref(), None);
+    Revision {
+        content: RevisionContent {
+            file: None,
+            content,
+            content_hash,
+        },
+        metadata: RevisionMetadata {
+            domain_id: "42".to_string(),
+            time_stamp,
+            previous_verification_hash,
+            merge_verification_hash: None,
+            metadata_hash,
+            verification_hash: verification_hash(&content_hash, &metadata_hash, None, None),
+        },
+        signature: None,
+        witness: None,
+    }
+}
+
+async fn store(storage: &MemoryStorage, revisions: &[&Revision]) {
+    let genesis = revisions[0].metadata.verification_hash;
+    for rev in revisions {
+        storage
+            .store((*rev).clone(), context(genesis))
+            .await
+            .unwrap();
+    }
+}
+
+#[tokio::test]
+async fn forks_are_one_chain() {
+    // a <- b <- c and a <- d, e on its own
+    let a = revision("a", None);
+    let b = revision("b", Some(&a));
+    let c = revision("c", Some(&b));
+    let d = revision("d", Some(&a));
+    let e = revision("e", None);
+    let storage = MemoryStorage::new();
+    store(&storage, &[&a, &b, &c, &d]).await;
+    store(&storage, &[&e]).await;
+
+    let state = GuardianState::new(storage.clone());
+    let mut reported = vec![];
+    let summary = state
+        .bootstrap(storage.list().await.unwrap(), 2, |progress| {
+            reported.push(progress)
+        })
+        .await;
+
+    assert!(summary.failures.is_empty());
+    assert_eq!((summary.chains, summary.revisions_verified), (2, 5));
+    assert_eq!(
+        reported.last(),
+        Some(&Progress {
+            chains_done: 2,
+            chains_total: 2,
+            revisions_verified: 5,
+        })
+    );
+    let genesis = state.get_node(&a.metadata.verification_hash).unwrap();
+    assert_eq!(genesis.leafs.len(), 2);
+}
+
+#[tokio::test]
+async fn failures_are_collected_per_chain() {
+    let a = revision("a", None);
+    let b = revision("b", Some(&a));
+    let mut forged = revision("c", Some(&b));
+    forged
+        .content
+        .content
+        .insert("main".to_string(), "forged".to_string());
+    let e = revision("e", None);
+    let storage = MemoryStorage::new();
+    store(&storage, &[&a, &b, &forged]).await;
+    store(&storage, &[&e]).await;
+
+    let unknown = Hash::from([7; 64]);
+    let mut latests = storage.list().await.unwrap();
+    latests.push(unknown);
+
+    let state = GuardianState::new(storage);
+    let summary = state.bootstrap(latests, 1, |_progress| {}).await;
+
+    // the revisions before the forged one and the other chain are in
+    assert_eq!((summary.chains, summary.revisions_verified), (2, 3));
+    assert!(state.get_node(&b.metadata.verification_hash).is_some());
+    assert!(state.get_node(&e.metadata.verification_hash).is_some());
+
+    let mut failures: Vec<(Hash, Hash)> = summary
+        .failures
+        .iter()
+        .map(|failure| (failure.chain, failure.latest))
+        .collect();
+    failures.sort();
+    let mut expected = vec![
+        (unknown, unknown),
+        (
+            a.metadata.verification_hash,
+            forged.metadata.verification_hash,
+        ),
+    ];
+    expected.sort();
+    assert_eq!(failures, expected);
+    assert!(summary
+        .failures
+        .iter()
+        .any(|failure| matches!(failure.error, guardian::Error::Verifier(_))));
+}
@@ -0,0 +1,140 @@
+use std::time::Duration;
+
+use futures::StreamExt;
+use guardian::{
+    changes::{self, ChangeFailure, DeadLetters, Retry},
+    GuardianState,
+};
+use guardian_common::{custom_types::*, signing::SimpleSigner};
+use local_storage::prelude::*;
+
+fn context(name: &str, genesis_hash: Hash) -> RevContext {
+    RevContext {
+        namespace: 0,
+        name: name.to_string(),
+        genesis_hash,
+        domain_id: "42".to_string(),
+    }
+}
+
+fn retry() -> Retry {
+    Retry {
+        attempts: 3,
+        backoff: Duration::from_millis(1),
+        max_backoff: Duration::from_millis(2),
+    }
+}
+
+/// a change of a revision that is gone by the time it is handled is put aside, the changes after it still get in
+#[tokio::test]
+async fn unresolvable_change_does_not_block_later_ones() {
+    let signer: SimpleSigner = "0x284750bbd0425ce597494511b7a4d579d0b366633af7584050610d64971141a7"
+        .parse()
+        .unwrap();
+    let user = ethaddr::Address([1; 20]);
+    let chain = guardian::contract_generation::make_guardian_servitude(user, signer);
+    let genesis = chain[0].metadata.verification_hash;
+    let latest = chain[1].metadata.verification_hash;
+
+    let storage = MemoryStorage::new();
+    let gone = Revision::default();
+    let gone_hash = gone.metadata.verification_hash;
+    storage
+        .store(gone, context("Gone", gone_hash))
+        .await
+        .unwrap();
+    storage.remove(gone_hash).unwrap();
+    for rev in chain {
+        storage
+            .store(rev, context("Guardian_Servitude", genesis))
+            .await
+            .unwrap();
+    }
+    let state = GuardianState::new(storage.clone());
+
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("dead_letters.jsonl");
+    let dead_letters = DeadLetters::open(&path).unwrap();
+    let mut cursor = None;
+    let mut attempts = 0;
+    let mut changes = std::pin::pin!(storage.changes(None));
+    // created and deleted, then the two revisions of the servitude
+    for _ in 0..4 {
+        let change = changes.next().await.unwrap().unwrap();
+        let handled = changes::handle_change(&change, &retry(), &dead_letters, |hash, event| {
+            attempts += 1;
+            let state = &state;
+            async move {
+                state.apply_change(hash, event).await?;
+                Ok::<_, ChangeFailure>(())
+            }
+        })
+        .await;
+        assert!(handled);
+        cursor = Some(change.cursor);
+    }
+
+    assert_eq!(cursor, Some(4));
+    // the missing revision is no use trying again
+    assert_eq!(attempts, 4);
+    let letters = changes::read(&path).unwrap();
+    assert_eq!(letters.len(), 1);
+    assert_eq!(letters[0].hash, gone_hash);
+    assert_eq!(letters[0].event, StorageEvent::Created);
+    assert_eq!(letters[0].attempts, 1);
+    assert!(state.get_node(&latest).is_some());
+}
+
+#[tokio::test]
+async fn transient_failures_are_retried_a_bounded_number_of_times() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("dead_letters.jsonl");
+    let dead_letters = DeadLetters::open(&path).unwrap();
+    let change = StorageChange {
+        hash: Hash::default(),
+        event: StorageEvent::Created,
+        cursor: 1,
+    };
+
+    let mut attempts = 0;
+    let handled = changes::handle_change(&change, &retry(), &dead_letters, |_hash, _event| {
+        attempts += 1;
+        async { Err(ChangeFailure::Transient("connection reset".to_string())) }
+    })
+    .await;
+
+    assert!(handled);
+    assert_eq!(attempts, 3);
+    let letters = changes::read(&path).unwrap();
+    assert_eq!(letters.len(), 1);
+    assert_eq!(letters[0].attempts, 3);
+    assert_eq!(letters[0].error, "connection reset");
+}
+
+#[tokio::test]
+async fn transient_failure_that_goes_away_is_no_dead_letter() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("dead_letters.jsonl");
+    let dead_letters = DeadLetters::open(&path).unwrap();
+    let change = StorageChange {
+        hash: Hash::default(),
+        event: StorageEvent::Created,
+        cursor: 1,
+    };
+
+    let mut attempts = 0;
+    let handled = changes::handle_change(&change, &retry(), &dead_letters, |_hash, _event| {
+        attempts += 1;
+        let result = if attempts < 2 {
+            Err(ChangeFailure::Transient("connection reset".to_string()))
+        } else {
+            Ok(())
+        };
+        async move { result }
+    })
+    .await;
+
+    assert!(handled);
+    assert_eq!(attempts, 2);
+    assert!(changes::read(&path).unwrap().is_empty());
+}
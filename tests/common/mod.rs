@@ -0,0 +1,165 @@
+//! Fixtures shared by the integration tests.
+//!
+//! A state is either restored from made up [`SnapshotNode`]s, which skips verifying and parsing the revisions, or
+//! built like the guardian does it, from signed revisions in a [`MemoryStorage`] put in by
+//! [`add_with_ancestors`](GuardianState::add_with_ancestors).
+#![allow(dead_code)]
+
+use std::sync::Arc;
+
+use contract_interpreter::{AccessAgreement, Contract, Principal};
+use guardian::{
+    snapshot::{Snapshot, SnapshotNode},
+    GuardianState, StateNode,
+};
+use guardian_common::{
+    custom_types::*,
+    signing::{Signer, SimpleSigner},
+};
+use local_storage::prelude::*;
+
+/// a made up hash, for snapshot nodes and templates
+pub fn hash(n: u8) -> Hash {
+    Hash::from([n; 64])
+}
+
+/// the revision `n` after `prev`, holding `contract` with its sequence number
+pub fn node(n: u8, prev: Option<u8>, contract: Option<(Contract, u8)>) -> SnapshotNode {
+    SnapshotNode {
+        hash: hash(n),
+        prev: prev.map(hash),
+        merge: None,
+        page: None,
+        seqno: contract.as_ref().map(|(_, seqno)| *seqno),
+        contract: contract.map(|(contract, _)| contract),
+    }
+}
+
+pub fn restore(nodes: Vec<SnapshotNode>) -> GuardianState<MemoryStorage> {
+    let snapshot = Snapshot {
+        version: guardian::snapshot::VERSION,
+        taken_at: chrono::Utc::now().naive_utc(),
+        cursor: 0,
+        nodes,
+    };
+    GuardianState::restore(MemoryStorage::new(), snapshot).0
+}
+
+/// the signer with the private key `[n; 32]`
+pub fn signer(n: u8) -> SimpleSigner {
+    SimpleSigner::try_from([n; 32]).unwrap()
+}
+
+pub fn address(signer: &SimpleSigner) -> ethaddr::Address {
+    signer.identity().into()
+}
+
+pub fn context(name: &str, genesis_hash: Hash) -> RevContext {
+    RevContext {
+        namespace: 0,
+        name: name.to_string(),
+        genesis_hash,
+        domain_id: "42".to_string(),
+    }
+}
+
+/// an unsigned revision with the text `text` after `prev`, merging `merge`
+pub fn revision(text: &str, prev: Option<&Revision>, merge: Option<&Revision>) -> Revision {
+    use verifier::v1_1::hashes::*;
+    let content: std::collections::BTreeMap<String, String> =
+        [("main".to_string(), text.to_string())].into();
+    let content_hash = content_hash(&content);
+    let time_stamp: Timestamp = "20240601000000".parse().unwrap();
+    let previous_verification_hash = prev.map(|prev| prev.metadata.verification_hash);
+    let merge_verification_hash = merge.map(|merge| merge.metadata.verification_hash);
+    let metadata_hash = metadata_hash(
+        "42",
+        &time_stamp,
+        previous_verification_hash.as_ref(),
+        merge_verification_hash.as_ref(),
+    );
+    Revision {
+        content: RevisionContent {
+            file: None,
+            content,
+            content_hash,
+        },
+        metadata: RevisionMetadata {
+            domain_id: "42".to_string(),
+            time_stamp,
+            previous_verification_hash,
+            merge_verification_hash,
+            metadata_hash,
+            verification_hash: verification_hash(&content_hash, &metadata_hash, None, None),
+        },
+        signature: None,
+        witness: None,
+    }
+}
+
+/// a page of unsigned revisions with the texts `texts`, genesis first
+pub fn page(texts: &[&str]) -> Vec<Revision> {
+    let mut chain: Vec<Revision> = vec![];
+    for text in texts {
+        let rev = revision(text, chain.last(), None);
+        chain.push(rev);
+    }
+    chain
+}
+
+/// an agreement of `sender` granting `receiver` access to `pages` without terms or time limits
+pub fn agreement(
+    sender: ethaddr::Address,
+    receiver: impl Into<Principal>,
+    pages: &[(&str, Hash)],
+) -> AccessAgreement {
+    AccessAgreement {
+        sender,
+        receiver: receiver.into(),
+        pages: pages
+            .iter()
+            .map(|(name, hash)| (name.to_string(), *hash))
+            .collect(),
+        page_pattern: None,
+        terms: None,
+        valid_from: None,
+        valid_until: None,
+        may_reshare: false,
+        template_version: 2,
+    }
+}
+
+/// `contract` declared and signed by `signer`, with the templates known to `state`
+pub fn signed_contract(
+    state: &GuardianState<MemoryStorage>,
+    contract: &Contract,
+    signer: &SimpleSigner,
+) -> Vec<Revision> {
+    guardian::contract_generation::make_contract(contract, &state.templates.read(), signer).unwrap()
+}
+
+/// stores `chain` as the page `name` and adds it to the state like the guardian does, returning the added nodes
+pub async fn add_chain(
+    state: &GuardianState<MemoryStorage>,
+    name: &str,
+    chain: &[Revision],
+) -> Vec<Arc<StateNode>> {
+    let genesis = chain[0].metadata.verification_hash;
+    for rev in chain {
+        state
+            .storage
+            .store(rev.clone(), context(name, genesis))
+            .await
+            .unwrap();
+    }
+    let latest = chain.last().unwrap().metadata.verification_hash;
+    state.add_with_ancestors(latest).await.unwrap()
+}
+
+pub fn genesis_of(chain: &[Revision]) -> Hash {
+    chain[0].metadata.verification_hash
+}
+
+pub fn latest_of(chain: &[Revision]) -> Hash {
+    chain.last().unwrap().metadata.verification_hash
+}
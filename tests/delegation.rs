@@ -0,0 +1,109 @@
+mod common;
+
+use common::*;
+use contract_interpreter::{AccessAgreement, Contract};
+use guardian::{delegation::DEFAULT_MAX_DELEGATION_DEPTH, explain::Denial, GuardianState};
+use local_storage::prelude::*;
+
+const OWNER: ethaddr::Address = ethaddr::Address([1; 20]);
+const RECEIVER: ethaddr::Address = ethaddr::Address([2; 20]);
+const THIRD: ethaddr::Address = ethaddr::Address([3; 20]);
+const FOURTH: ethaddr::Address = ethaddr::Address([4; 20]);
+
+/// a page (1 <- 2) passed on along `chain`, each agreement (n0 <- n1) sending to the next address
+fn state(chain: &[(ethaddr::Address, bool)]) -> GuardianState<MemoryStorage> {
+    let mut nodes = vec![node(1, None, None), node(2, Some(1), None)];
+    for (n, pair) in (1..).zip(chain.windows(2)) {
+        let [(sender, may_reshare), (receiver, _)] = pair else {
+            unreachable!()
+        };
+        let agreement = Contract::AccessAgreement(AccessAgreement {
+            may_reshare: *may_reshare,
+            ..agreement(*sender, *receiver, &[("Page", hash(1))])
+        });
+        for seqno in 0..2 {
+            let prev = (seqno > 0).then_some(n * 10);
+            nodes.push(node(n * 10 + seqno, prev, Some((agreement.clone(), seqno))));
+        }
+    }
+    restore(nodes)
+}
+
+#[test]
+fn reshare_allowed() {
+    let state = state(&[(OWNER, true), (RECEIVER, false), (THIRD, false)]);
+    let (_node, delegation) = state.get_rev_delegation(THIRD, hash(2), RECEIVER).unwrap();
+    assert_eq!(delegation.contracts(), vec![hash(21), hash(11)]);
+    assert_eq!(delegation.depth(), 1);
+    assert_eq!(
+        state.get_accessible_latests(THIRD, RECEIVER),
+        [hash(2)].into()
+    );
+
+    // the receiver still gets it from the owner
+    let (_node, delegation) = state.get_rev_delegation(RECEIVER, hash(2), OWNER).unwrap();
+    assert_eq!(delegation.contracts(), vec![hash(11)]);
+}
+
+#[test]
+fn reshare_not_allowed() {
+    let state = state(&[(OWNER, false), (RECEIVER, false), (THIRD, false)]);
+    assert!(state.get_rev_accessible(THIRD, hash(2), RECEIVER).is_none());
+    assert!(state.get_accessible_latests(THIRD, RECEIVER).is_empty());
+    assert!(state
+        .explain_access(THIRD, hash(2), RECEIVER)
+        .denials
+        .contains(&Denial::ReshareNotAllowed));
+
+    // the owner is not affected
+    assert!(state.get_rev_accessible(RECEIVER, hash(2), OWNER).is_some());
+}
+
+#[test]
+fn delegation_depth_is_limited() {
+    let chain = [
+        (OWNER, true),
+        (RECEIVER, true),
+        (THIRD, true),
+        (FOURTH, false),
+    ];
+    let mut state = state(&chain);
+    assert_eq!(state.max_delegation_depth, DEFAULT_MAX_DELEGATION_DEPTH);
+    let (_node, delegation) = state.get_rev_delegation(FOURTH, hash(2), THIRD).unwrap();
+    assert_eq!(delegation.contracts(), vec![hash(31), hash(21), hash(11)]);
+
+    state.max_delegation_depth = 1;
+    assert!(state.get_rev_accessible(FOURTH, hash(2), THIRD).is_none());
+    assert!(state
+        .explain_access(FOURTH, hash(2), THIRD)
+        .denials
+        .contains(&Denial::DelegationTooDeep { max_depth: 1 }));
+    assert!(state.get_rev_accessible(THIRD, hash(2), RECEIVER).is_some());
+}
+
+#[tokio::test]
+async fn signed_reshare_is_delegated() {
+    let (owner, receiver, third) = (signer(1), signer(2), signer(3));
+    let state = GuardianState::new(MemoryStorage::new());
+    let page = page(&["a", "b"]);
+    add_chain(&state, "Page", &page).await;
+    let pages = [("Page", genesis_of(&page))];
+    let share = Contract::AccessAgreement(AccessAgreement {
+        may_reshare: true,
+        ..agreement(address(&owner), address(&receiver), &pages)
+    });
+    let share = signed_contract(&state, &share, &owner);
+    add_chain(&state, "Share", &share).await;
+    let reshare = Contract::AccessAgreement(agreement(address(&receiver), address(&third), &pages));
+    let reshare = signed_contract(&state, &reshare, &receiver);
+    add_chain(&state, "Reshare", &reshare).await;
+
+    let (_node, delegation) = state
+        .get_rev_delegation(address(&third), latest_of(&page), address(&receiver))
+        .unwrap();
+    assert_eq!(
+        delegation.contracts(),
+        vec![latest_of(&reshare), latest_of(&share)]
+    );
+    assert_eq!(delegation.depth(), 1);
+}
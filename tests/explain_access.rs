@@ -0,0 +1,137 @@
+mod common;
+
+use common::*;
+use contract_interpreter::{AccessAgreement, AccessAgreementEffects, Contract, GuardianServitude};
+use guardian::{explain::Denial, GuardianState};
+use local_storage::prelude::*;
+
+const OWNER: ethaddr::Address = ethaddr::Address([1; 20]);
+const USER: ethaddr::Address = ethaddr::Address([2; 20]);
+const GUARDIAN: ethaddr::Address = ethaddr::Address([3; 20]);
+
+/// a page (1 <- 2) and an agreement (10 <- 11 [<- 12]) sent by `sender`, a servitude for the user (20 <- 21 <- 22)
+fn state(sender: ethaddr::Address, terms: bool) -> GuardianState<MemoryStorage> {
+    let agreement = Contract::AccessAgreement(AccessAgreement {
+        sender,
+        receiver: USER.into(),
+        pages: vec![("Page".to_string(), hash(1))],
+        page_pattern: None,
+        terms: terms.then(|| "terms".to_string()),
+        valid_from: None,
+        valid_until: None,
+        may_reshare: false,
+        template_version: 2,
+    });
+    let servitude = Contract::GuardianServitude(GuardianServitude {
+        guardian: GUARDIAN,
+        user: USER,
+        template_version: 1,
+    });
+    restore(vec![
+        node(1, None, None),
+        node(2, Some(1), None),
+        node(10, None, Some((agreement.clone(), 0))),
+        node(11, Some(10), Some((agreement, 1))),
+        node(20, None, Some((servitude.clone(), 0))),
+        node(21, Some(20), Some((servitude.clone(), 1))),
+        node(22, Some(21), Some((servitude, 2))),
+    ])
+}
+
+#[test]
+fn granted() {
+    let explanation = state(OWNER, false).explain_access(USER, hash(2), OWNER);
+    assert_eq!(explanation.granted_by, Some(hash(11)));
+    assert_eq!(explanation.contracts.len(), 1);
+    let contract = &explanation.contracts[0];
+    assert_eq!(contract.effect, Some(AccessAgreementEffects::Granted));
+    assert!(contract.sender_is_owner && contract.shared);
+    assert_eq!(contract.denial, None);
+    // the guardian has no certificate yet
+    assert_eq!(
+        explanation.denials,
+        vec![Denial::CertificateMissing { guardian: GUARDIAN }]
+    );
+}
+
+#[test]
+fn only_offered() {
+    let explanation = state(OWNER, true).explain_access(USER, hash(2), OWNER);
+    assert!(!explanation.is_granted());
+    assert_eq!(
+        explanation.contracts[0].effect,
+        Some(AccessAgreementEffects::Offered)
+    );
+    assert!(explanation.denials.contains(&Denial::OnlyOffered));
+
+    // the agreement itself is readable
+    let explanation = state(OWNER, true).explain_access(USER, hash(11), OWNER);
+    assert_eq!(explanation.granted_by, Some(hash(11)));
+}
+
+#[test]
+fn sender_not_owner() {
+    let other = ethaddr::Address([4; 20]);
+    let explanation = state(other, false).explain_access(USER, hash(2), OWNER);
+    assert!(!explanation.is_granted());
+    assert!(!explanation.contracts[0].sender_is_owner);
+    assert!(explanation
+        .denials
+        .contains(&Denial::SenderNotOwner { sender: other }));
+}
+
+#[test]
+fn unknown_and_unshared() {
+    let state = state(OWNER, false);
+    let explanation = state.explain_access(USER, hash(99), OWNER);
+    assert!(explanation.denials.contains(&Denial::RevisionUnknown));
+
+    let stranger = ethaddr::Address([5; 20]);
+    let explanation = state.explain_access(stranger, hash(2), OWNER);
+    assert!(explanation.contracts.is_empty());
+    assert_eq!(
+        explanation.denials,
+        vec![Denial::NoAgreement, Denial::ServitudeMissing]
+    );
+}
+
+/// an offer with terms added from signed revisions is explained as such until the receiver signs it as well
+#[tokio::test]
+async fn signed_offer_is_accepted_by_the_receiver() {
+    let owner = signer(1);
+    let user = signer(2);
+    let state = GuardianState::new(MemoryStorage::new());
+    let page = page(&["a", "b"]);
+    add_chain(&state, "Page", &page).await;
+    let offer = Contract::AccessAgreement(AccessAgreement {
+        terms: Some("terms".to_string()),
+        ..agreement(
+            address(&owner),
+            address(&user),
+            &[("Page", genesis_of(&page))],
+        )
+    });
+    let mut chain = signed_contract(&state, &offer, &owner);
+    add_chain(&state, "Agreement", &chain).await;
+
+    let explanation = state.explain_access(address(&user), latest_of(&page), address(&owner));
+    assert!(!explanation.is_granted());
+    assert_eq!(
+        explanation.contracts[0].effect,
+        Some(AccessAgreementEffects::Offered)
+    );
+    assert!(explanation.denials.contains(&Denial::OnlyOffered));
+
+    let accepted = guardian::contract_generation::sign_revision(chain.last().unwrap(), &user);
+    chain.push(accepted);
+    add_chain(&state, "Agreement", &chain).await;
+    let explanation = state.explain_access(address(&user), latest_of(&page), address(&owner));
+    assert_eq!(explanation.granted_by, Some(latest_of(&chain)));
+    // the offer is still explained next to the acceptance
+    let accepted = explanation
+        .contracts
+        .iter()
+        .find(|contract| contract.contract == latest_of(&chain))
+        .unwrap();
+    assert_eq!(accepted.effect, Some(AccessAgreementEffects::Accepted));
+}
@@ -0,0 +1,154 @@
+mod common;
+
+use common::*;
+use contract_interpreter::{AccessAgreement, Contract, ContractKind, ForkResolution};
+use guardian::{explain::Denial, fork::Fork, snapshot::SnapshotNode, GuardianState};
+use local_storage::prelude::*;
+
+const SENDER: ethaddr::Address = ethaddr::Address([1; 20]);
+const RECEIVER: ethaddr::Address = ethaddr::Address([2; 20]);
+
+/// a page forked at 1 (1 <- 2, 1 <- 3) shared by a granted agreement (10 <- 11), and `extra` revisions
+fn state(extra: Vec<SnapshotNode>) -> GuardianState<MemoryStorage> {
+    let agreement = Contract::AccessAgreement(AccessAgreement {
+        sender: SENDER,
+        receiver: RECEIVER.into(),
+        pages: vec![("Page".to_string(), hash(1))],
+        page_pattern: None,
+        terms: None,
+        valid_from: None,
+        valid_until: None,
+        may_reshare: false,
+        template_version: 2,
+    });
+    let mut nodes = vec![
+        node(1, None, None),
+        node(2, Some(1), None),
+        node(3, Some(1), None),
+        node(10, None, Some((agreement.clone(), 0))),
+        node(11, Some(10), Some((agreement, 1))),
+    ];
+    nodes.extend(extra);
+    restore(nodes)
+}
+
+/// a resolution (20 <- 21) of the fork at 1 by `sender`, picking 3
+fn resolution(sender: ethaddr::Address) -> Vec<SnapshotNode> {
+    let resolution = Contract::ForkResolution(ForkResolution {
+        sender,
+        fork: hash(1),
+        tip: hash(3),
+        template_version: 1,
+    });
+    vec![
+        node(20, None, Some((resolution.clone(), 0))),
+        node(21, Some(20), Some((resolution, 1))),
+    ]
+}
+
+#[test]
+fn forks_are_detected() {
+    let state = state(vec![]);
+    assert_eq!(
+        state.forks(),
+        vec![Fork {
+            genesis: hash(1),
+            point: hash(1),
+            tips: vec![hash(2), hash(3)],
+            resolution: None,
+        }]
+    );
+    // shared as before unless refused
+    assert_eq!(
+        state.get_accessible_latests(RECEIVER, SENDER),
+        [hash(2), hash(3)].into()
+    );
+
+    // merged again
+    let merged = self::state(vec![SnapshotNode {
+        merge: Some(hash(3)),
+        ..node(4, Some(2), None)
+    }]);
+    assert!(merged.forks().is_empty());
+}
+
+#[test]
+fn unresolved_forks_are_withheld() {
+    let mut state = state(vec![]);
+    state.refuse_forks = true;
+    assert!(state.get_accessible_latests(RECEIVER, SENDER).is_empty());
+    assert!(state
+        .get_rev_accessible(RECEIVER, hash(1), SENDER)
+        .is_none());
+    assert!(state
+        .explain_access(RECEIVER, hash(2), SENDER)
+        .denials
+        .contains(&Denial::Forked { fork: hash(1) }));
+
+    // the owner has to resolve it
+    let mut state = self::state(resolution(RECEIVER));
+    state.refuse_forks = true;
+    assert!(state.get_accessible_latests(RECEIVER, SENDER).is_empty());
+}
+
+#[test]
+fn resolved_forks_share_the_picked_branch() {
+    let mut state = state(resolution(SENDER));
+    state.refuse_forks = true;
+    assert_eq!(state.forks()[0].picked_by(SENDER), Some(hash(3)));
+    assert_eq!(
+        state.get_accessible_latests(RECEIVER, SENDER),
+        [hash(3)].into()
+    );
+    assert!(state
+        .get_rev_accessible(RECEIVER, hash(1), SENDER)
+        .is_some());
+    assert!(state
+        .get_rev_accessible(RECEIVER, hash(3), SENDER)
+        .is_some());
+    assert!(state
+        .get_rev_accessible(RECEIVER, hash(2), SENDER)
+        .is_none());
+}
+
+/// a signed resolution of a fork added like the guardian does picks the branch that is shared
+#[tokio::test]
+async fn signed_resolution_picks_the_branch() {
+    let sender = signer(1);
+    let receiver = address(&signer(2));
+    let mut state = GuardianState::new(MemoryStorage::new());
+    state.refuse_forks = true;
+    // the template is not published, so its hash comes from the registry
+    state
+        .templates
+        .write()
+        .insert(ContractKind::ForkResolution, 1, hash(99));
+    let genesis = revision("a", None, None);
+    let left = revision("b", Some(&genesis), None);
+    let right = revision("c", Some(&genesis), None);
+    add_chain(&state, "Page", &[genesis.clone(), left.clone()]).await;
+    add_chain(&state, "Page", &[genesis.clone(), right.clone()]).await;
+    let shared = agreement(
+        address(&sender),
+        receiver,
+        &[("Page", genesis.metadata.verification_hash)],
+    );
+    let chain = signed_contract(&state, &Contract::AccessAgreement(shared), &sender);
+    add_chain(&state, "Agreement", &chain).await;
+    assert!(state
+        .get_accessible_latests(receiver, address(&sender))
+        .is_empty());
+
+    let resolution = guardian::contract_generation::make_fork_resolution(
+        genesis.metadata.verification_hash,
+        right.metadata.verification_hash,
+        &state.templates.read(),
+        &sender,
+    )
+    .unwrap();
+    add_chain(&state, "Resolution", &resolution).await;
+    assert_eq!(
+        state.get_accessible_latests(receiver, address(&sender)),
+        [right.metadata.verification_hash].into()
+    );
+}
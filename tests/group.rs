@@ -0,0 +1,128 @@
+mod common;
+
+use common::*;
+use contract_interpreter::{Contract, ContractKind, Group, Principal};
+use guardian::{contract_generation::make_group, GuardianState};
+use local_storage::prelude::*;
+
+const OWNER: ethaddr::Address = ethaddr::Address([1; 20]);
+const ALICE: ethaddr::Address = ethaddr::Address([2; 20]);
+const BOB: ethaddr::Address = ethaddr::Address([3; 20]);
+
+fn group(members: &[ethaddr::Address]) -> Contract {
+    Contract::Group(Group {
+        owner: OWNER,
+        members: members.to_vec(),
+        template_version: 1,
+    })
+}
+
+/// a page (1 <- 2) shared by a granted agreement (10 <- 11) with the group 20, whose revisions are `revisions`
+fn state(revisions: Vec<(Contract, u8)>) -> GuardianState<MemoryStorage> {
+    let agreement = Contract::AccessAgreement(agreement(
+        OWNER,
+        Principal::Group(hash(20)),
+        &[("Page", hash(1))],
+    ));
+    let mut nodes = vec![
+        node(1, None, None),
+        node(2, Some(1), None),
+        node(10, None, Some((agreement.clone(), 0))),
+        node(11, Some(10), Some((agreement, 1))),
+    ];
+    for (n, revision) in (20..).zip(revisions) {
+        nodes.push(node(n, (n > 20).then_some(n - 1), Some(revision)));
+    }
+    restore(nodes)
+}
+
+#[test]
+fn members_get_access() {
+    let state = state(vec![(group(&[ALICE]), 0), (group(&[ALICE]), 1)]);
+    assert_eq!(state.group_members(hash(20)), vec![ALICE]);
+    assert_eq!(state.groups_of(ALICE), vec![hash(20)]);
+    assert!(state.get_rev_accessible(ALICE, hash(2), OWNER).is_some());
+    assert_eq!(state.get_accessible_latests(ALICE, OWNER), [hash(2)].into());
+    assert!(state.get_rev_accessible(BOB, hash(2), OWNER).is_none());
+    assert!(state.get_accessible_latests(BOB, OWNER).is_empty());
+}
+
+#[test]
+fn unsigned_groups_share_nothing() {
+    let state = state(vec![(group(&[ALICE]), 0)]);
+    assert!(state.group_members(hash(20)).is_empty());
+    assert!(state.get_rev_accessible(ALICE, hash(2), OWNER).is_none());
+}
+
+#[test]
+fn membership_changes_without_resigning() {
+    let signed = vec![(group(&[ALICE]), 0), (group(&[ALICE]), 1)];
+
+    // pending until the owner signs
+    let mut revisions = signed.clone();
+    revisions.push((group(&[BOB]), 2));
+    let state = self::state(revisions.clone());
+    assert_eq!(state.group_members(hash(20)), vec![ALICE]);
+    assert!(state.get_rev_accessible(ALICE, hash(2), OWNER).is_some());
+    assert!(state.get_rev_accessible(BOB, hash(2), OWNER).is_none());
+
+    revisions.push((group(&[BOB]), 1));
+    let state = self::state(revisions);
+    assert_eq!(state.group_members(hash(20)), vec![BOB]);
+    assert!(state.get_rev_accessible(ALICE, hash(2), OWNER).is_none());
+    assert!(state.get_rev_accessible(BOB, hash(2), OWNER).is_some());
+
+    // taking the change back
+    drop(state.rm(hash(23)));
+    assert_eq!(state.group_members(hash(20)), vec![ALICE]);
+    assert!(state.get_rev_accessible(ALICE, hash(2), OWNER).is_some());
+}
+
+#[test]
+fn receiver_is_a_user_or_a_group() {
+    let user: Principal = "0x0202020202020202020202020202020202020202"
+        .parse()
+        .unwrap();
+    assert_eq!(user, ALICE);
+    let group: Principal = hash(20).to_string().parse().unwrap();
+    assert_eq!(group, Principal::Group(hash(20)));
+    assert!("neither".parse::<Principal>().is_err());
+
+    for principal in [user, group] {
+        let json = serde_json::to_string(&principal).unwrap();
+        assert_eq!(serde_json::from_str::<Principal>(&json).unwrap(), principal);
+    }
+}
+
+#[tokio::test]
+async fn signed_group_shares_with_its_members() {
+    let (owner, alice, bob) = (signer(1), signer(2), signer(3));
+    let state = GuardianState::new(MemoryStorage::new());
+    // the template is not published yet
+    state
+        .templates
+        .write()
+        .insert(ContractKind::Group, 1, hash(99));
+    let page = page(&["a", "b"]);
+    add_chain(&state, "Page", &page).await;
+    let group = make_group(vec![address(&alice)], &state.templates.read(), &owner).unwrap();
+    add_chain(&state, "Group", &group).await;
+    let agreement = Contract::AccessAgreement(agreement(
+        address(&owner),
+        Principal::Group(genesis_of(&group)),
+        &[("Page", genesis_of(&page))],
+    ));
+    let chain = signed_contract(&state, &agreement, &owner);
+    add_chain(&state, "Agreement", &chain).await;
+
+    assert_eq!(
+        state.group_members(genesis_of(&group)),
+        vec![address(&alice)]
+    );
+    assert!(state
+        .get_rev_accessible(address(&alice), latest_of(&page), address(&owner))
+        .is_some());
+    assert!(state
+        .get_rev_accessible(address(&bob), latest_of(&page), address(&owner))
+        .is_none());
+}
@@ -0,0 +1,77 @@
+mod common;
+
+use std::sync::Arc;
+
+use common::*;
+use contract_interpreter::Contract;
+use guardian::{
+    audit::{self, AuditLog, AuditOutcome, AuditRequest},
+    contract_generation::{make_guardian_cert, make_guardian_servitude, sign_revision},
+    handler::Handler,
+    GuardianState,
+};
+use guardian_api::ApiHandler;
+use local_storage::prelude::*;
+use webpki::types::CertificateDer;
+
+/// a remote guardian known by its certificate, serving the user `remote_user`
+async fn remote_guardian(
+    state: &GuardianState<MemoryStorage>,
+    remote_user: &guardian_common::signing::SimpleSigner,
+) -> CertificateDer<'static> {
+    let remote = signer(3);
+    let key_pair = rcgen::KeyPair::generate().unwrap();
+    let (cert, claim) = make_guardian_cert(key_pair, [127, 0, 0, 1].into(), 8080, &remote).unwrap();
+    add_chain(state, "Identity", &claim).await;
+    let mut servitude = make_guardian_servitude(address(remote_user), &remote);
+    let accepted = sign_revision(servitude.last().unwrap(), remote_user);
+    servitude.push(accepted);
+    add_chain(state, "Servitude", &servitude).await;
+    cert.der().clone()
+}
+
+/// the agreements of a served user besides the admin are handed out as well, and audited as such
+#[tokio::test]
+async fn serves_the_agreements_of_every_served_user() {
+    let (admin, member, remote_user) = (signer(1), signer(2), signer(4));
+    let state = Arc::new(GuardianState::new(MemoryStorage::new()));
+    let cert = remote_guardian(&state, &remote_user).await;
+    let page = page(&["a", "b"]);
+    add_chain(&state, "Page", &page).await;
+    let agreement = Contract::AccessAgreement(agreement(
+        address(&member),
+        address(&remote_user),
+        &[("Page", genesis_of(&page))],
+    ));
+    let chain = signed_contract(&state, &agreement, &member);
+    add_chain(&state, "Agreement", &chain).await;
+
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("audit.jsonl");
+    let handler = Handler {
+        state: state.clone(),
+        cert,
+        owners: vec![address(&admin)],
+        audit: Arc::new(AuditLog::open(&path).unwrap()),
+    };
+    // only the admin's agreements
+    assert!(!handler.list().await.unwrap().contains(&latest_of(&page)));
+    assert!(handler.get_revision(latest_of(&page)).await.is_err());
+
+    let handler = Handler {
+        owners: vec![address(&admin), address(&member)],
+        ..handler
+    };
+    assert!(handler.list().await.unwrap().contains(&latest_of(&page)));
+    let branch = handler.get_branch(latest_of(&page)).await.unwrap();
+    assert_eq!(branch.hashes[0], latest_of(&page));
+    let rev = handler.get_revision(latest_of(&page)).await.unwrap();
+    assert_eq!(rev.metadata.verification_hash, latest_of(&page));
+
+    let entries = audit::read(&path).unwrap();
+    let last = entries.last().unwrap();
+    assert_eq!(last.user, Some(address(&remote_user)));
+    assert_eq!(last.request, AuditRequest::GetRevision(latest_of(&page)));
+    assert_eq!(last.outcome, AuditOutcome::Allowed);
+    assert_eq!(last.contracts, vec![latest_of(&chain)]);
+}
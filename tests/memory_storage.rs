@@ -0,0 +1,82 @@
+use guardian::GuardianState;
+use guardian_common::{
+    custom_types::*,
+    signing::{Signer, SimpleSigner},
+};
+use local_storage::prelude::*;
+
+fn context(genesis_hash: Hash) -> RevContext {
+    RevContext {
+        namespace: 0,
+        name: "Guardian_Servitude".to_string(),
+        genesis_hash,
+        domain_id: "42".to_string(),
+    }
+}
+
+/// revisions stored in one task end up in the state through the update handler running in another
+#[tokio::test]
+async fn state_follows_memory_storage() {
+    let signer: SimpleSigner = "0x284750bbd0425ce597494511b7a4d579d0b366633af7584050610d64971141a7"
+        .parse()
+        .unwrap();
+    let guardian: ethaddr::Address = signer.identity().into();
+    let user = ethaddr::Address([1; 20]);
+    let chain = guardian::contract_generation::make_guardian_servitude(user, signer);
+    let genesis = chain[0].metadata.verification_hash;
+    let hashes: Vec<Hash> = chain
+        .iter()
+        .map(|rev| rev.metadata.verification_hash)
+        .collect();
+
+    let storage = MemoryStorage::new();
+    let state = GuardianState::new(storage.clone());
+
+    let (sendr, mut recvr) = tokio::sync::mpsc::unbounded_channel();
+    let watcher = storage.clone();
+    tokio::spawn(async move {
+        watcher
+            .update_handler(move |hash, event| {
+                sendr.send((hash, event)).unwrap();
+            })
+            .await
+    });
+
+    let writer = storage.clone();
+    tokio::spawn(async move {
+        for rev in chain {
+            writer.store(rev, context(genesis)).await.unwrap();
+        }
+    });
+
+    for expected in hashes.iter().copied() {
+        let (hash, event) = recvr.recv().await.unwrap();
+        assert_eq!((hash, event), (expected, StorageEvent::Created));
+        let revision = storage.read(hash).await.unwrap();
+        state.add(hash, revision).await.unwrap();
+    }
+
+    let latest = state.get_node(&hashes[1]).unwrap();
+    assert_eq!(latest.prev.upgrade().unwrap().hash, genesis);
+    // signed by the guardian only, the user still has to accept it
+    let effect = &latest
+        .contract
+        .as_ref()
+        .unwrap()
+        .effective
+        .as_ref()
+        .unwrap()
+        .effect;
+    assert!(matches!(
+        effect,
+        contract_interpreter::ContractEffect::GuardianServitude((
+            contract_interpreter::GuardianServitude {
+                guardian: g,
+                user: u,
+                ..
+            },
+            contract_interpreter::GuardianServitudeEffects::Declared,
+        )) if *g == guardian && *u == user
+    ));
+    assert!(state.guardian_users(guardian).is_empty());
+}
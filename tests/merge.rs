@@ -0,0 +1,178 @@
+mod common;
+
+use common::*;
+use contract_interpreter::{AccessAgreement, Contract};
+use guardian::{snapshot::SnapshotNode, GuardianState};
+use guardian_common::custom_types::*;
+use local_storage::prelude::*;
+
+const SENDER: ethaddr::Address = ethaddr::Address([1; 20]);
+const RECEIVER: ethaddr::Address = ethaddr::Address([2; 20]);
+
+/// a page (1 <- 2 <- 3, 1 <- 4, 3 + 4 <- 5) with its branch 4 shared by a granted agreement (10 <- 11)
+fn state() -> GuardianState<MemoryStorage> {
+    let agreement = Contract::AccessAgreement(AccessAgreement {
+        sender: SENDER,
+        receiver: RECEIVER.into(),
+        pages: vec![("Page".to_string(), hash(4))],
+        page_pattern: None,
+        terms: None,
+        valid_from: None,
+        valid_until: None,
+        may_reshare: false,
+        template_version: 2,
+    });
+    restore(vec![
+        node(1, None, None),
+        node(2, Some(1), None),
+        node(3, Some(2), None),
+        node(4, Some(1), None),
+        SnapshotNode {
+            merge: Some(hash(4)),
+            ..node(5, Some(3), None)
+        },
+        node(10, None, Some((agreement.clone(), 0))),
+        node(11, Some(10), Some((agreement, 1))),
+    ])
+}
+
+#[test]
+fn merge_joins_branches() {
+    let state = state();
+    let merge = state.get_node(&hash(5)).unwrap();
+    let mut parents: Vec<Hash> = merge.parents().map(|parent| parent.hash).collect();
+    parents.sort();
+    assert_eq!(parents, vec![hash(3), hash(4)]);
+    assert!(state
+        .get_node(&hash(3))
+        .unwrap()
+        .leafs
+        .contains_key(&hash(5)));
+    assert!(state
+        .get_node(&hash(4))
+        .unwrap()
+        .leafs
+        .contains_key(&hash(5)));
+
+    // shared through the merged branch, the merge is the only latest
+    assert!(state
+        .get_rev_accessible(RECEIVER, hash(5), SENDER)
+        .is_some());
+    assert_eq!(
+        state.get_accessible_latests(RECEIVER, SENDER),
+        [hash(5)].into()
+    );
+
+    // every ancestor once, each before its parents
+    let branch = state
+        .get_accessible_branch(RECEIVER, hash(5), SENDER)
+        .unwrap();
+    assert_eq!(branch.len(), 5);
+    assert_eq!((branch[0], branch[4]), (hash(5), hash(1)));
+    let position = |n: u8| branch.iter().position(|h| *h == hash(n)).unwrap();
+    assert!(position(3) < position(2));
+}
+
+#[test]
+fn merge_survives_snapshot() {
+    let state = state();
+    let snapshot = state.snapshot(0);
+    let position = |n: u8| {
+        snapshot
+            .nodes
+            .iter()
+            .position(|node| node.hash == hash(n))
+            .unwrap()
+    };
+    assert!(position(5) > position(3) && position(5) > position(4));
+    assert_eq!(snapshot.nodes.len(), 7);
+
+    let (restored, _) = GuardianState::restore(MemoryStorage::new(), snapshot);
+    assert_eq!(
+        restored.get_accessible_latests(RECEIVER, SENDER),
+        [hash(5)].into()
+    );
+}
+
+#[test]
+fn rm_merge_restores_latests() {
+    let state = state();
+    drop(state.rm(hash(5)));
+    assert_eq!(
+        state.get_accessible_latests(RECEIVER, SENDER),
+        [hash(4)].into()
+    );
+}
+
+#[tokio::test]
+async fn add_brings_merged_branch() {
+    let genesis = revision("a", None, None);
+    let left = revision("b", Some(&genesis), None);
+    let right = revision("c", Some(&genesis), None);
+    let merge = revision("d", Some(&left), Some(&right));
+    let hashes: Vec<Hash> = [&genesis, &left, &right, &merge]
+        .iter()
+        .map(|rev| rev.metadata.verification_hash)
+        .collect();
+
+    let storage = MemoryStorage::new();
+    for rev in [&genesis, &left, &right, &merge] {
+        storage
+            .store(rev.clone(), context("Main_Page", hashes[0]))
+            .await
+            .unwrap();
+    }
+    let state = GuardianState::new(storage.clone());
+
+    // the merged revision is not there yet
+    assert!(matches!(
+        state.add(hashes[3], merge.clone()).await,
+        Err(guardian::Error::PrevNotInState)
+    ));
+    let added = state.add_with_ancestors(hashes[3]).await.unwrap();
+    assert_eq!(added.len(), 4);
+    assert_eq!(added[0].hash, hashes[0]);
+    assert_eq!(added[3].hash, hashes[3]);
+    assert_eq!(added[3].merge.upgrade().unwrap().hash, hashes[2]);
+
+    // the merge is part of what is hashed
+    let mut forged = merge.clone();
+    forged.metadata.merge_verification_hash = None;
+    let state = GuardianState::new(storage);
+    state.add_with_ancestors(hashes[1]).await.unwrap();
+    assert!(matches!(
+        state.add(hashes[3], forged).await,
+        Err(guardian::Error::Verifier(_))
+    ));
+}
+
+/// a page shared by a signed agreement ends at the merge of its branches once it is added like the guardian does
+#[tokio::test]
+async fn signed_agreement_shares_the_merge() {
+    let sender = signer(1);
+    let receiver = address(&signer(2));
+    let state = GuardianState::new(MemoryStorage::new());
+    let genesis = revision("a", None, None);
+    let left = revision("b", Some(&genesis), None);
+    let right = revision("c", Some(&genesis), None);
+    let merge = revision("d", Some(&left), Some(&right));
+    add_chain(&state, "Page", &[genesis.clone(), right.clone()]).await;
+    let shared = agreement(
+        address(&sender),
+        receiver,
+        &[("Page", genesis.metadata.verification_hash)],
+    );
+    let chain = signed_contract(&state, &Contract::AccessAgreement(shared), &sender);
+    add_chain(&state, "Agreement", &chain).await;
+    assert_eq!(
+        state.get_accessible_latests(receiver, address(&sender)),
+        [right.metadata.verification_hash].into()
+    );
+
+    let added = add_chain(&state, "Page", &[genesis, left, merge.clone()]).await;
+    assert_eq!(added.len(), 2);
+    assert_eq!(
+        state.get_accessible_latests(receiver, address(&sender)),
+        [merge.metadata.verification_hash].into()
+    );
+}
@@ -0,0 +1,111 @@
+mod common;
+
+use common::*;
+use contract_interpreter::{AccessAgreement, Contract};
+use guardian::{page_pattern::Page, snapshot::SnapshotNode, GuardianState};
+use local_storage::prelude::*;
+
+const OWNER: ethaddr::Address = ethaddr::Address([1; 20]);
+const AUDITOR: ethaddr::Address = ethaddr::Address([2; 20]);
+
+fn titled(title: &str) -> Option<Page> {
+    Some(Page {
+        namespace: 0,
+        title: title.to_string(),
+    })
+}
+
+/// the genesis `n` of the page `title`
+fn genesis(n: u8, contract: Option<(Contract, u8)>, title: &str) -> SnapshotNode {
+    SnapshotNode {
+        page: titled(title),
+        ..node(n, None, contract)
+    }
+}
+
+/// pages under "Audit/" (1 <- 2) and elsewhere (3), a granted agreement (10 <- 11) for "0:Audit/", and a page under
+/// "Audit/" created after it (5)
+fn state() -> GuardianState<MemoryStorage> {
+    let agreement = Contract::AccessAgreement(AccessAgreement {
+        page_pattern: Some("0:Audit/".parse().unwrap()),
+        ..agreement(OWNER, AUDITOR, &[])
+    });
+    restore(vec![
+        genesis(1, None, "Audit/One"),
+        node(2, Some(1), None),
+        genesis(3, None, "Other"),
+        genesis(10, Some((agreement.clone(), 0)), "Agreement"),
+        node(11, Some(10), Some((agreement, 1))),
+        genesis(5, None, "Audit/Two"),
+    ])
+}
+
+#[test]
+fn matching_pages_are_shared() {
+    let state = state();
+    assert_eq!(
+        state.get_accessible_latests(AUDITOR, OWNER),
+        [hash(2), hash(5)].into()
+    );
+    assert!(state.get_rev_accessible(AUDITOR, hash(1), OWNER).is_some());
+    assert!(state.get_rev_accessible(AUDITOR, hash(3), OWNER).is_none());
+
+    // the page survives a snapshot
+    let snapshot = state.snapshot(0);
+    let restored = GuardianState::restore(MemoryStorage::new(), snapshot).0;
+    assert_eq!(
+        restored.get_accessible_latests(AUDITOR, OWNER),
+        [hash(2), hash(5)].into()
+    );
+}
+
+#[test]
+fn moved_pages_follow_the_pattern() {
+    let state = state();
+    state.move_page(hash(1), titled("Archive/One").unwrap());
+    state.move_page(hash(3), titled("Audit/Three").unwrap());
+    assert!(state.get_rev_accessible(AUDITOR, hash(2), OWNER).is_none());
+    assert!(state.get_rev_accessible(AUDITOR, hash(3), OWNER).is_some());
+    assert_eq!(
+        state.get_accessible_latests(AUDITOR, OWNER),
+        [hash(3), hash(5)].into()
+    );
+}
+
+/// the page of a genesis comes from the context in the storage
+#[tokio::test]
+async fn page_is_taken_from_the_context() {
+    let signer = signer(1);
+    let chain = guardian::contract_generation::make_guardian_servitude(address(&signer), &signer);
+    let state = GuardianState::new(MemoryStorage::new());
+    let added = add_chain(&state, "Audit/Servitude", &chain).await;
+    assert_eq!(state.page_of(&added[1]), titled("Audit/Servitude"));
+    assert_eq!(*added[1].page.read(), None);
+}
+
+#[tokio::test]
+async fn signed_pattern_shares_matching_pages() {
+    let (owner, auditor) = (signer(1), signer(2));
+    let state = GuardianState::new(MemoryStorage::new());
+    let audited = page(&["a", "b"]);
+    add_chain(&state, "Audit/One", &audited).await;
+    let other = page(&["c"]);
+    add_chain(&state, "Other", &other).await;
+    let agreement = Contract::AccessAgreement(AccessAgreement {
+        page_pattern: Some("0:Audit/".parse().unwrap()),
+        ..agreement(address(&owner), address(&auditor), &[])
+    });
+    let chain = signed_contract(&state, &agreement, &owner);
+    add_chain(&state, "Agreement", &chain).await;
+    // created after the agreement
+    let later = page(&["d"]);
+    add_chain(&state, "Audit/Two", &later).await;
+
+    assert_eq!(
+        state.get_accessible_latests(address(&auditor), address(&owner)),
+        [latest_of(&audited), latest_of(&later)].into()
+    );
+    assert!(state
+        .get_rev_accessible(address(&auditor), latest_of(&other), address(&owner))
+        .is_none());
+}
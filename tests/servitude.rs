@@ -0,0 +1,115 @@
+mod common;
+
+use common::*;
+use contract_interpreter::{Contract, GuardianServitude};
+use guardian::{
+    contract_generation::{make_guardian_servitude, sign_revision},
+    GuardianState,
+};
+use local_storage::prelude::*;
+
+const GUARDIAN: ethaddr::Address = ethaddr::Address([1; 20]);
+const OTHER_GUARDIAN: ethaddr::Address = ethaddr::Address([9; 20]);
+
+/// accepted servitudes of [`GUARDIAN`] for each of `users`
+fn state(users: &[ethaddr::Address]) -> GuardianState<MemoryStorage> {
+    let servitudes: Vec<_> = users.iter().map(|user| (GUARDIAN, *user)).collect();
+    state_of(&servitudes)
+}
+
+/// accepted servitudes of each guardian for its user, the one at `n` made of the revisions `hash(n * 10..n * 10 + 3)`
+fn state_of(servitudes: &[(ethaddr::Address, ethaddr::Address)]) -> GuardianState<MemoryStorage> {
+    let mut nodes = vec![];
+    for (n, (guardian, user)) in (0..).zip(servitudes) {
+        let servitude = Contract::GuardianServitude(GuardianServitude {
+            guardian: *guardian,
+            user: *user,
+            template_version: 1,
+        });
+        for seqno in 0..3 {
+            let prev = (seqno > 0).then(|| n * 10 + seqno - 1);
+            nodes.push(node(n * 10 + seqno, prev, Some((servitude.clone(), seqno))));
+        }
+    }
+    restore(nodes)
+}
+
+#[test]
+fn serves_several_users() {
+    let (a, b) = (ethaddr::Address([2; 20]), ethaddr::Address([3; 20]));
+    let state = state(&[b, a, a]);
+    assert_eq!(state.guardian_users(GUARDIAN), vec![a, b]);
+    assert!(!state.is_guardian_poisoned(GUARDIAN));
+
+    // gone with the servitude
+    drop(state.rm(hash(2)));
+    assert_eq!(state.guardian_users(GUARDIAN), vec![a]);
+}
+
+#[test]
+fn serving_itself_poisons() {
+    let state = state(&[ethaddr::Address([2; 20]), GUARDIAN]);
+    assert!(state.is_guardian_poisoned(GUARDIAN));
+    assert!(state.guardian_users(GUARDIAN).is_empty());
+}
+
+#[test]
+fn revoking_one_of_two_servitudes_keeps_the_user() {
+    let a = ethaddr::Address([2; 20]);
+    let state = state(&[a, a]);
+    assert_eq!(state.guardian_users(GUARDIAN), vec![a]);
+
+    drop(state.rm(hash(12)));
+    assert_eq!(state.guardian_users(GUARDIAN), vec![a]);
+    drop(state.rm(hash(2)));
+    assert!(state.guardian_users(GUARDIAN).is_empty());
+}
+
+#[test]
+fn two_guardians_for_one_user_serve_them_not() {
+    let (a, b) = (ethaddr::Address([2; 20]), ethaddr::Address([3; 20]));
+    let state = state_of(&[(GUARDIAN, a), (OTHER_GUARDIAN, a), (GUARDIAN, b)]);
+    assert_eq!(state.user_guardians(a), vec![GUARDIAN, OTHER_GUARDIAN]);
+    // only the contested user is dropped, neither guardian is poisoned
+    assert_eq!(state.guardian_users(GUARDIAN), vec![b]);
+    assert!(state.guardian_users(OTHER_GUARDIAN).is_empty());
+    assert!(!state.is_guardian_poisoned(OTHER_GUARDIAN));
+
+    // resolved once one of the servitudes is gone
+    drop(state.rm(hash(12)));
+    assert_eq!(state.guardian_users(GUARDIAN), vec![a, b]);
+}
+
+#[test]
+fn poisoned_guardians_do_not_contest() {
+    let a = ethaddr::Address([2; 20]);
+    let state = state_of(&[
+        (GUARDIAN, a),
+        (OTHER_GUARDIAN, a),
+        (OTHER_GUARDIAN, OTHER_GUARDIAN),
+    ]);
+    assert_eq!(state.user_guardians(a), vec![GUARDIAN]);
+    assert_eq!(state.guardian_users(GUARDIAN), vec![a]);
+}
+
+#[tokio::test]
+async fn signed_servitude_serves_once_accepted() {
+    let (guardian, user) = (signer(1), signer(2));
+    let state = GuardianState::new(MemoryStorage::new());
+    let mut chain = make_guardian_servitude(address(&user), &guardian);
+    add_chain(&state, "Servitude", &chain).await;
+    // only declared by the guardian
+    assert!(state.guardian_users(address(&guardian)).is_empty());
+
+    let accepted = sign_revision(chain.last().unwrap(), &user);
+    chain.push(accepted);
+    add_chain(&state, "Servitude", &chain).await;
+    assert_eq!(
+        state.guardian_users(address(&guardian)),
+        vec![address(&user)]
+    );
+    assert_eq!(
+        state.user_guardians(address(&user)),
+        vec![address(&guardian)]
+    );
+}
@@ -0,0 +1,93 @@
+use guardian::{snapshot::Snapshot, GuardianState};
+use guardian_common::{
+    custom_types::*,
+    signing::{Signer, SimpleSigner},
+};
+use local_storage::prelude::*;
+
+fn context(genesis_hash: Hash) -> RevContext {
+    RevContext {
+        namespace: 0,
+        name: "Snapshot".to_string(),
+        genesis_hash,
+        domain_id: "42".to_string(),
+    }
+}
+
+/// a state restored from a saved snapshot has the same revisions and derived maps, without reading the storage
+#[tokio::test]
+async fn restored_state_matches_snapshotted_state() {
+    let signer: SimpleSigner = "0x284750bbd0425ce597494511b7a4d579d0b366633af7584050610d64971141a7"
+        .parse()
+        .unwrap();
+    let guardian: ethaddr::Address = signer.identity().into();
+    let user = ethaddr::Address([1; 20]);
+    let servitude = guardian::contract_generation::make_guardian_servitude(user, &signer);
+    let (cert, claim) = guardian::contract_generation::make_guardian_cert(
+        rcgen::KeyPair::generate().unwrap(),
+        [127, 0, 0, 1].into(),
+        8080,
+        &signer,
+    )
+    .unwrap();
+
+    let storage = MemoryStorage::new();
+    let state = GuardianState::new(storage.clone());
+    for chain in [servitude, claim] {
+        let genesis = chain[0].metadata.verification_hash;
+        for rev in chain {
+            let hash = rev.metadata.verification_hash;
+            storage.store(rev.clone(), context(genesis)).await.unwrap();
+            state.add(hash, rev).await.unwrap();
+        }
+    }
+    assert_eq!(state.guardian_identity(cert.der()), Some(guardian));
+
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("snapshot.json");
+    state
+        .snapshot(storage.latest_cursor().await.unwrap())
+        .save(&path)
+        .unwrap();
+    let snapshot = Snapshot::<usize>::load(&path).unwrap().unwrap();
+    assert_eq!(snapshot.nodes.len(), 4);
+
+    // nothing to read from, everything has to come from the snapshot
+    let (restored, cursor) = GuardianState::restore(MemoryStorage::new(), snapshot);
+    assert_eq!(cursor, 4);
+
+    let mut hashes: Vec<Hash> = state.genesis_map.iter().map(|e| *e.key()).collect();
+    let mut restored_hashes: Vec<Hash> = restored.genesis_map.iter().map(|e| *e.key()).collect();
+    hashes.sort();
+    restored_hashes.sort();
+    assert_eq!(hashes, restored_hashes);
+
+    for entry in state.genesis_map.iter() {
+        let mut node = entry.value().clone();
+        loop {
+            let other = restored.get_node(&node.hash).unwrap();
+            assert_eq!(
+                node.prev.upgrade().map(|prev| prev.hash),
+                other.prev.upgrade().map(|prev| prev.hash)
+            );
+            let effect = |node: &guardian::StateNode| {
+                let info = node.contract.as_ref()?;
+                Some(format!("{:?}", info.effective.as_ref()?.effect))
+            };
+            assert_eq!(effect(&node), effect(&other));
+            let Some(leaf) = node.leafs.iter().map(|leaf| leaf.value().clone()).next() else {
+                break;
+            };
+            node = leaf;
+        }
+    }
+    assert_eq!(restored.guardian_identity(cert.der()), Some(guardian));
+    assert!(restored.guardian_users(guardian).is_empty());
+}
+
+#[test]
+fn missing_snapshot_is_none() {
+    let dir = tempfile::tempdir().unwrap();
+    let snapshot = Snapshot::<usize>::load(dir.path().join("snapshot.json")).unwrap();
+    assert!(snapshot.is_none());
+}
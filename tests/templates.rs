@@ -0,0 +1,244 @@
+use contract_interpreter::{Contract, ContractKind, ContractParseError};
+use guardian::GuardianState;
+use guardian_common::custom_types::*;
+use local_storage::prelude::*;
+
+fn context(namespace: i32, name: &str, genesis_hash: Hash) -> RevContext {
+    RevContext {
+        namespace,
+        name: name.to_string(),
+        genesis_hash,
+        domain_id: "42".to_string(),
+    }
+}
+
+fn revision(content: &[(&str, String)]) -> Revision {
+    revision_after(None, content)
+}
+
+fn revision_after(prev: Option<Hash>, content: &[(&str, String)]) -> Revision {
+    use verifier::v1_1::hashes::*;
+    let content: std::collections::BTreeMap<String, String> = content
+        .iter()
+        .map(|(key, value)| (key.to_string(), value.clone()))
+        .collect();
+    let content_hash = content_hash(&content);
+    let time_stamp: Timestamp = "20240601000000".parse().unwrap();
+    let metadata_hash = metadata_hash("42", &time_stamp, prev.as_ref(), None);
+    Revision {
+        content: RevisionContent {
+            file: None,
+            content,
+            content_hash,
+        },
+        metadata: RevisionMetadata {
+            domain_id: "42".to_string(),
+            time_stamp,
+            previous_verification_hash: prev,
+            merge_verification_hash: None,
+            metadata_hash,
+            verification_hash: verification_hash(&content_hash, &metadata_hash, None, None),
+        },
+        signature: None,
+        witness: None,
+    }
+}
+
+/// an edited template page `name` and a contract made with it, transcluding `pages` besides the template
+async fn storage_of(
+    name: &str,
+    main: String,
+    pages: &[(&str, Hash)],
+) -> (MemoryStorage, (Hash, Revision), (Hash, Revision)) {
+    let template = revision(&[("main", "edited".to_string())]);
+    let template_hash = template.metadata.verification_hash;
+    let mut transclusions = vec![format!(
+        r#"{{"dbkey":"{name}","ns":10,"verification_hash":"{template_hash}"}}"#
+    )];
+    for (page, hash) in pages {
+        transclusions.push(format!(
+            r#"{{"dbkey":"{page}","ns":0,"verification_hash":"{hash}"}}"#
+        ));
+    }
+    let transclusions = format!("[{}]", transclusions.join(","));
+    let contract = revision(&[("main", main), ("transclusion-hashes", transclusions)]);
+    let contract_hash = contract.metadata.verification_hash;
+
+    let storage = MemoryStorage::new();
+    storage
+        .store(template.clone(), context(10, name, template_hash))
+        .await
+        .unwrap();
+    storage
+        .store(contract.clone(), context(0, "Contract", contract_hash))
+        .await
+        .unwrap();
+    (
+        storage,
+        (template_hash, template),
+        (contract_hash, contract),
+    )
+}
+
+/// an edited "Template:ForkResolution" page and a fork resolution made with it
+async fn storage() -> (MemoryStorage, (Hash, Revision), (Hash, Revision)) {
+    let main = format!(
+        "{{{{ForkResolution\n|sender={}\n|fork={}\n|tip={}\n}}}}",
+        ethaddr::Address([1; 20]),
+        Hash::from([2; 64]),
+        Hash::from([3; 64]),
+    );
+    storage_of("ForkResolution", main, &[]).await
+}
+
+#[tokio::test]
+async fn discovered_templates_are_recognized() {
+    let (storage, (template_hash, _), (contract_hash, contract)) = storage().await;
+    let state = GuardianState::new(storage.clone());
+
+    let unknown = state.add(contract_hash, contract.clone()).await;
+    let Err(guardian::Error::ContractInterpreter(invalid)) = unknown else {
+        panic!("unknown template version accepted");
+    };
+    assert!(matches!(
+        &invalid.error,
+        ContractParseError::UnknownContractHash {
+            template,
+            hash,
+        } if template == "ForkResolution" && *hash == template_hash
+    ));
+
+    let latests = storage.list().await.unwrap();
+    assert_eq!(state.discover_templates(&latests, 4).await.unwrap(), 1);
+    let node = state.add(contract_hash, contract).await.unwrap();
+    assert!(node.contract.is_some());
+}
+
+#[tokio::test]
+async fn added_templates_are_registered() {
+    let (storage, (template_hash, template), _) = storage().await;
+    let state = GuardianState::new(storage);
+    assert_eq!(state.templates.read().kind_of(template_hash), None);
+
+    state.add(template_hash, template).await.unwrap();
+    assert_eq!(
+        state.templates.read().kind_of(template_hash),
+        Some(ContractKind::ForkResolution)
+    );
+}
+
+#[tokio::test]
+async fn data_access_agreement_template_is_discovered() {
+    let main = format!(
+        "{{{{DataAccessAgreement\n|sender={}\n|receiver={}\n|pages=Main_Page\n}}}}",
+        ethaddr::Address([1; 20]),
+        ethaddr::Address([2; 20]),
+    );
+    let (storage, (template_hash, _), (contract_hash, contract)) = storage_of(
+        "DataAccessAgreement",
+        main,
+        &[("Main_Page", Hash::from([4; 64]))],
+    )
+    .await;
+    let state = GuardianState::new(storage.clone());
+
+    let latests = storage.list().await.unwrap();
+    assert_eq!(state.discover_templates(&latests, 4).await.unwrap(), 1);
+    assert_eq!(
+        state.templates.read().version_of(template_hash),
+        Some((ContractKind::AccessAgreement, 2))
+    );
+    let node = state.add(contract_hash, contract).await.unwrap();
+    assert!(node.contract.is_some());
+}
+
+#[tokio::test]
+async fn older_template_revisions_keep_their_version() {
+    // the template as it was with `files=`, and edited to the current version
+    let old = revision(&[("main", "files".to_string())]);
+    let old_hash = old.metadata.verification_hash;
+    let current = revision_after(Some(old_hash), &[("main", "pages".to_string())]);
+    let current_hash = current.metadata.verification_hash;
+    let transclusions = format!(
+        r#"[{{"dbkey":"DataAccessAgreement","ns":10,"verification_hash":"{old_hash}"}},{{"dbkey":"Main_Page","ns":0,"verification_hash":"{}"}}]"#,
+        Hash::from([4; 64]),
+    );
+    let main = format!(
+        "{{{{DataAccessAgreement\n|sender={}\n|receiver={}\n|files=Main_Page\n}}}}",
+        ethaddr::Address([1; 20]),
+        ethaddr::Address([2; 20]),
+    );
+    let contract = revision(&[("main", main), ("transclusion-hashes", transclusions)]);
+    let contract_hash = contract.metadata.verification_hash;
+
+    let storage = MemoryStorage::new();
+    let template_context = context(10, "DataAccessAgreement", old_hash);
+    storage
+        .store(old.clone(), template_context.clone())
+        .await
+        .unwrap();
+    storage
+        .store(current.clone(), template_context)
+        .await
+        .unwrap();
+    storage
+        .store(contract.clone(), context(0, "Agreement", contract_hash))
+        .await
+        .unwrap();
+    let state = GuardianState::new(storage.clone());
+
+    let latests = storage.list().await.unwrap();
+    assert_eq!(state.discover_templates(&latests, 4).await.unwrap(), 1);
+    state.add(old_hash, old).await.unwrap();
+    state.add(current_hash, current).await.unwrap();
+    let templates = state.templates.read().clone();
+    assert_eq!(templates.version_of(old_hash), None);
+    assert_eq!(
+        templates.version_of(current_hash),
+        Some((ContractKind::AccessAgreement, 2))
+    );
+
+    // the older version is configured
+    state
+        .templates
+        .write()
+        .insert(ContractKind::AccessAgreement, 1, old_hash);
+    let node = state.add(contract_hash, contract).await.unwrap();
+    let Some(Contract::AccessAgreement(agreement)) = node.contract.as_ref().map(|info| &info.data)
+    else {
+        panic!("v1 agreement not recognized");
+    };
+    assert_eq!(agreement.template_version, 1);
+    assert_eq!(agreement.pages.len(), 1);
+}
+
+/// a page using some other "Template:Group" is no contract, unless Group contracts are configured
+#[tokio::test]
+async fn unrelated_group_template_is_no_contract() {
+    let main = "{{Group\n|name=Friends\n}}".to_string();
+    let (storage, (template_hash, template), (contract_hash, contract)) =
+        storage_of("Group", main, &[]).await;
+    let state = GuardianState::new(storage.clone());
+
+    let latests = storage.list().await.unwrap();
+    assert_eq!(state.discover_templates(&latests, 4).await.unwrap(), 0);
+    state.add(template_hash, template).await.unwrap();
+    assert_eq!(state.templates.read().kind_of(template_hash), None);
+    let node = state.add(contract_hash, contract.clone()).await.unwrap();
+    assert!(node.contract.is_none());
+
+    let state = GuardianState::new(storage);
+    state
+        .templates
+        .write()
+        .insert(ContractKind::Group, 1, Hash::from([9; 64]));
+    let unknown = state.add(contract_hash, contract).await;
+    let Err(guardian::Error::ContractInterpreter(invalid)) = unknown else {
+        panic!("unknown version of a configured template accepted");
+    };
+    assert!(matches!(
+        &invalid.error,
+        ContractParseError::UnknownContractHash { template, hash }
+            if template == "Group" && *hash == template_hash
+    ));
+}
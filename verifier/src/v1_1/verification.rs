@@ -17,6 +17,7 @@ pub fn metadata_hash(
     domain_id: &str,
     time_stamp: &Timestamp,
     previous_verification_hash: Option<&Hash>,
+    merge_verification_hash: Option<&Hash>,
 ) -> Hash {
     // 4.a create hasher {m}
     let mut m = crypt::Hasher::default();
@@ -28,6 +29,10 @@ pub fn metadata_hash(
     if let Some(prev_verification_hash) = previous_verification_hash {
         m.update(prev_verification_hash.to_stackstr());
     }
+    // 4.d' if rev.metadata.merge_verification_hash exists then add rev.metadata.merge_verification_hash to hasher {m}
+    if let Some(merge_verification_hash) = merge_verification_hash {
+        m.update(merge_verification_hash.to_stackstr());
+    }
     Hash::from(m.finalize())
 }
 
@@ -129,6 +134,7 @@ pub(super) fn only_verification_hash_integrity(
         &rev.metadata.domain_id,
         &rev.metadata.time_stamp,
         rev.metadata.previous_verification_hash.as_ref(),
+        rev.metadata.merge_verification_hash.as_ref(),
     ) != rev.metadata.metadata_hash
     {
         integrity |= MetadataHashNotMatching;